@@ -0,0 +1,385 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStrExt;
+use std::io::{self, Cursor, Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::vfs::{Metadata, Vfs, VfsEntry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarZst,
+}
+
+impl Format {
+    /// Recognizes archives by name; the content is checked when they are opened.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.as_bytes().to_ascii_lowercase();
+        if name.ends_with(b".zip") {
+            Some(Format::Zip)
+        } else if name.ends_with(b".tar") {
+            Some(Format::Tar)
+        } else if name.ends_with(b".tar.gz") || name.ends_with(b".tgz") {
+            Some(Format::TarGz)
+        } else if name.ends_with(b".tar.xz") || name.ends_with(b".txz") {
+            Some(Format::TarXz)
+        } else if name.ends_with(b".tar.zst") || name.ends_with(b".tzst") {
+            Some(Format::TarZst)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    Format::from_path(path).is_some()
+}
+
+/// A member name as a relative path, or `None` when it would escape the
+/// archive: absolute paths and `..` components are refused rather than fixed.
+pub fn safe_relative(name: &Path) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in name.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(relative)
+}
+
+trait ReadSeek: Read + Seek + Send {}
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+#[derive(Debug, Clone, Copy)]
+struct Member {
+    meta: Metadata,
+    /// Position in the archive; `None` for folders that only exist implicitly.
+    index: Option<usize>,
+}
+
+/// The member index of one archive. Contents are read on demand.
+pub struct ArchiveFs {
+    source: Arc<dyn Vfs>,
+    path: PathBuf,
+    format: Format,
+    /// The archive's own metadata when it was indexed, to notice changes.
+    stamp: Metadata,
+    members: BTreeMap<PathBuf, Member>,
+}
+
+impl ArchiveFs {
+    pub fn open(source: Arc<dyn Vfs>, path: &Path) -> io::Result<Self> {
+        let format = Format::from_path(path).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not an archive"))?;
+        let stamp = source.stat(path)?;
+        let mut archive = Self { source, path: path.to_path_buf(), format, stamp, members: BTreeMap::new() };
+        archive.members.insert(PathBuf::new(), Member { meta: Metadata { is_dir: true, len: 0, modified: stamp.modified }, index: None });
+        archive.index()?;
+        Ok(archive)
+    }
+
+    fn reader(&self) -> io::Result<Box<dyn ReadSeek>> {
+        if let Some(local) = self.source.local_path(&self.path) {
+            return Ok(Box::new(std::fs::File::open(local)?));
+        }
+        // Remote archives are fetched whole, since zip needs to seek
+        let mut data = Vec::new();
+        self.source.open_read(&self.path)?.read_to_end(&mut data)?;
+        Ok(Box::new(Cursor::new(data)))
+    }
+
+    fn tar(&self) -> io::Result<tar::Archive<Box<dyn Read>>> {
+        let reader = self.reader()?;
+        let reader: Box<dyn Read> = match self.format {
+            Format::TarGz => Box::new(flate2::read::GzDecoder::new(reader)),
+            Format::TarXz => Box::new(xz2::read::XzDecoder::new(reader)),
+            Format::TarZst => Box::new(zstd::Decoder::new(reader)?),
+            _ => Box::new(reader),
+        };
+        Ok(tar::Archive::new(reader))
+    }
+
+    fn index(&mut self) -> io::Result<()> {
+        let mut found = Vec::new();
+        if self.format == Format::Zip {
+            let mut zip = zip::ZipArchive::new(self.reader()?).map_err(io::Error::other)?;
+            for index in 0..zip.len() {
+                let file = zip.by_index_raw(index).map_err(io::Error::other)?;
+                let modified = file.last_modified().and_then(zip_time);
+                let meta = Metadata { is_dir: file.is_dir(), len: if file.is_dir() { 0 } else { file.size() }, modified };
+                found.push((PathBuf::from(file.name()), meta, index));
+            }
+        } else {
+            for (index, entry) in self.tar()?.entries()?.enumerate() {
+                let entry = entry?;
+                let kind = entry.header().entry_type();
+                if !kind.is_dir() && !kind.is_file() {
+                    continue;
+                }
+                let modified = entry.header().mtime().ok().map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+                let meta = Metadata { is_dir: kind.is_dir(), len: if kind.is_dir() { 0 } else { entry.size() }, modified };
+                found.push((entry.path()?.into_owned(), meta, index));
+            }
+        }
+        for (name, meta, index) in found {
+            let Some(path) = safe_relative(&name).filter(|path| !path.as_os_str().is_empty()) else { continue };
+            for parent in path.ancestors().skip(1) {
+                self.members.entry(parent.to_path_buf()).or_insert(Member { meta: Metadata { is_dir: true, len: 0, modified: None }, index: None });
+            }
+            self.members.insert(path, Member { meta, index: Some(index) });
+        }
+        Ok(())
+    }
+
+    fn member(&self, relative: &Path) -> io::Result<&Member> {
+        self.members.get(relative).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} is not in the archive", relative.display())))
+    }
+
+    pub fn list(&self, relative: &Path) -> io::Result<Vec<VfsEntry>> {
+        if !self.member(relative)?.meta.is_dir {
+            return Err(io::Error::new(io::ErrorKind::NotADirectory, format!("{} is not a directory", relative.display())));
+        }
+        let entries = self
+            .members
+            .range(relative.to_path_buf()..)
+            .skip(1)
+            .take_while(|(path, _)| path.starts_with(relative))
+            .filter(|(path, _)| path.parent() == Some(relative))
+            .map(|(path, member)| VfsEntry { name: path.file_name().map(OsString::from).unwrap_or_default(), metadata: Some(member.meta) })
+            .collect();
+        Ok(entries)
+    }
+
+    pub fn stat(&self, relative: &Path) -> io::Result<Metadata> {
+        Ok(self.member(relative)?.meta)
+    }
+
+    /// Reads a member's contents into memory.
+    pub fn read(&self, relative: &Path) -> io::Result<Vec<u8>> {
+        let member = self.member(relative)?;
+        let Some(index) = member.index.filter(|_| !member.meta.is_dir) else {
+            return Err(io::Error::new(io::ErrorKind::IsADirectory, format!("{} is a directory", relative.display())));
+        };
+        let mut data = Vec::with_capacity(member.meta.len as usize);
+        if self.format == Format::Zip {
+            let mut zip = zip::ZipArchive::new(self.reader()?).map_err(io::Error::other)?;
+            zip.by_index(index).map_err(io::Error::other)?.read_to_end(&mut data)?;
+        } else {
+            // Tar streams can't seek, so walk up to the member
+            let mut tar = self.tar()?;
+            let mut entry = tar.entries()?.nth(index).ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "archive changed while reading"))??;
+            entry.read_to_end(&mut data)?;
+        }
+        Ok(data)
+    }
+}
+
+/// Zip stores local time without a zone.
+fn zip_time(time: zip::DateTime) -> Option<SystemTime> {
+    let date = chrono::NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())?;
+    let local = date.and_hms_opt(time.hour().into(), time.minute().into(), time.second().into())?.and_local_timezone(chrono::Local).earliest()?;
+    Some(local.into())
+}
+
+fn read_only() -> io::Error {
+    io::Error::new(io::ErrorKind::ReadOnlyFilesystem, "archives are read-only")
+}
+
+/// Wraps a filesystem so that archives in it can be entered like folders:
+/// `/a/b.zip/dir/file` is `dir/file` inside `/a/b.zip`. Everything else is
+/// passed through.
+pub struct ArchiveMounts {
+    inner: Arc<dyn Vfs>,
+    mounted: Mutex<HashMap<PathBuf, Arc<ArchiveFs>>>,
+}
+
+impl ArchiveMounts {
+    pub fn new(inner: Arc<dyn Vfs>) -> Self {
+        Self { inner, mounted: Mutex::new(HashMap::new()) }
+    }
+
+    /// The outermost archive that is `path` or contains it.
+    pub fn archive_root(&self, path: &Path) -> Option<PathBuf> {
+        let ancestors: Vec<&Path> = path.ancestors().collect();
+        ancestors.into_iter().rev().find(|ancestor| is_archive(ancestor) && self.inner.stat(ancestor).is_ok_and(|meta| !meta.is_dir)).map(Path::to_path_buf)
+    }
+
+    /// The archive holding `path` and the path inside it.
+    fn locate(&self, path: &Path) -> io::Result<Option<(Arc<ArchiveFs>, PathBuf)>> {
+        let Some(root) = self.archive_root(path) else { return Ok(None) };
+        let relative = path.strip_prefix(&root).expect("root is an ancestor").to_path_buf();
+        Ok(Some((self.mount(&root)?, relative)))
+    }
+
+    fn mount(&self, archive: &Path) -> io::Result<Arc<ArchiveFs>> {
+        let stamp = self.inner.stat(archive)?;
+        let mut mounted = self.mounted.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(fs) = mounted.get(archive).filter(|fs| fs.stamp == stamp) {
+            return Ok(fs.clone());
+        }
+        let fs = Arc::new(ArchiveFs::open(self.inner.clone(), archive).map_err(|err| io::Error::new(err.kind(), format!("cannot open archive {}: {}", archive.display(), err)))?);
+        mounted.insert(archive.to_path_buf(), fs.clone());
+        Ok(fs)
+    }
+
+    fn write_check(&self, path: &Path) -> io::Result<()> {
+        match self.archive_root(path) {
+            Some(_) => Err(read_only()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Vfs for ArchiveMounts {
+    fn list(&self, dir: &Path) -> io::Result<Vec<VfsEntry>> {
+        match self.locate(dir)? {
+            Some((archive, relative)) => archive.list(&relative),
+            None => self.inner.list(dir),
+        }
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<Metadata> {
+        match self.locate(path)? {
+            Some((archive, relative)) => archive.stat(&relative),
+            None => self.inner.stat(path),
+        }
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        match self.locate(path)? {
+            Some((archive, relative)) => Ok(Box::new(Cursor::new(archive.read(&relative)?))),
+            None => self.inner.open_read(path),
+        }
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        self.write_check(path)?;
+        self.inner.open_write(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.write_check(from)?;
+        self.write_check(to)?;
+        self.inner.rename(from, to)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.write_check(path)?;
+        self.inner.remove(path)
+    }
+
+    fn mkdir(&self, path: &Path) -> io::Result<()> {
+        self.write_check(path)?;
+        self.inner.mkdir(path)
+    }
+
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        match self.archive_root(path) {
+            Some(_) => None,
+            None => self.inner.local_path(path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+    use std::ffi::OsStr;
+
+    fn zip_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn tar_gz_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast()));
+        for (name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(1_700_000_000);
+            // Written raw so the test can include names `append_data` would reject
+            header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_cksum();
+            tar.append(&header, *data).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn formats_are_recognized_by_raw_name() {
+        assert_eq!(Format::from_path(Path::new("/srv/Backup.TAR.GZ")), Some(Format::TarGz));
+        assert_eq!(Format::from_path(Path::new(OsStr::from_bytes(b"/srv/caf\xe9.zip"))), Some(Format::Zip));
+        assert_eq!(Format::from_path(Path::new("/srv/notes.gz")), None);
+    }
+
+    fn mounts(archive: &str, data: Vec<u8>) -> ArchiveMounts {
+        let fs = MemoryFs::default();
+        fs.add_file(archive, data);
+        ArchiveMounts::new(Arc::new(fs))
+    }
+
+    fn names(vfs: &dyn Vfs, dir: &str) -> Vec<(String, bool)> {
+        let mut names: Vec<(String, bool)> = vfs.list(Path::new(dir)).unwrap().into_iter().map(|e| (e.name.to_string_lossy().to_string(), e.metadata.unwrap().is_dir)).collect();
+        names.sort();
+        names
+    }
+
+    fn read(vfs: &dyn Vfs, path: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        vfs.open_read(Path::new(path)).unwrap().read_to_end(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn recognizes_archive_names() {
+        assert_eq!(Format::from_path(Path::new("a/B.ZIP")), Some(Format::Zip));
+        assert_eq!(Format::from_path(Path::new("x.tar.gz")), Some(Format::TarGz));
+        assert_eq!(Format::from_path(Path::new("x.tgz")), Some(Format::TarGz));
+        assert_eq!(Format::from_path(Path::new("x.tar.zst")), Some(Format::TarZst));
+        assert_eq!(Format::from_path(Path::new("x.gz")), None);
+    }
+
+    #[test]
+    fn zip_members_list_as_folders() {
+        let vfs = mounts("/home/a.zip", zip_bytes(&[("docs/readme.txt", b"hi"), ("docs/deep/x.bin", b"\0\x01"), ("top.txt", b"top")]));
+        assert_eq!(names(&vfs, "/home"), [("a.zip".to_string(), false)]);
+        assert_eq!(names(&vfs, "/home/a.zip"), [("docs".to_string(), true), ("top.txt".to_string(), false)]);
+        assert_eq!(names(&vfs, "/home/a.zip/docs"), [("deep".to_string(), true), ("readme.txt".to_string(), false)]);
+        assert_eq!(vfs.stat(Path::new("/home/a.zip/top.txt")).unwrap().len, 3);
+        assert!(vfs.stat(Path::new("/home/a.zip/top.txt")).unwrap().modified.is_some());
+        assert_eq!(read(&vfs, "/home/a.zip/docs/readme.txt"), b"hi");
+        assert_eq!(vfs.archive_root(Path::new("/home/a.zip/docs/deep")), Some(PathBuf::from("/home/a.zip")));
+        assert_eq!(vfs.archive_root(Path::new("/home")), None);
+        assert_eq!(vfs.mkdir(Path::new("/home/a.zip/new")).unwrap_err().kind(), io::ErrorKind::ReadOnlyFilesystem);
+    }
+
+    #[test]
+    fn tar_gz_members_skip_escaping_names() {
+        let vfs = mounts("/t.tar.gz", tar_gz_bytes(&[("./a/one.txt", b"1"), ("../evil", b"x"), ("/etc/passwd", b"x"), ("a/two.txt", b"22")]));
+        assert_eq!(names(&vfs, "/t.tar.gz"), [("a".to_string(), true)]);
+        assert_eq!(names(&vfs, "/t.tar.gz/a"), [("one.txt".to_string(), false), ("two.txt".to_string(), false)]);
+        assert_eq!(read(&vfs, "/t.tar.gz/a/two.txt"), b"22");
+        let modified = vfs.stat(Path::new("/t.tar.gz/a/one.txt")).unwrap().modified.unwrap();
+        assert_eq!(modified, SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+    }
+
+    #[test]
+    fn safe_relative_refuses_traversal() {
+        assert_eq!(safe_relative(Path::new("./a/b")), Some(PathBuf::from("a/b")));
+        assert_eq!(safe_relative(Path::new("a/../../b")), None);
+        assert_eq!(safe_relative(Path::new("/abs")), None);
+    }
+}
@@ -0,0 +1,387 @@
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::archive::{self, Format};
+use crate::paths;
+use crate::vfs::{Progress, copy_with_progress};
+
+/// Formats `compress` can write. Plain `.tar` is only read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressFormat {
+    Zip,
+    TarGz,
+    TarXz,
+    TarZst,
+}
+
+impl CompressFormat {
+    pub const ALL: [CompressFormat; 4] = [CompressFormat::Zip, CompressFormat::TarGz, CompressFormat::TarXz, CompressFormat::TarZst];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            CompressFormat::Zip => "zip",
+            CompressFormat::TarGz => "tar.gz",
+            CompressFormat::TarXz => "tar.xz",
+            CompressFormat::TarZst => "tar.zst",
+        }
+    }
+
+    pub fn levels(self) -> std::ops::RangeInclusive<u32> {
+        match self {
+            CompressFormat::TarZst => 1..=19,
+            _ => 0..=9,
+        }
+    }
+
+    pub fn default_level(self) -> u32 {
+        match self {
+            CompressFormat::TarZst => 3,
+            _ => 6,
+        }
+    }
+}
+
+/// What `extract` does when a file is already there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conflict {
+    Skip,
+    Replace,
+    /// Extract as `name (2).ext` next to the existing file.
+    KeepBoth,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ExtractReport {
+    pub files: u64,
+    pub skipped: u64,
+    /// Members refused because they would land outside the target folder,
+    /// or are links.
+    pub refused: Vec<String>,
+}
+
+/// `path`, or the first of `name (2).ext`, `name (3).ext`, … that doesn't exist.
+pub fn unique_path(path: &Path) -> PathBuf {
+    if std::fs::symlink_metadata(path).is_err() {
+        return path.to_path_buf();
+    }
+    // Split on the raw bytes so names that aren't UTF-8 stay as they are
+    let name = path.file_name().unwrap_or(path.as_os_str()).as_bytes();
+    // Keep compound extensions like .tar.gz together
+    let (stem, ext) = match name.iter().skip(1).position(|&b| b == b'.') {
+        Some(i) => name.split_at(i + 1),
+        None => (name, &b""[..]),
+    };
+    (2..)
+        .map(|n| path.with_file_name(OsStr::from_bytes(&[stem, format!(" ({})", n).as_bytes(), ext].concat())))
+        .find(|candidate| std::fs::symlink_metadata(candidate).is_err())
+        .expect("unbounded")
+}
+
+/// A file or folder to pack: where it is, and its name inside the archive.
+type Member = (PathBuf, PathBuf, std::fs::Metadata);
+
+/// Files and folders to pack, and their total size.
+fn collect(sources: &[PathBuf]) -> io::Result<(Vec<Member>, u64)> {
+    let mut found = Vec::new();
+    let mut total = 0;
+    let mut pending: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for source in sources {
+        let name = source.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("cannot compress {}", source.display())))?;
+        pending.push((source.clone(), PathBuf::from(name)));
+    }
+    while let Some((path, name)) = pending.pop() {
+        let meta = std::fs::symlink_metadata(&path)?;
+        if meta.is_dir() {
+            for entry in std::fs::read_dir(&path)? {
+                let entry = entry?;
+                pending.push((entry.path(), name.join(entry.file_name())));
+            }
+        } else if meta.is_file() {
+            total += meta.len();
+        } else {
+            // Links and special files are left out
+            continue;
+        }
+        found.push((path, name, meta));
+    }
+    found.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok((found, total))
+}
+
+fn mtime(meta: &std::fs::Metadata) -> u64 {
+    meta.modified().ok().and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok()).map_or(0, |age| age.as_secs())
+}
+
+/// Packs `sources` into `target`. The archive is written under a temporary
+/// name and only renamed into place once complete, so a failed or cancelled
+/// run leaves nothing behind.
+pub fn compress(sources: &[PathBuf], target: &Path, format: CompressFormat, level: u32, progress: &dyn Progress) -> io::Result<()> {
+    let (members, total) = collect(sources)?;
+    progress.start(total);
+    let name = target.file_name().unwrap_or(target.as_os_str()).as_bytes();
+    let partial = target.with_file_name(OsStr::from_bytes(&[b".", name, b".part"].concat()));
+    let result = write_archive(&members, &partial, format, level, progress).and_then(|()| std::fs::rename(&partial, target));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    result
+}
+
+fn write_archive(members: &[Member], target: &Path, format: CompressFormat, level: u32, progress: &dyn Progress) -> io::Result<()> {
+    let file = File::create(target)?;
+    if format == CompressFormat::Zip {
+        let mut zip = zip::ZipWriter::new(file);
+        for (path, name, meta) in members {
+            let options = zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated)
+                .compression_level(Some(level.into()))
+                .unix_permissions(std::os::unix::fs::PermissionsExt::mode(&meta.permissions()) & 0o777);
+            let name = name.to_string_lossy();
+            if meta.is_dir() {
+                zip.add_directory(name, options).map_err(io::Error::other)?;
+            } else {
+                zip.start_file(name, options).map_err(io::Error::other)?;
+                copy_with_progress(&mut File::open(path)?, &mut zip, path, progress)?;
+            }
+        }
+        zip.finish().map_err(io::Error::other)?.sync_all()?;
+        return Ok(());
+    }
+
+    // Each encoder is finished explicitly so errors in the last block surface
+    let file = match format {
+        CompressFormat::TarGz => write_tar(flate2::write::GzEncoder::new(file, flate2::Compression::new(level)), members, progress)?.finish()?,
+        CompressFormat::TarXz => write_tar(xz2::write::XzEncoder::new(file, level), members, progress)?.finish()?,
+        CompressFormat::TarZst => write_tar(zstd::Encoder::new(file, level as i32)?, members, progress)?.finish()?,
+        CompressFormat::Zip => unreachable!("handled above"),
+    };
+    file.sync_all()
+}
+
+fn write_tar<W: Write>(writer: W, members: &[Member], progress: &dyn Progress) -> io::Result<W> {
+    let mut tar = tar::Builder::new(writer);
+    for (path, name, meta) in members {
+        let mut header = tar::Header::new_gnu();
+        header.set_metadata(meta);
+        header.set_mtime(mtime(meta));
+        if meta.is_dir() {
+            tar.append_data(&mut header, name, io::empty())?;
+        } else {
+            let mut reader = ProgressReader { inner: File::open(path)?, name: path, progress };
+            tar.append_data(&mut header, name, &mut reader)?;
+        }
+    }
+    tar.into_inner()
+}
+
+/// Reports bytes as the tar builder pulls them.
+struct ProgressReader<'a, R> {
+    inner: R,
+    name: &'a Path,
+    progress: &'a dyn Progress,
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.progress.advance(self.name, read as u64)?;
+        Ok(read)
+    }
+}
+
+/// Unpacks the archive at `path` into `dest`, which is created if needed.
+/// Members with absolute paths or `..` components are refused, as are links,
+/// so nothing is ever written outside `dest`.
+pub fn extract(path: &Path, dest: &Path, conflict: Conflict, progress: &dyn Progress) -> io::Result<ExtractReport> {
+    let format = Format::from_path(path).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a supported archive", path.display())))?;
+    std::fs::create_dir_all(dest)?;
+    let mut report = ExtractReport::default();
+    if format == Format::Zip {
+        let mut zip = zip::ZipArchive::new(File::open(path)?).map_err(io::Error::other)?;
+        progress.start((0..zip.len()).filter_map(|i| zip.by_index_raw(i).ok().map(|file| file.size())).sum());
+        for index in 0..zip.len() {
+            let mut file = zip.by_index(index).map_err(io::Error::other)?;
+            let name = file.name().to_string();
+            let Some(relative) = archive::safe_relative(Path::new(&name)).filter(|_| !file.is_symlink()) else {
+                report.refused.push(name);
+                continue;
+            };
+            let mode = file.unix_mode();
+            let is_dir = file.is_dir();
+            write_member(dest, &relative, is_dir, mode, &mut file, conflict, progress, &mut report)?;
+        }
+    } else {
+        let reader: Box<dyn Read> = match format {
+            Format::TarGz => Box::new(flate2::read::GzDecoder::new(File::open(path)?)),
+            Format::TarXz => Box::new(xz2::read::XzDecoder::new(File::open(path)?)),
+            Format::TarZst => Box::new(zstd::Decoder::new(File::open(path)?)?),
+            _ => Box::new(File::open(path)?),
+        };
+        // The size of compressed tars is unknown without a second pass
+        progress.start(0);
+        let mut tar = tar::Archive::new(reader);
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            let name = entry.path_bytes();
+            let name = paths::display(OsStr::from_bytes(&name));
+            let kind = entry.header().entry_type();
+            let relative = entry.path().ok().and_then(|path| archive::safe_relative(&path)).filter(|_| kind.is_dir() || kind.is_file());
+            let Some(relative) = relative else {
+                report.refused.push(name);
+                continue;
+            };
+            let mode = entry.header().mode().ok();
+            write_member(dest, &relative, kind.is_dir(), mode, &mut entry, conflict, progress, &mut report)?;
+        }
+    }
+    Ok(report)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_member(dest: &Path, relative: &Path, is_dir: bool, mode: Option<u32>, reader: &mut dyn Read, conflict: Conflict, progress: &dyn Progress, report: &mut ExtractReport) -> io::Result<()> {
+    if relative.as_os_str().is_empty() {
+        return Ok(());
+    }
+    let mut target = dest.join(relative);
+    if is_dir {
+        return std::fs::create_dir_all(&target);
+    }
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::symlink_metadata(&target).is_ok() {
+        match conflict {
+            Conflict::Skip => {
+                report.skipped += 1;
+                return Ok(());
+            }
+            Conflict::Replace => std::fs::remove_file(&target)?,
+            Conflict::KeepBoth => target = unique_path(&target),
+        }
+    }
+    let mut file = File::create(&target)?;
+    copy_with_progress(reader, &mut file, relative, progress)?;
+    if let Some(mode) = mode.filter(|mode| mode & 0o111 != 0) {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(mode & 0o777))?;
+    }
+    report.files += 1;
+    Ok(())
+}
+
+/// Where "Extract to folder" puts `archive`: a new folder named after it.
+pub fn extract_folder(archive: &Path) -> PathBuf {
+    let name = archive.file_name().unwrap_or(archive.as_os_str()).as_bytes();
+    let lower = name.to_ascii_lowercase();
+    let stem_len = [".tar.gz", ".tar.zst", ".tar.xz", ".tgz", ".tzst", ".txz", ".tar", ".zip"]
+        .iter()
+        .find(|ext| lower.ends_with(ext.as_bytes()))
+        .map_or(name.len(), |ext| name.len() - ext.len());
+    unique_path(&archive.with_file_name(OsStr::from_bytes(&name[..stem_len])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Scratch {
+        dir: PathBuf,
+    }
+
+    impl Scratch {
+        fn new(test: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("datara-ops-{}-{}", test, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self { dir }
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        let scratch = Scratch::new("roundtrip");
+        let src = scratch.dir.join("project");
+        std::fs::create_dir_all(src.join("sub")).unwrap();
+        std::fs::write(src.join("a.txt"), "alpha").unwrap();
+        std::fs::write(src.join("sub/b.txt"), "beta").unwrap();
+        for format in CompressFormat::ALL {
+            let target = scratch.dir.join(format!("out.{}", format.extension()));
+            compress(std::slice::from_ref(&src), &target, format, format.default_level(), &()).unwrap();
+            let dest = scratch.dir.join(format!("x-{}", format.extension()));
+            let report = extract(&target, &dest, Conflict::Skip, &()).unwrap();
+            assert_eq!(report.files, 2, "{:?}", format);
+            assert_eq!(read(&dest.join("project/sub/b.txt")), "beta");
+            assert!(!scratch.dir.join(format!(".out.{}.part", format.extension())).exists());
+        }
+    }
+
+    #[test]
+    fn extraction_refuses_traversal() {
+        let scratch = Scratch::new("traversal");
+        let archive = scratch.dir.join("evil.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        for name in ["../escaped.txt", "/abs.txt", "ok/inner.txt"] {
+            zip.start_file(name, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(b"x").unwrap();
+        }
+        zip.finish().unwrap();
+
+        let dest = scratch.dir.join("out");
+        let report = extract(&archive, &dest, Conflict::Skip, &()).unwrap();
+        assert_eq!(report.files, 1);
+        assert_eq!(report.refused, ["../escaped.txt", "/abs.txt"]);
+        assert!(!scratch.dir.join("escaped.txt").exists());
+        assert!(dest.join("ok/inner.txt").exists());
+    }
+
+    #[test]
+    fn conflicts_follow_the_policy() {
+        let scratch = Scratch::new("conflict");
+        let src = scratch.dir.join("f.txt");
+        std::fs::write(&src, "new").unwrap();
+        let archive = scratch.dir.join("a.tar.gz");
+        compress(&[src], &archive, CompressFormat::TarGz, 1, &()).unwrap();
+        let dest = scratch.dir.join("dest");
+        std::fs::create_dir_all(&dest).unwrap();
+        std::fs::write(dest.join("f.txt"), "old").unwrap();
+
+        assert_eq!(extract(&archive, &dest, Conflict::Skip, &()).unwrap().skipped, 1);
+        assert_eq!(read(&dest.join("f.txt")), "old");
+        extract(&archive, &dest, Conflict::KeepBoth, &()).unwrap();
+        assert_eq!(read(&dest.join("f (2).txt")), "new");
+        extract(&archive, &dest, Conflict::Replace, &()).unwrap();
+        assert_eq!(read(&dest.join("f.txt")), "new");
+    }
+
+    #[test]
+    fn extract_folder_strips_the_extension() {
+        assert_eq!(extract_folder(Path::new("/nonexistent/photos.tar.gz")), Path::new("/nonexistent/photos"));
+        assert_eq!(extract_folder(Path::new("/nonexistent/a.b.zip")), Path::new("/nonexistent/a.b"));
+        assert_eq!(extract_folder(Path::new(OsStr::from_bytes(b"/nonexistent/bad\xffname.ZIP"))), Path::new(OsStr::from_bytes(b"/nonexistent/bad\xffname")));
+    }
+
+    #[test]
+    fn unique_paths_keep_raw_names_and_compound_extensions() {
+        let scratch = Scratch::new("unique");
+        let taken = scratch.dir.join(OsStr::from_bytes(b"caf\xe9.tar.gz"));
+        std::fs::write(&taken, "").unwrap();
+        std::fs::write(scratch.dir.join(".profile"), "").unwrap();
+        assert_eq!(unique_path(&scratch.dir.join("free.zip")), scratch.dir.join("free.zip"));
+        assert_eq!(unique_path(&taken), scratch.dir.join(OsStr::from_bytes(b"caf\xe9 (2).tar.gz")));
+        assert_eq!(unique_path(&scratch.dir.join(".profile")), scratch.dir.join(".profile (2)"));
+    }
+}
@@ -0,0 +1,288 @@
+use std::cmp::Ordering;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::paths;
+use crate::vfs::{LocalFs, Vfs};
+
+/// A listed directory entry, with the metadata the views need read once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub name: OsString,
+    pub is_dir: bool,
+    /// Size in bytes; `None` for directories.
+    pub len: Option<u64>,
+    pub modified: Option<SystemTime>,
+}
+
+impl Entry {
+    pub fn display_name(&self) -> String {
+        paths::display(&self.name)
+    }
+}
+
+/// Folders first, then case-insensitive by name.
+pub fn sort_entries(entries: &mut [Entry]) {
+    entries.sort_by(|a, b| match (a.is_dir, b.is_dir) {
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        _ => a.name.to_string_lossy().to_lowercase().cmp(&b.name.to_string_lossy().to_lowercase()),
+    });
+}
+
+/// Reads `dir` without sorting. Entries whose metadata can't be read are
+/// listed as plain files without size or date.
+pub fn list_dir(vfs: &dyn Vfs, dir: &Path, show_hidden: bool) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for entry in vfs.list(dir)? {
+        if !show_hidden && paths::is_hidden(&entry.name) {
+            continue;
+        }
+        let meta = entry.metadata;
+        let is_dir = meta.is_some_and(|meta| meta.is_dir);
+        entries.push(Entry {
+            path: dir.join(&entry.name),
+            name: entry.name,
+            is_dir,
+            len: meta.filter(|_| !is_dir).map(|meta| meta.len),
+            modified: meta.and_then(|meta| meta.modified),
+        });
+    }
+    Ok(entries)
+}
+
+/// The folder being browsed, its sorted listing and the back/forward history,
+/// independent of any UI.
+#[derive(Clone)]
+pub struct Browser {
+    /// Where `current_dir` and the entry paths live.
+    pub vfs: Arc<dyn Vfs>,
+    pub current_dir: PathBuf,
+    pub entries: Vec<Entry>,
+    pub history: Vec<PathBuf>,
+    pub future: Vec<PathBuf>,
+    pub selected_path: Option<PathBuf>,
+    pub show_hidden: bool,
+}
+
+impl Browser {
+    /// Starts at `dir` on the local filesystem without listing it; call
+    /// `read_dir` next.
+    pub fn new(dir: PathBuf) -> Self {
+        Self::with_vfs(Arc::new(LocalFs), dir)
+    }
+
+    pub fn with_vfs(vfs: Arc<dyn Vfs>, dir: PathBuf) -> Self {
+        Self { vfs, current_dir: dir, entries: Vec::new(), history: Vec::new(), future: Vec::new(), selected_path: None, show_hidden: false }
+    }
+
+    /// Lists the current folder again, dropping the selection. On error the
+    /// listing stays empty.
+    pub fn read_dir(&mut self) -> io::Result<()> {
+        let listing = list_dir(self.vfs.as_ref(), &self.current_dir, self.show_hidden);
+        self.set_listing(listing)
+    }
+
+    /// Shows `listing` as the contents of the current folder, e.g. one read
+    /// on another thread after a `move_*` call; see `read_dir`.
+    pub fn set_listing(&mut self, listing: io::Result<Vec<Entry>>) -> io::Result<()> {
+        self.entries.clear();
+        self.selected_path = None;
+        self.entries = listing?;
+        sort_entries(&mut self.entries);
+        Ok(())
+    }
+
+    /// Moves to `path`; with `push_history` the current folder can be returned
+    /// to with `navigate_back`. The move happens even if listing fails.
+    pub fn navigate_to(&mut self, path: PathBuf, push_history: bool) -> io::Result<()> {
+        self.move_to(path, push_history);
+        self.read_dir()
+    }
+
+    /// `navigate_to` without listing the folder.
+    pub fn move_to(&mut self, path: PathBuf, push_history: bool) {
+        if push_history {
+            self.history.push(self.current_dir.clone());
+            self.future.clear();
+        }
+        self.current_dir = path;
+    }
+
+    /// Moves to `dir` on another filesystem. The history is dropped, since
+    /// its paths belong to the old one.
+    pub fn switch_vfs(&mut self, vfs: Arc<dyn Vfs>, dir: PathBuf) -> io::Result<()> {
+        self.move_to_vfs(vfs, dir);
+        self.read_dir()
+    }
+
+    /// `switch_vfs` without listing the folder.
+    pub fn move_to_vfs(&mut self, vfs: Arc<dyn Vfs>, dir: PathBuf) {
+        self.vfs = vfs;
+        self.history.clear();
+        self.future.clear();
+        self.move_to(dir, false);
+    }
+
+    /// Does nothing at the root.
+    pub fn navigate_up(&mut self) -> io::Result<()> {
+        match self.current_dir.parent() {
+            Some(parent) => self.navigate_to(parent.to_path_buf(), true),
+            None => Ok(()),
+        }
+    }
+
+    pub fn navigate_back(&mut self) -> io::Result<()> {
+        if !self.move_back() {
+            return Ok(());
+        }
+        self.read_dir()
+    }
+
+    /// `navigate_back` without listing; false when there is no history.
+    pub fn move_back(&mut self) -> bool {
+        let Some(prev) = self.history.pop() else { return false };
+        self.future.push(std::mem::replace(&mut self.current_dir, prev));
+        true
+    }
+
+    pub fn navigate_forward(&mut self) -> io::Result<()> {
+        if !self.move_forward() {
+            return Ok(());
+        }
+        self.read_dir()
+    }
+
+    /// `navigate_forward` without listing; false at the end of the history.
+    pub fn move_forward(&mut self) -> bool {
+        let Some(next) = self.future.pop() else { return false };
+        self.history.push(std::mem::replace(&mut self.current_dir, next));
+        true
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        !self.history.is_empty()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        !self.future.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+
+    /// `/root` holding `a/inner/`, `B/`, `c.txt` and `.hidden`.
+    fn browser() -> Browser {
+        let fs = MemoryFs::default();
+        fs.add_dir("/root/a/inner").add_dir("/root/B").add_file("/root/c.txt", "hello").add_file("/root/.hidden", "");
+        let mut browser = Browser::with_vfs(Arc::new(fs), PathBuf::from("/root"));
+        browser.read_dir().unwrap();
+        browser
+    }
+
+    fn names(browser: &Browser) -> Vec<String> {
+        browser.entries.iter().map(Entry::display_name).collect()
+    }
+
+    fn paths(list: &[&str]) -> Vec<PathBuf> {
+        list.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn listing_sorts_folders_first_and_hides_dotfiles() {
+        let mut browser = browser();
+        assert_eq!(names(&browser), ["a", "B", "c.txt"]);
+        assert_eq!(browser.entries[2].len, Some(5));
+        assert_eq!(browser.entries[2].path, Path::new("/root/c.txt"));
+        assert_eq!(browser.entries[0].len, None);
+
+        browser.show_hidden = true;
+        browser.read_dir().unwrap();
+        assert_eq!(names(&browser), ["a", "B", ".hidden", "c.txt"]);
+    }
+
+    #[test]
+    fn navigate_to_records_history_and_clears_forward() {
+        let mut browser = browser();
+        browser.navigate_to(PathBuf::from("/root/a"), true).unwrap();
+        assert_eq!(names(&browser), ["inner"]);
+        assert_eq!(browser.history, paths(&["/root"]));
+
+        browser.navigate_back().unwrap();
+        assert!(browser.can_go_forward());
+        browser.navigate_to(PathBuf::from("/root/B"), true).unwrap();
+        assert!(!browser.can_go_forward());
+
+        browser.navigate_to(PathBuf::from("/root/a"), false).unwrap();
+        assert_eq!(browser.history, paths(&["/root"]));
+    }
+
+    #[test]
+    fn back_and_forward_retrace_the_path() {
+        let mut browser = browser();
+        browser.navigate_to(PathBuf::from("/root/a"), true).unwrap();
+        browser.navigate_to(PathBuf::from("/root/a/inner"), true).unwrap();
+
+        browser.navigate_back().unwrap();
+        assert_eq!(browser.current_dir, Path::new("/root/a"));
+        browser.navigate_back().unwrap();
+        assert_eq!(browser.current_dir, Path::new("/root"));
+        assert!(!browser.can_go_back());
+        browser.navigate_back().unwrap();
+        assert_eq!(browser.current_dir, Path::new("/root"));
+
+        browser.navigate_forward().unwrap();
+        browser.navigate_forward().unwrap();
+        assert_eq!(browser.current_dir, Path::new("/root/a/inner"));
+        assert!(!browser.can_go_forward());
+        assert_eq!(browser.history, paths(&["/root", "/root/a"]));
+    }
+
+    #[test]
+    fn navigate_up_stops_at_the_root() {
+        let mut browser = browser();
+        browser.navigate_to(PathBuf::from("/root/a/inner"), false).unwrap();
+        browser.navigate_up().unwrap();
+        assert_eq!(browser.current_dir, Path::new("/root/a"));
+        assert_eq!(browser.history, paths(&["/root/a/inner"]));
+
+        browser.navigate_up().unwrap();
+        browser.navigate_up().unwrap();
+        assert_eq!(browser.current_dir, Path::new("/"));
+        assert_eq!(names(&browser), ["root"]);
+        browser.navigate_up().unwrap();
+        assert_eq!(browser.current_dir, Path::new("/"));
+        assert_eq!(browser.history.len(), 3);
+    }
+
+    #[test]
+    fn switching_filesystems_drops_the_history() {
+        let mut browser = browser();
+        browser.navigate_to(PathBuf::from("/root/a"), true).unwrap();
+        let other = MemoryFs::default();
+        other.add_file("/srv/x", "");
+        browser.switch_vfs(Arc::new(other), PathBuf::from("/srv")).unwrap();
+        assert_eq!(names(&browser), ["x"]);
+        assert!(!browser.can_go_back());
+        assert!(browser.navigate_to(PathBuf::from("/root"), false).is_err());
+    }
+
+    #[test]
+    fn failed_listing_still_moves_and_empties() {
+        let mut browser = browser();
+        browser.selected_path = Some(PathBuf::from("/root/c.txt"));
+        assert!(browser.navigate_to(PathBuf::from("/root/gone"), true).is_err());
+        assert_eq!(browser.current_dir, Path::new("/root/gone"));
+        assert!(browser.entries.is_empty());
+        assert_eq!(browser.selected_path, None);
+        browser.navigate_back().unwrap();
+        assert_eq!(names(&browser), ["a", "B", "c.txt"]);
+    }
+}
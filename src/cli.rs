@@ -0,0 +1,226 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use crate::pick::{PickOptions, PickTarget};
+
+pub const USAGE: &str = "\
+Usage: datara [OPTIONS] [PATH]...
+
+Opens each PATH in its own tab; a file opens its directory with the file selected.
+When Datara is already running, the PATHs open as new tabs in that window instead.
+
+Options:
+      --select <FILE>      Highlight FILE in its directory
+      --view <grid|list>   Start in grid or list view
+      --config <PATH>      Use PATH instead of $XDG_CONFIG_HOME/datara/config.toml
+      --show-hidden        Show hidden files
+      --scale <FACTOR>     UI scale, 0.5 to 3.0
+      --new-instance       Start a separate window even if Datara is running
+      --control <SOCKET>   Accept JSON-RPC commands on the Unix socket SOCKET
+      --pick[=WHAT]        Choose files, dirs or both (default files) and print them
+      --multiple           With --pick, allow choosing several entries (Ctrl+click)
+      --filter <EXT,...>   With --pick, only offer files with these extensions
+      --null               With --pick, end each printed path with NUL instead of newline
+      --save <NAME>        Choose a folder and file name to save to, printed like --pick
+      --portal             Serve the xdg-desktop-portal FileChooser on the session bus
+  -h, --help               Print this help
+  -V, --version            Print the version
+
+--view, --show-hidden and --scale apply to this session only and are not saved.
+With --pick, the exit status is 0 when the selection was confirmed and 1 when cancelled.";
+
+/// Settings given on the command line; they override the config file for
+/// this session without being written back.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+    pub grid_view: Option<bool>,
+    pub show_hidden: Option<bool>,
+    pub ui_scale: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LaunchOptions {
+    pub paths: Vec<PathBuf>,
+    pub select: Option<PathBuf>,
+    pub config_path: Option<PathBuf>,
+    pub overrides: Overrides,
+    pub pick: Option<PickOptions>,
+    /// Don't hand the paths to an already running instance.
+    pub new_instance: bool,
+    /// Socket to serve the JSON-RPC remote control on.
+    pub control: Option<PathBuf>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run(LaunchOptions),
+    /// Run as a portal backend; dialogs are `--pick` child processes.
+    Portal,
+    Help,
+    Version,
+}
+
+/// Parses the arguments after the program name. Paths are kept as `OsString`
+/// so names that aren't valid UTF-8 survive.
+pub fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Command, String> {
+    let mut options = LaunchOptions::default();
+    let mut args = args.into_iter();
+    let mut only_paths = false;
+    let mut multiple = false;
+    let mut null = false;
+    let mut extensions: Option<Vec<String>> = None;
+    let mut save_name: Option<String> = None;
+    while let Some(arg) = args.next() {
+        let Some(flag) = arg.to_str().filter(|a| !only_paths && a.starts_with('-') && *a != "-") else {
+            options.paths.push(PathBuf::from(arg));
+            continue;
+        };
+        let (name, inline_value) = match flag.split_once('=') {
+            Some((name, value)) => (name, Some(OsString::from(value))),
+            None => (flag, None),
+        };
+        // `--show-hidden=false` would otherwise turn hidden files on
+        if inline_value.is_some() && matches!(name, "--" | "-h" | "--help" | "-V" | "--version" | "--portal" | "--show-hidden" | "--new-instance" | "--multiple" | "--null") {
+            return Err(format!("{} takes no value", name));
+        }
+        let mut value = |name: &str| inline_value.clone().or_else(|| args.next()).ok_or_else(|| format!("{} needs a value", name));
+        match name {
+            "--" => only_paths = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "--portal" => return Ok(Command::Portal),
+            "--select" => options.select = Some(PathBuf::from(value(name)?)),
+            "--config" => options.config_path = Some(PathBuf::from(value(name)?)),
+            "--show-hidden" => options.overrides.show_hidden = Some(true),
+            "--new-instance" => options.new_instance = true,
+            "--control" => options.control = Some(PathBuf::from(value(name)?)),
+            "--view" => {
+                let view = value(name)?;
+                options.overrides.grid_view = Some(match view.to_str() {
+                    Some("grid") => true,
+                    Some("list") => false,
+                    _ => return Err(format!("--view must be 'grid' or 'list', got '{}'", view.to_string_lossy())),
+                });
+            }
+            "--scale" => {
+                let scale = value(name)?;
+                let parsed = scale.to_str().and_then(|s| s.parse::<f32>().ok()).filter(|s| (0.5..=3.0).contains(s));
+                options.overrides.ui_scale = Some(parsed.ok_or_else(|| format!("--scale must be a number from 0.5 to 3.0, got '{}'", scale.to_string_lossy()))?);
+            }
+            "--pick" => {
+                let target = match &inline_value {
+                    Some(what) => what.to_str().and_then(PickTarget::from_name).ok_or_else(|| format!("--pick must be files, dirs or both, got '{}'", what.to_string_lossy()))?,
+                    None => PickTarget::Files,
+                };
+                options.pick = Some(PickOptions::new(target));
+            }
+            "--save" => {
+                let name = value(name)?;
+                save_name = Some(name.to_str().ok_or("--save name must be valid UTF-8")?.to_string());
+            }
+            "--multiple" => multiple = true,
+            "--null" => null = true,
+            "--filter" => {
+                let list = value(name)?;
+                let list = list.to_str().ok_or("--filter must be valid UTF-8")?;
+                extensions = Some(list.split(',').map(|ext| ext.trim().trim_start_matches("*.").trim_start_matches('.').to_lowercase()).filter(|ext| !ext.is_empty()).collect());
+            }
+            _ => return Err(format!("unknown option '{}'", flag)),
+        }
+    }
+    if save_name.is_some() {
+        options.pick.get_or_insert(PickOptions::new(PickTarget::Files)).save_name = save_name;
+    }
+    match &mut options.pick {
+        Some(pick) => {
+            pick.multiple = multiple;
+            pick.extensions = extensions.unwrap_or_default();
+            pick.separator = if null { b'\0' } else { b'\n' };
+        }
+        None if multiple || null || extensions.is_some() => return Err("--multiple, --filter and --null need --pick".to_string()),
+        None => {}
+    }
+    Ok(Command::Run(options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::ffi::OsStringExt;
+
+    fn run(args: &[&str]) -> Result<LaunchOptions, String> {
+        match parse(args.iter().map(OsString::from))? {
+            Command::Run(options) => Ok(options),
+            other => panic!("expected a launch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn values_come_inline_or_as_the_next_argument() {
+        let options = run(&["--view=list", "--scale", "1.5", "--select=/tmp/a b", "--control", "/run/datara.sock"]).unwrap();
+        assert_eq!(options.overrides, Overrides { grid_view: Some(false), show_hidden: None, ui_scale: Some(1.5) });
+        assert_eq!(options.select, Some(PathBuf::from("/tmp/a b")));
+        assert_eq!(options.control, Some(PathBuf::from("/run/datara.sock")));
+        assert_eq!(run(&["--view=tiles"]).unwrap_err(), "--view must be 'grid' or 'list', got 'tiles'");
+        assert_eq!(run(&["--scale=9"]).unwrap_err(), "--scale must be a number from 0.5 to 3.0, got '9'");
+        assert_eq!(run(&["--config"]).unwrap_err(), "--config needs a value");
+        assert_eq!(run(&["--frobnicate"]).unwrap_err(), "unknown option '--frobnicate'");
+        assert_eq!(parse([OsString::from("--portal")]), Ok(Command::Portal));
+        assert_eq!(parse(["/tmp", "-h"].map(OsString::from)), Ok(Command::Help));
+    }
+
+    #[test]
+    fn double_dash_ends_the_options() {
+        let options = run(&["-", "--show-hidden", "--", "--view=grid", "-h"]).unwrap();
+        assert_eq!(options.paths, [PathBuf::from("-"), PathBuf::from("--view=grid"), PathBuf::from("-h")]);
+        assert_eq!(options.overrides.show_hidden, Some(true));
+        assert_eq!(options.overrides.grid_view, None);
+    }
+
+    #[test]
+    fn pick_takes_what_to_pick_and_its_modifiers() {
+        let pick = run(&["--pick=dirs", "--multiple", "--null"]).unwrap().pick.unwrap();
+        assert_eq!((pick.target, pick.multiple, pick.separator), (PickTarget::Directories, true, b'\0'));
+        assert_eq!(run(&["--pick"]).unwrap().pick, Some(PickOptions::new(PickTarget::Files)));
+        assert_eq!(run(&["--pick=folders"]).unwrap_err(), "--pick must be files, dirs or both, got 'folders'");
+        // `--pick` takes no separate value; the next argument is a path
+        assert_eq!(run(&["--pick", "both"]).unwrap().paths, [PathBuf::from("both")]);
+
+        let save = run(&["--save", "report.pdf"]).unwrap().pick.unwrap();
+        assert_eq!((save.target, save.save_name.as_deref()), (PickTarget::Files, Some("report.pdf")));
+    }
+
+    #[test]
+    fn switches_refuse_inline_values() {
+        for flag in ["--show-hidden", "--new-instance", "--multiple", "--null", "--help", "--portal"] {
+            assert_eq!(run(&["--pick", &format!("{}=false", flag)]).unwrap_err(), format!("{} takes no value", flag));
+        }
+        assert_eq!(run(&["--show-hidden="]).unwrap_err(), "--show-hidden takes no value");
+        assert!(run(&["--new-instance"]).unwrap().new_instance);
+    }
+
+    #[test]
+    fn filters_are_normalized_to_bare_lowercase_extensions() {
+        let pick = run(&["--pick", "--filter", "*.PNG, .jpg,,txt"]).unwrap().pick.unwrap();
+        assert_eq!(pick.extensions, ["png", "jpg", "txt"]);
+    }
+
+    #[test]
+    fn pick_modifiers_need_pick() {
+        for flag in ["--multiple", "--null", "--filter=png"] {
+            assert_eq!(run(&[flag]).unwrap_err(), "--multiple, --filter and --null need --pick");
+        }
+    }
+
+    #[test]
+    fn paths_need_not_be_utf8() {
+        let path = OsString::from_vec(b"/tmp/caf\xe9".to_vec());
+        let dash = OsString::from_vec(b"-\xff".to_vec());
+        let args = [OsString::from("--select"), path.clone(), path.clone(), dash.clone()];
+        let Ok(Command::Run(options)) = parse(args) else { panic!("expected a launch") };
+        assert_eq!(options.select, Some(PathBuf::from(&path)));
+        // Not an option, since no option name is invalid UTF-8
+        assert_eq!(options.paths, [PathBuf::from(path), PathBuf::from(dash)]);
+        assert_eq!(parse([OsString::from("--save"), OsString::from_vec(b"caf\xe9".to_vec())]), Err("--save name must be valid UTF-8".to_string()));
+    }
+}
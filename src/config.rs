@@ -0,0 +1,396 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::openers::{self, OpenerRule};
+
+/// Schema version written to `config.toml`. Bump it together with a step in
+/// `migrate` whenever a field is renamed or changes meaning.
+pub const CONFIG_VERSION: i64 = 1;
+pub const DEFAULT_TERMINAL_COMMAND: &str = "gnome-terminal --";
+/// Pre-TOML settings file, written to the working directory.
+const LEGACY_SETTINGS_FILE: &str = "datara_settings.txt";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub version: i64,
+    pub display: DisplayConfig,
+    pub files: FilesConfig,
+    pub openers: OpenersConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    pub ui_scale: f32,
+    pub max_items_per_row: i32,
+    pub horizontal_spacing: f32,
+    pub vertical_spacing: f32,
+    pub show_scanlines: bool,
+    pub show_preview: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilesConfig {
+    pub show_hidden: bool,
+    pub show_thumbnails: bool,
+    /// Absolute paths and `sftp://`, `dav://` or `davs://` URLs.
+    pub bookmarks: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenersConfig {
+    pub terminal_command: String,
+    pub rules: Vec<OpenerRule>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            display: DisplayConfig::default(),
+            files: FilesConfig::default(),
+            openers: OpenersConfig::default(),
+        }
+    }
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            ui_scale: 1.0,
+            max_items_per_row: 3,
+            horizontal_spacing: 16.0,
+            vertical_spacing: 12.0,
+            show_scanlines: false,
+            show_preview: false,
+        }
+    }
+}
+
+impl Default for FilesConfig {
+    fn default() -> Self {
+        Self { show_hidden: false, show_thumbnails: true, bookmarks: Vec::new() }
+    }
+}
+
+impl Default for OpenersConfig {
+    fn default() -> Self {
+        Self { terminal_command: DEFAULT_TERMINAL_COMMAND.to_string(), rules: openers::default_rules() }
+    }
+}
+
+/// Outcome of `load`: the config to use plus anything worth telling the user.
+pub struct LoadedConfig {
+    pub config: Config,
+    pub errors: Vec<String>,
+    /// Set when the legacy settings file was converted.
+    pub migrated_from: Option<PathBuf>,
+    /// Set when the file could not be read or parsed; `config` is then the
+    /// defaults.
+    pub failed: bool,
+}
+
+/// `$XDG_CONFIG_HOME/datara/config.toml`, falling back to `~/.config`.
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("datara").join("config.toml"))
+}
+
+/// Reads `path`, migrating the legacy settings file when `path` doesn't exist yet.
+pub fn load(path: &Path) -> LoadedConfig {
+    load_from(path, Path::new(LEGACY_SETTINGS_FILE))
+}
+
+fn load_from(path: &Path, legacy: &Path) -> LoadedConfig {
+    let mut loaded = LoadedConfig { config: Config::default(), errors: Vec::new(), migrated_from: None, failed: false };
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            if let Ok(contents) = std::fs::read_to_string(legacy) {
+                loaded.config = from_legacy(&contents, &mut loaded.errors);
+                loaded.migrated_from = Some(legacy.to_path_buf());
+            }
+            loaded.errors.extend(loaded.config.validate());
+            return loaded;
+        }
+        Err(err) => {
+            loaded.errors.push(format!("Failed to read {}: {}", path.display(), err));
+            loaded.failed = true;
+            return loaded;
+        }
+    };
+
+    match parse(&contents) {
+        Ok(config) => loaded.config = config,
+        Err(err) => {
+            // Keep the user's file around instead of overwriting it with defaults
+            let backup = path.with_extension("toml.bak");
+            let _ = std::fs::copy(path, &backup);
+            loaded.errors.push(format!("Invalid config {}: {} (backed up to {})", path.display(), err, backup.display()));
+            loaded.failed = true;
+        }
+    }
+    loaded.errors.extend(loaded.config.validate());
+    loaded
+}
+
+fn parse(contents: &str) -> Result<Config, String> {
+    let mut table: toml::Table = contents.parse().map_err(|err: toml::de::Error| err.message().to_string())?;
+    let version = match table.get("version") {
+        Some(toml::Value::Integer(version)) => *version,
+        Some(_) => return Err("'version' must be an integer".to_string()),
+        None => return Err("missing 'version'".to_string()),
+    };
+    if version > CONFIG_VERSION {
+        return Err(format!("written by a newer Datara (schema version {}, this build reads up to {})", version, CONFIG_VERSION));
+    }
+    if version == CONFIG_VERSION {
+        // Deserializing the text rather than the table keeps line numbers in errors
+        return toml::from_str(contents).map_err(|err: toml::de::Error| err.to_string().trim_end().to_string());
+    }
+    migrate(&mut table, version)?;
+    Config::deserialize(table).map_err(|err| err.message().to_string())
+}
+
+/// Upgrades an older schema in place. Version 0 only ever existed as the
+/// key=value file, see `from_legacy`; later versions add steps here.
+fn migrate(table: &mut toml::Table, from: i64) -> Result<(), String> {
+    if from < 1 {
+        return Err(format!("unsupported schema version {}", from));
+    }
+    table.insert("version".to_string(), toml::Value::Integer(CONFIG_VERSION));
+    Ok(())
+}
+
+/// Converts the old `key=value` settings file.
+fn from_legacy(contents: &str, errors: &mut Vec<String>) -> Config {
+    let mut config = Config::default();
+    let mut rules = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let mut bad = |what: &str| errors.push(format!("{} line {}: invalid {} '{}'", LEGACY_SETTINGS_FILE, number + 1, what, value));
+        match key {
+            "ui_scale" => match value.parse() { Ok(val) => config.display.ui_scale = val, Err(_) => bad(key) },
+            "max_items_per_row" => match value.parse() { Ok(val) => config.display.max_items_per_row = val, Err(_) => bad(key) },
+            "show_scanlines" => match value.parse() { Ok(val) => config.display.show_scanlines = val, Err(_) => bad(key) },
+            "show_hidden" => match value.parse() { Ok(val) => config.files.show_hidden = val, Err(_) => bad(key) },
+            "horizontal_spacing" => match value.parse() { Ok(val) => config.display.horizontal_spacing = val, Err(_) => bad(key) },
+            "vertical_spacing" => match value.parse() { Ok(val) => config.display.vertical_spacing = val, Err(_) => bad(key) },
+            "show_preview" => match value.parse() { Ok(val) => config.display.show_preview = val, Err(_) => bad(key) },
+            "show_thumbnails" => match value.parse() { Ok(val) => config.files.show_thumbnails = val, Err(_) => bad(key) },
+            "terminal_command" => config.openers.terminal_command = value.to_string(),
+            "opener" => match OpenerRule::from_config(value) {
+                Ok(rule) => rules.push(rule),
+                Err(err) => errors.push(format!("{} line {}: {}", LEGACY_SETTINGS_FILE, number + 1, err)),
+            },
+            _ => {}
+        }
+    }
+    if !rules.is_empty() {
+        config.openers.rules = rules;
+    }
+    config
+}
+
+impl Config {
+    /// Clamps out-of-range values and drops unusable opener rules, describing each fix.
+    pub fn validate(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let defaults = DisplayConfig::default();
+        let display = &mut self.display;
+        clamp_field(&mut errors, "display.ui_scale", &mut display.ui_scale, 0.5, 3.0, defaults.ui_scale);
+        clamp_field(&mut errors, "display.horizontal_spacing", &mut display.horizontal_spacing, 0.0, 100.0, defaults.horizontal_spacing);
+        clamp_field(&mut errors, "display.vertical_spacing", &mut display.vertical_spacing, 0.0, 50.0, defaults.vertical_spacing);
+        if !(2..=5).contains(&display.max_items_per_row) {
+            errors.push(format!("display.max_items_per_row must be between 2 and 5, got {}", display.max_items_per_row));
+            display.max_items_per_row = display.max_items_per_row.clamp(2, 5);
+        }
+        if self.openers.terminal_command.trim().is_empty() {
+            errors.push("openers.terminal_command is empty".to_string());
+            self.openers.terminal_command = DEFAULT_TERMINAL_COMMAND.to_string();
+        }
+        self.openers.rules.retain(|rule| match rule.validate() {
+            Ok(()) => true,
+            Err(err) => {
+                errors.push(format!("Dropped opener rule '{}': {}", rule.pattern, err));
+                false
+            }
+        });
+        if self.openers.rules.is_empty() {
+            self.openers.rules = openers::default_rules();
+        }
+        errors
+    }
+
+    /// Writes the file atomically: a temporary file in the same directory is
+    /// flushed to disk and then renamed over the old config.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let contents = toml::to_string_pretty(self).map_err(std::io::Error::other)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("toml.tmp");
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(&tmp, path)
+    }
+}
+
+fn clamp_field(errors: &mut Vec<String>, name: &str, value: &mut f32, min: f32, max: f32, default: f32) {
+    if !value.is_finite() {
+        errors.push(format!("{} is not a number", name));
+        *value = default;
+    } else if *value < min || *value > max {
+        errors.push(format!("{} must be between {} and {}, got {}", name, min, max, value));
+        *value = value.clamp(min, max);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory for one test, removed when dropped.
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("datara-config-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn saved_configs_load_back_unchanged() {
+        let scratch = Scratch::new("round-trip");
+        let path = scratch.0.join("datara").join("config.toml");
+        let mut config = Config::default();
+        config.display.ui_scale = 1.5;
+        config.display.max_items_per_row = 5;
+        config.files.show_hidden = true;
+        config.files.bookmarks = vec!["/srv/assets".to_string(), "sftp://me@host/home".to_string()];
+        config.openers.terminal_command = "xterm -e".to_string();
+        config.openers.rules.truncate(2);
+        config.save(&path).unwrap();
+
+        let loaded = load_from(&path, &scratch.0.join(LEGACY_SETTINGS_FILE));
+        assert_eq!(loaded.config, config);
+        assert!(loaded.errors.is_empty(), "{:?}", loaded.errors);
+        assert!(!loaded.failed && loaded.migrated_from.is_none());
+        // Only the config itself is left, no temporary file
+        let names: Vec<_> = std::fs::read_dir(path.parent().unwrap()).unwrap().flatten().map(|entry| entry.file_name()).collect();
+        assert_eq!(names, ["config.toml"]);
+    }
+
+    #[test]
+    fn missing_fields_take_their_defaults() {
+        let config = parse("version = 1\n[display]\nui_scale = 2.0\n").unwrap();
+        assert_eq!(config.display.ui_scale, 2.0);
+        assert_eq!(config.display.max_items_per_row, DisplayConfig::default().max_items_per_row);
+        assert_eq!(config.files, FilesConfig::default());
+        assert_eq!(config.openers, OpenersConfig::default());
+    }
+
+    #[test]
+    fn the_legacy_file_is_migrated_when_there_is_no_config() {
+        let scratch = Scratch::new("legacy");
+        let legacy = scratch.0.join(LEGACY_SETTINGS_FILE);
+        std::fs::write(
+            &legacy,
+            "ui_scale=1.25\nmax_items_per_row=4\nshow_hidden=true\nshow_thumbnails=nope\nterminal_command=kitty\nopener=ext|txt|terminal|nano %f\nopener=bogus|x|none|x\nunknown=1\n",
+        )
+        .unwrap();
+        let loaded = load_from(&scratch.0.join("config.toml"), &legacy);
+        assert_eq!(loaded.migrated_from.as_deref(), Some(legacy.as_path()));
+        assert!(!loaded.failed);
+        assert_eq!(loaded.config.display.ui_scale, 1.25);
+        assert_eq!(loaded.config.display.max_items_per_row, 4);
+        assert!(loaded.config.files.show_hidden);
+        assert!(loaded.config.files.show_thumbnails);
+        assert_eq!(loaded.config.openers.terminal_command, "kitty");
+        assert_eq!(loaded.config.openers.rules.len(), 1);
+        assert_eq!(loaded.config.openers.rules[0].command, "nano %f");
+        assert!(loaded.config.openers.rules[0].terminal);
+        assert_eq!(loaded.errors.len(), 2, "{:?}", loaded.errors);
+        assert!(loaded.errors[0].contains("line 4") && loaded.errors[0].contains("show_thumbnails"));
+        assert!(loaded.errors[1].contains("line 7"));
+    }
+
+    #[test]
+    fn no_config_and_no_legacy_file_gives_the_defaults() {
+        let scratch = Scratch::new("fresh");
+        let loaded = load_from(&scratch.0.join("config.toml"), &scratch.0.join(LEGACY_SETTINGS_FILE));
+        assert_eq!(loaded.config, Config::default());
+        assert!(loaded.errors.is_empty() && !loaded.failed && loaded.migrated_from.is_none());
+    }
+
+    #[test]
+    fn future_and_unknown_versions_are_refused() {
+        assert!(parse("version = 2\n").unwrap_err().contains("newer Datara"));
+        assert!(parse("version = 0\n").unwrap_err().contains("unsupported schema version 0"));
+        assert_eq!(parse("version = \"1\"\n").unwrap_err(), "'version' must be an integer");
+        assert_eq!(parse("[display]\n").unwrap_err(), "missing 'version'");
+    }
+
+    #[test]
+    fn invalid_files_are_backed_up_and_defaults_used() {
+        let scratch = Scratch::new("invalid");
+        let path = scratch.0.join("config.toml");
+        let contents = "version = 99\n[display]\nui_scale = 1.5\n";
+        std::fs::write(&path, contents).unwrap();
+        let loaded = load_from(&path, &scratch.0.join(LEGACY_SETTINGS_FILE));
+        assert!(loaded.failed);
+        assert_eq!(loaded.config, Config::default());
+        assert!(loaded.errors[0].contains("config.toml.bak"), "{:?}", loaded.errors);
+        assert_eq!(std::fs::read_to_string(path.with_extension("toml.bak")).unwrap(), contents);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+
+        std::fs::write(&path, "version = 1\ndisplay = 3\n").unwrap();
+        let loaded = load_from(&path, &scratch.0.join(LEGACY_SETTINGS_FILE));
+        assert!(loaded.failed);
+        assert!(loaded.errors[0].contains("display"), "{:?}", loaded.errors);
+    }
+
+    #[test]
+    fn validate_clamps_and_explains() {
+        let mut config = Config::default();
+        config.display.ui_scale = 10.0;
+        config.display.horizontal_spacing = f32::NAN;
+        config.display.vertical_spacing = -1.0;
+        config.display.max_items_per_row = 9;
+        config.openers.terminal_command = "  ".to_string();
+        config.openers.rules.truncate(1);
+        config.openers.rules[0].command = "datara:nothing".to_string();
+        let errors = config.validate();
+        assert_eq!(errors.len(), 6, "{:?}", errors);
+        assert_eq!(config.display.ui_scale, 3.0);
+        assert_eq!(config.display.horizontal_spacing, DisplayConfig::default().horizontal_spacing);
+        assert_eq!(config.display.vertical_spacing, 0.0);
+        assert_eq!(config.display.max_items_per_row, 5);
+        assert_eq!(config.openers.terminal_command, DEFAULT_TERMINAL_COMMAND);
+        // Dropping every rule brings back the defaults
+        assert_eq!(config.openers.rules, openers::default_rules());
+        assert!(errors.iter().any(|err| err.contains("Unknown built-in viewer 'nothing'")));
+
+        let mut valid = Config::default();
+        assert!(valid.validate().is_empty());
+        assert_eq!(valid, Config::default());
+    }
+}
@@ -0,0 +1,188 @@
+use eframe::egui;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::{self, Config, LoadedConfig};
+
+/// Changes are written once the settings have been stable for this long.
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+/// How often the file is checked for external edits while the window has
+/// focus; it is checked again as soon as focus returns.
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What `ConfigStore::update` did this frame.
+pub enum ConfigEvent {
+    SaveFailed(String),
+    /// The file was edited by someone else and has been read back.
+    Reloaded(LoadedConfig),
+    /// The edited file could not be read back; the current settings stay.
+    ReloadFailed(Vec<String>),
+}
+
+/// Identifies one version of the file on disk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        Some(Self { modified: meta.modified().ok(), len: meta.len() })
+    }
+}
+
+/// Persists the settings with dirty tracking and debouncing, and picks up
+/// external edits of the config file.
+pub struct ConfigStore {
+    path: Option<PathBuf>,
+    /// What the file on disk holds; `None` until it has been read or written.
+    saved: Option<Config>,
+    dirty_since: Option<Instant>,
+    stamp: Option<FileStamp>,
+    last_check: Instant,
+    /// Last save failure, so a persistent one is reported only once.
+    save_error: Option<String>,
+    /// The last external edit could not be parsed. Nothing is saved until
+    /// it is fixed, so the user's half-finished edit isn't overwritten.
+    broken: bool,
+}
+
+impl ConfigStore {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path, saved: None, dirty_since: None, stamp: None, last_check: Instant::now(), save_error: None, broken: false }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Reads the config file; call once at startup.
+    pub fn load(&mut self) -> Option<LoadedConfig> {
+        let path = self.path.clone()?;
+        let loaded = config::load(&path);
+        self.stamp = FileStamp::of(&path);
+        // Migrated or repaired settings still need to be written out
+        let clean = loaded.errors.is_empty() && loaded.migrated_from.is_none() && self.stamp.is_some();
+        self.saved = clean.then(|| loaded.config.clone());
+        Some(loaded)
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty_since.is_some()
+    }
+
+    /// Compares `current` against the file, saving after `SAVE_DELAY` without
+    /// further changes, and checks for external edits. Call once per frame.
+    pub fn update(&mut self, ctx: &egui::Context, current: &Config) -> Option<ConfigEvent> {
+        let path = self.path.clone()?;
+        let now = Instant::now();
+        let focused = ctx.input(|i| i.focused);
+
+        if focused && now.duration_since(self.last_check) >= RELOAD_CHECK_INTERVAL {
+            self.last_check = now;
+            let stamp = FileStamp::of(&path);
+            if stamp.is_some() && stamp != self.stamp {
+                let loaded = config::load(&path);
+                self.stamp = stamp;
+                if loaded.failed {
+                    self.broken = true;
+                    return Some(ConfigEvent::ReloadFailed(loaded.errors));
+                }
+                // External edits win over unsaved local changes
+                self.broken = false;
+                self.saved = Some(loaded.config.clone());
+                self.dirty_since = None;
+                return Some(ConfigEvent::Reloaded(loaded));
+            }
+        }
+
+        if self.saved.as_ref() == Some(current) || self.broken {
+            self.dirty_since = None;
+        } else {
+            let dirty_since = *self.dirty_since.get_or_insert(now);
+            let elapsed = now.duration_since(dirty_since);
+            if elapsed < SAVE_DELAY {
+                ctx.request_repaint_after(SAVE_DELAY - elapsed);
+            } else {
+                return self.save(&path, current);
+            }
+        }
+        if focused {
+            ctx.request_repaint_after(RELOAD_CHECK_INTERVAL);
+        }
+        None
+    }
+
+    /// Writes pending changes immediately, e.g. on exit.
+    pub fn flush(&mut self, current: &Config) -> Option<ConfigEvent> {
+        let path = self.path.clone()?;
+        if self.saved.as_ref() == Some(current) || self.broken {
+            return None;
+        }
+        self.save(&path, current)
+    }
+
+    fn save(&mut self, path: &Path, current: &Config) -> Option<ConfigEvent> {
+        self.dirty_since = None;
+        match current.save(path) {
+            Ok(()) => {
+                self.saved = Some(current.clone());
+                self.stamp = FileStamp::of(path);
+                self.save_error = None;
+                None
+            }
+            Err(err) => {
+                let err = format!("Failed to save settings: {}", err);
+                // Retry on the next change rather than every frame
+                self.saved = Some(current.clone());
+                if self.save_error.as_ref() == Some(&err) {
+                    return None;
+                }
+                self.save_error = Some(err.clone());
+                Some(ConfigEvent::SaveFailed(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `update` in a frame of a focused window, as if the check
+    /// interval had passed.
+    fn update(store: &mut ConfigStore, ctx: &egui::Context, current: &Config) -> Option<ConfigEvent> {
+        store.last_check -= RELOAD_CHECK_INTERVAL;
+        let mut event = None;
+        let _ = ctx.run(egui::RawInput::default(), |ctx| event = store.update(ctx, current));
+        event
+    }
+
+    #[test]
+    fn broken_edits_keep_the_running_config() {
+        let dir = std::env::temp_dir().join(format!("datara-config-store-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let mut config = Config::default();
+        config.files.bookmarks.push("/srv/assets".to_string());
+        config.save(&path).unwrap();
+        let ctx = egui::Context::default();
+        let mut store = ConfigStore::new(Some(path.clone()));
+        assert_eq!(store.load().unwrap().config, config);
+
+        std::fs::write(&path, "version = 1\n[display\n").unwrap();
+        assert!(matches!(update(&mut store, &ctx, &config), Some(ConfigEvent::ReloadFailed(errors)) if !errors.is_empty()));
+        // Local changes don't overwrite the half-finished edit
+        let mut changed = config.clone();
+        changed.files.bookmarks.clear();
+        assert!(update(&mut store, &ctx, &changed).is_none());
+        assert!(store.flush(&changed).is_none());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "version = 1\n[display\n");
+
+        config.save(&path).unwrap();
+        assert!(matches!(update(&mut store, &ctx, &changed), Some(ConfigEvent::Reloaded(loaded)) if loaded.config == config));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,365 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+/// A subscriber that stops reading is dropped after this long.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Methods carried out by the window. `subscribe` is answered by the
+/// server itself.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum Command {
+    /// Goes to a path or URL, as if typed into the address bar.
+    Navigate { location: String },
+    CurrentDir {},
+    /// The entries shown in the current folder.
+    List {},
+    /// Selects an entry of the current folder, or nothing.
+    Select {
+        #[serde(default)]
+        path: Option<PathBuf>,
+    },
+    /// Opens an entry as a double click would.
+    Open { path: PathBuf },
+    Mkdir { path: PathBuf },
+    Rename { path: PathBuf, to: PathBuf },
+    /// Deletes a file or a folder with everything inside; answered once done.
+    Delete { path: PathBuf },
+    /// Copies a file or folder to a path that must not exist yet; answered
+    /// once done.
+    Copy { path: PathBuf, to: PathBuf },
+    /// Changes the given view settings and returns all of them.
+    SetView {
+        #[serde(default)]
+        grid: Option<bool>,
+        #[serde(default)]
+        show_hidden: Option<bool>,
+        #[serde(default)]
+        scale: Option<f32>,
+    },
+}
+
+impl Command {
+    pub const METHODS: [&'static str; 10] = ["navigate", "current_dir", "list", "select", "open", "mkdir", "rename", "delete", "copy", "set_view"];
+}
+
+/// Sent to subscribers as JSON-RPC notifications.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum Event {
+    /// The current folder changed, by navigating or switching tabs.
+    Navigated { location: String, tab: usize },
+    Selected { path: Option<String> },
+}
+
+impl Event {
+    pub const NAMES: [&'static str; 2] = ["navigated", "selected"];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Event::Navigated { .. } => "navigated",
+            Event::Selected { .. } => "selected",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    /// The command was understood but could not be carried out.
+    pub const FAILED: i64 = -32000;
+
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+
+    pub fn failed(message: impl Into<String>) -> Self {
+        Self::new(Self::FAILED, message)
+    }
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(Self::INVALID_PARAMS, message)
+    }
+}
+
+impl From<io::Error> for RpcError {
+    fn from(err: io::Error) -> Self {
+        Self::failed(err.to_string())
+    }
+}
+
+pub type Outcome = Result<Value, RpcError>;
+
+/// Answers one call; may be moved to another thread, e.g. into a job.
+pub struct Reply(Sender<Outcome>);
+
+impl Reply {
+    pub fn send(self, outcome: Outcome) {
+        // The client may have hung up meanwhile
+        let _ = self.0.send(outcome);
+    }
+}
+
+/// A command waiting for the window to carry it out.
+pub struct Call {
+    pub command: Command,
+    pub reply: Reply,
+}
+
+enum Parsed {
+    Command(Command),
+    Subscribe(Vec<String>),
+}
+
+/// Reads one request line. Errors carry the id to answer with, which is
+/// null when the request was unreadable.
+fn parse_request(line: &str) -> Result<(Option<Value>, Parsed), (Value, RpcError)> {
+    let request: Value = serde_json::from_str(line).map_err(|err| (Value::Null, RpcError::new(RpcError::PARSE_ERROR, err.to_string())))?;
+    let id = request.get("id").cloned();
+    let invalid = |message: &str| (id.clone().unwrap_or(Value::Null), RpcError::new(RpcError::INVALID_REQUEST, message));
+    if request.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Err(invalid("not a JSON-RPC 2.0 request"));
+    }
+    let method = request.get("method").and_then(Value::as_str).ok_or_else(|| invalid("method must be a string"))?;
+    let params = match request.get("params") {
+        None | Some(Value::Null) => json!({}),
+        Some(params) => params.clone(),
+    };
+    let params_error = |err: serde_json::Error| (id.clone().unwrap_or(Value::Null), RpcError::invalid_params(err.to_string()));
+    if method == "subscribe" {
+        #[derive(Deserialize)]
+        struct Subscribe {
+            #[serde(default)]
+            events: Option<Vec<String>>,
+        }
+        let events = serde_json::from_value::<Subscribe>(params).map_err(params_error)?.events.unwrap_or_else(|| Event::NAMES.map(String::from).to_vec());
+        if let Some(unknown) = events.iter().find(|event| !Event::NAMES.contains(&event.as_str())) {
+            return Err((id.unwrap_or(Value::Null), RpcError::invalid_params(format!("unknown event '{}'", unknown))));
+        }
+        return Ok((id, Parsed::Subscribe(events)));
+    }
+    if !Command::METHODS.contains(&method) {
+        return Err((id.unwrap_or(Value::Null), RpcError::new(RpcError::METHOD_NOT_FOUND, format!("unknown method '{}'", method))));
+    }
+    let command = serde_json::from_value(json!({ "method": method, "params": params })).map_err(params_error)?;
+    Ok((id, Parsed::Command(command)))
+}
+
+fn response(id: Value, outcome: Outcome) -> Value {
+    match outcome {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    }
+}
+
+fn write_line(writer: &Mutex<UnixStream>, message: &Value) -> io::Result<()> {
+    let mut line = message.to_string();
+    line.push('\n');
+    writer.lock().map_err(|_| io::Error::other("connection poisoned"))?.write_all(line.as_bytes())
+}
+
+struct Subscriber {
+    events: Vec<String>,
+    writer: Arc<Mutex<UnixStream>>,
+}
+
+type Wake = Arc<dyn Fn() + Send + Sync>;
+
+/// JSON-RPC 2.0 over a Unix socket, one message per line, with params
+/// given by name. Any number of clients may connect; each one's calls are
+/// answered in order.
+pub struct Control {
+    calls: Receiver<Call>,
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+    path: PathBuf,
+}
+
+/// Binds `socket` with mode 0600 from the start. It is bound in a private
+/// folder beside it and linked into place, so it's never reachable with the
+/// permissions the umask gives.
+fn bind_private(socket: &Path) -> io::Result<UnixListener> {
+    let parent = socket.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let dir = parent.join(format!(".datara-control-{}", std::process::id()));
+    std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    let staged = dir.join("socket");
+    let bound = UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+        // Unlike a rename, fails if another instance took the path meanwhile
+        std::fs::hard_link(&staged, socket)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_file(&staged);
+    let _ = std::fs::remove_dir(&dir);
+    bound
+}
+
+impl Control {
+    /// Listens on `socket`, readable by this user only. `wake` is called
+    /// whenever a call is waiting.
+    pub fn listen(socket: &Path, wake: impl Fn() + Send + Sync + 'static) -> io::Result<Self> {
+        if UnixStream::connect(socket).is_ok() {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, "another Datara is listening there"));
+        }
+        // Left behind by an instance that crashed; anything else is kept
+        if std::fs::symlink_metadata(socket).is_ok_and(|meta| meta.file_type().is_socket()) {
+            std::fs::remove_file(socket)?;
+        }
+        let listener = bind_private(socket)?;
+
+        let (sender, calls) = mpsc::channel();
+        let subscribers = Arc::new(Mutex::new(Vec::new()));
+        let wake: Wake = Arc::new(wake);
+        let thread_subscribers = subscribers.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                let (sender, subscribers, wake) = (sender.clone(), thread_subscribers.clone(), wake.clone());
+                std::thread::spawn(move || serve_connection(stream, sender, subscribers, wake));
+            }
+        });
+        Ok(Self { calls, subscribers, path: socket.to_path_buf() })
+    }
+
+    /// The calls that arrived since the last poll; call once per frame and
+    /// answer each one.
+    pub fn poll(&self) -> Vec<Call> {
+        self.calls.try_iter().collect()
+    }
+
+    /// Tells the clients that subscribed to this kind of event.
+    pub fn notify(&self, event: &Event) {
+        let Ok(mut subscribers) = self.subscribers.lock() else { return };
+        if subscribers.is_empty() {
+            return;
+        }
+        let mut message = serde_json::to_value(event).unwrap_or_default();
+        message["jsonrpc"] = json!("2.0");
+        subscribers.retain(|subscriber| !subscriber.events.iter().any(|name| name == event.name()) || write_line(&subscriber.writer, &message).is_ok());
+    }
+}
+
+impl Drop for Control {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn serve_connection(stream: UnixStream, calls: Sender<Call>, subscribers: Arc<Mutex<Vec<Subscriber>>>, wake: Wake) {
+    let Ok(writer) = stream.try_clone() else { return };
+    let _ = writer.set_write_timeout(Some(WRITE_TIMEOUT));
+    let writer = Arc::new(Mutex::new(writer));
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (id, outcome) = match parse_request(&line) {
+            Err((id, error)) => (Some(id), Err(error)),
+            Ok((id, Parsed::Subscribe(events))) => {
+                if let Ok(mut subscribers) = subscribers.lock() {
+                    subscribers.push(Subscriber { events, writer: writer.clone() });
+                }
+                (id, Ok(json!(true)))
+            }
+            Ok((id, Parsed::Command(command))) => {
+                let (reply, outcome) = mpsc::channel();
+                if calls.send(Call { command, reply: Reply(reply) }).is_err() {
+                    break;
+                }
+                wake();
+                (id, outcome.recv().unwrap_or_else(|_| Err(RpcError::failed("Datara is closing"))))
+            }
+        };
+        // Requests without an id are notifications and get no response
+        if let Some(id) = id
+            && write_line(&writer, &response(id, outcome)).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(line: &str) -> Command {
+        match parse_request(line) {
+            Ok((_, Parsed::Command(command))) => command,
+            _ => panic!("{} should parse as a command", line),
+        }
+    }
+
+    fn error_code(line: &str) -> i64 {
+        parse_request(line).err().map(|(_, error)| error.code).unwrap_or_default()
+    }
+
+    #[test]
+    fn parses_commands_with_and_without_params() {
+        assert_eq!(command(r#"{"jsonrpc":"2.0","id":1,"method":"navigate","params":{"location":"/tmp"}}"#), Command::Navigate { location: "/tmp".to_string() });
+        assert_eq!(command(r#"{"jsonrpc":"2.0","id":1,"method":"list"}"#), Command::List {});
+        assert_eq!(command(r#"{"jsonrpc":"2.0","id":1,"method":"select","params":{"path":null}}"#), Command::Select { path: None });
+        assert_eq!(command(r#"{"jsonrpc":"2.0","id":1,"method":"rename","params":{"path":"/a","to":"/b"}}"#), Command::Rename { path: PathBuf::from("/a"), to: PathBuf::from("/b") });
+        assert_eq!(command(r#"{"jsonrpc":"2.0","id":1,"method":"set_view","params":{"grid":false}}"#), Command::SetView { grid: Some(false), show_hidden: None, scale: None });
+        assert!(matches!(parse_request(r#"{"jsonrpc":"2.0","method":"subscribe","params":{"events":["navigated"]}}"#), Ok((None, Parsed::Subscribe(events))) if events == ["navigated"]));
+    }
+
+    #[test]
+    fn bad_requests_get_json_rpc_error_codes() {
+        assert_eq!(error_code("{not json"), RpcError::PARSE_ERROR);
+        assert_eq!(error_code(r#"{"id":1,"method":"list"}"#), RpcError::INVALID_REQUEST);
+        assert_eq!(error_code(r#"{"jsonrpc":"2.0","id":1,"method":"format_disk"}"#), RpcError::METHOD_NOT_FOUND);
+        assert_eq!(error_code(r#"{"jsonrpc":"2.0","id":1,"method":"open","params":{}}"#), RpcError::INVALID_PARAMS);
+        assert_eq!(error_code(r#"{"jsonrpc":"2.0","id":1,"method":"subscribe","params":{"events":["exploded"]}}"#), RpcError::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn calls_are_answered_and_events_delivered() {
+        let socket = std::env::temp_dir().join(format!("datara-control-{}.sock", std::process::id()));
+        let control = Control::listen(&socket, || {}).unwrap();
+        assert_eq!(std::fs::metadata(&socket).unwrap().permissions().mode() & 0o777, 0o600);
+        let client = UnixStream::connect(&socket).unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut lines = BufReader::new(client.try_clone().unwrap()).lines();
+        let send = |line: &str| (&client).write_all(format!("{}\n", line).as_bytes()).unwrap();
+
+        send(r#"{"jsonrpc":"2.0","id":1,"method":"subscribe","params":{"events":["navigated"]}}"#);
+        let subscribed: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(subscribed, json!({ "jsonrpc": "2.0", "id": 1, "result": true }));
+
+        send(r#"{"jsonrpc":"2.0","id":"a","method":"current_dir"}"#);
+        let call = loop {
+            if let Some(call) = control.poll().pop() {
+                break call;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(call.command, Command::CurrentDir {});
+        call.reply.send(Ok(json!({ "location": "/tmp" })));
+        let answered: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(answered, json!({ "jsonrpc": "2.0", "id": "a", "result": { "location": "/tmp" } }));
+
+        control.notify(&Event::Selected { path: None });
+        control.notify(&Event::Navigated { location: "/srv".to_string(), tab: 1 });
+        let event: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(event, json!({ "jsonrpc": "2.0", "method": "navigated", "params": { "location": "/srv", "tab": 1 } }));
+
+        drop(control);
+        assert!(!socket.exists());
+    }
+}
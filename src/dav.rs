@@ -0,0 +1,541 @@
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+use percent_encoding::{AsciiSet, CONTROLS, percent_decode_str, percent_encode};
+use quick_xml::events::Event;
+
+use crate::remote::{self, RemoteUrl, Scheme};
+use crate::vfs::{Metadata, Vfs, VfsEntry};
+
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Bytes escaped in a path segment; `/` separates segments and stays.
+const SEGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'#').add(b'%').add(b'<').add(b'>').add(b'?').add(b'[').add(b']').add(b'\\').add(b'^').add(b'`').add(b'{').add(b'|').add(b'}');
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<propfind xmlns="DAV:"><prop><resourcetype/><getcontentlength/><getlastmodified/><getetag/></prop></propfind>"#;
+
+/// HTTP connection settings shared by the filesystem and its uploads.
+#[derive(Clone)]
+struct Client {
+    agent: ureq::Agent,
+    /// `http(s)://host:port`.
+    origin: String,
+    /// `Authorization` header value.
+    auth: Option<String>,
+}
+
+impl Client {
+    fn url(&self, path: &Path, collection: bool) -> String {
+        let mut url = format!("{}{}", self.origin, percent_encode(path.as_os_str().as_bytes(), SEGMENT));
+        if collection && !url.ends_with('/') {
+            url.push('/');
+        }
+        url
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        let request = self.agent.request(method, url);
+        match &self.auth {
+            Some(auth) => request.set("Authorization", auth),
+            None => request,
+        }
+    }
+
+    /// Sends `method` to `path`, and once more to `path/` when the server
+    /// redirects there, as servers do for collections. `prepare` adds headers
+    /// and learns whether the request goes to the collection form.
+    fn call(&self, method: &str, path: &Path, body: &str, prepare: impl Fn(ureq::Request, bool) -> ureq::Request) -> io::Result<ureq::Response> {
+        let send = |collection: bool| prepare(self.request(method, &self.url(path, collection)), collection).send_string(body).map_err(http_error);
+        let collection = path.parent().is_none();
+        let response = send(collection)?;
+        if !collection && (300..400).contains(&response.status()) {
+            return send(true);
+        }
+        Ok(response)
+    }
+
+    fn propfind(&self, path: &Path, depth: &str) -> io::Result<Vec<DavEntry>> {
+        let response = self.call("PROPFIND", path, PROPFIND_BODY, |request, _| request.set("Depth", depth).set("Content-Type", "application/xml; charset=utf-8"))?;
+        if response.status() != 207 {
+            return Err(io::Error::other(format!("PROPFIND answered {} {}", response.status(), response.status_text())));
+        }
+        parse_multistatus(&response.into_string()?)
+    }
+}
+
+fn http_error(err: ureq::Error) -> io::Error {
+    match err {
+        ureq::Error::Status(code, response) => {
+            let kind = match code {
+                401 | 403 => io::ErrorKind::PermissionDenied,
+                // 409 Conflict: the parent collection is missing
+                404 | 409 => io::ErrorKind::NotFound,
+                412 => io::ErrorKind::AlreadyExists,
+                507 => io::ErrorKind::StorageFull,
+                _ => io::ErrorKind::Other,
+            };
+            let hint = if code == 401 { " (add a login to ~/.netrc)" } else { "" };
+            io::Error::new(kind, format!("{} {}{}", code, response.status_text(), hint))
+        }
+        ureq::Error::Transport(transport) => io::Error::other(transport.to_string()),
+    }
+}
+
+/// A WebDAV share. Credentials come from `~/.netrc`, as for curl; files
+/// opened by other programs are downloaded into the user's cache folder
+/// and reused until the server reports a change.
+pub struct DavFs {
+    client: Client,
+    cache: Option<PathBuf>,
+}
+
+impl DavFs {
+    /// Connects and checks that `url` can be listed.
+    pub fn connect(url: &RemoteUrl) -> io::Result<Self> {
+        let http = if url.scheme == Scheme::Davs { "https" } else { "http" };
+        let host = if url.host.contains(':') { format!("[{}]", url.host) } else { url.host.clone() };
+        let auth = std::env::var_os("HOME")
+            .and_then(|home| std::fs::read_to_string(PathBuf::from(home).join(".netrc")).ok())
+            .and_then(|netrc| netrc_login(&netrc, &url.host, url.user.as_deref()))
+            .map(|(login, password)| format!("Basic {}", remote::base64(format!("{}:{}", login, password).as_bytes())));
+        let client = Client {
+            agent: ureq::AgentBuilder::new().timeout_connect(TIMEOUT).timeout(TIMEOUT).redirects(0).build(),
+            origin: format!("{}://{}:{}", http, host, url.port_or_default()),
+            auth,
+        };
+        client.propfind(&url.path, "0")?;
+        let cache = cache_root().map(|root| root.join(format!("{}-{}", url.host, url.port_or_default())));
+        Ok(Self { client, cache })
+    }
+}
+
+/// `$XDG_CACHE_HOME/datara/dav`, falling back to `~/.cache`.
+fn cache_root() -> Option<PathBuf> {
+    let cache_home = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(cache_home.join("datara").join("dav"))
+}
+
+impl Vfs for DavFs {
+    fn list(&self, dir: &Path) -> io::Result<Vec<VfsEntry>> {
+        let entries = self.client.propfind(dir, "1")?;
+        Ok(entries
+            .into_iter()
+            // The folder itself is part of the answer
+            .filter(|entry| entry.path != dir)
+            .filter_map(|entry| Some(VfsEntry { name: entry.path.file_name()?.to_os_string(), metadata: Some(entry.metadata()) }))
+            .collect())
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<Metadata> {
+        let entries = self.client.propfind(path, "0")?;
+        entries.first().map(DavEntry::metadata).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} is not on the server", path.display())))
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        let response = self.client.request("GET", &self.client.url(path, false)).call().map_err(http_error)?;
+        if response.status() != 200 {
+            return Err(io::Error::other(format!("GET answered {} {}", response.status(), response.status_text())));
+        }
+        Ok(response.into_reader())
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        Ok(Box::new(Upload { client: self.client.clone(), url: self.client.url(path, false), spool: spool_file()?, pending: true }))
+    }
+
+    /// Never replaces `to`; the server answers 412 if it exists.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.client.call("MOVE", from, "", |request, collection| request.set("Destination", &self.client.url(to, collection)).set("Overwrite", "F"))?;
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        // DELETE takes whole collections; keep to the `Vfs` contract
+        if self.stat(path)?.is_dir && !self.list(path)?.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::DirectoryNotEmpty, format!("{} is not empty", path.display())));
+        }
+        self.client.call("DELETE", path, "", |request, _| request)?;
+        Ok(())
+    }
+
+    fn mkdir(&self, path: &Path) -> io::Result<()> {
+        match self.client.request("MKCOL", &self.client.url(path, true)).call() {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(405, _)) => Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", path.display()))),
+            Err(err) => Err(http_error(err)),
+        }
+    }
+
+    fn cache_path(&self, path: &Path) -> Option<PathBuf> {
+        let entry = self.client.propfind(path, "0").ok()?.into_iter().next()?;
+        let version = match &entry.etag {
+            Some(etag) => etag.clone(),
+            None => format!("{:?}-{:?}", entry.modified, entry.len),
+        };
+        let key = md5::compute([path.as_os_str().as_bytes(), version.as_bytes()].concat());
+        Some(self.cache.as_ref()?.join(format!("{:x}", key)).join(path.file_name()?))
+    }
+}
+
+/// An unnamed temporary file.
+fn spool_file() -> io::Result<File> {
+    static SPOOLS: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!("datara-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("upload-{}", SPOOLS.fetch_add(1, Ordering::Relaxed)));
+    let file = File::options().read(true).write(true).create_new(true).open(&path)?;
+    std::fs::remove_file(&path)?;
+    Ok(file)
+}
+
+/// Collects the data in a temporary file and PUTs it on `flush`; servers
+/// need the length up front. Dropping it unflushed discards the data, so an
+/// abandoned copy leaves nothing on the server.
+struct Upload {
+    client: Client,
+    url: String,
+    spool: File,
+    pending: bool,
+}
+
+impl Upload {
+    fn send(&mut self) -> io::Result<()> {
+        let len = self.spool.stream_position()?;
+        self.spool.rewind()?;
+        let result = self.client.request("PUT", &self.url).set("Content-Length", &len.to_string()).send(&mut self.spool);
+        self.pending = false;
+        result.map_err(http_error)?;
+        Ok(())
+    }
+}
+
+impl Write for Upload {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending = true;
+        self.spool.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.pending { self.send() } else { Ok(()) }
+    }
+}
+
+/// One `<response>` of a PROPFIND answer.
+#[derive(Debug, Default, PartialEq)]
+struct DavEntry {
+    /// Decoded from the href, without a trailing `/`.
+    path: PathBuf,
+    is_dir: bool,
+    len: Option<u64>,
+    modified: Option<SystemTime>,
+    etag: Option<String>,
+}
+
+impl DavEntry {
+    fn metadata(&self) -> Metadata {
+        Metadata { is_dir: self.is_dir, len: if self.is_dir { 0 } else { self.len.unwrap_or(0) }, modified: self.modified }
+    }
+}
+
+/// Path of an href, which may be a full URL, with `%XX` escapes decoded.
+fn href_path(href: &str) -> PathBuf {
+    let path = match href.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |slash| &rest[slash..]),
+        None => href,
+    };
+    let bytes: Vec<u8> = percent_decode_str(path).collect();
+    let trimmed = match bytes.iter().rposition(|&b| b != b'/') {
+        Some(last) => &bytes[..=last],
+        None => b"/",
+    };
+    PathBuf::from(OsString::from_vec(trimmed.to_vec()))
+}
+
+/// Reads a 207 Multi-Status body. Elements are matched by local name, as
+/// servers pick their own prefix for `DAV:`; properties are only taken from
+/// a `<propstat>` whose status is 200.
+fn parse_multistatus(xml: &str) -> io::Result<Vec<DavEntry>> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut stack: Vec<Vec<u8>> = Vec::new();
+    let mut entries = Vec::new();
+    let mut entry = DavEntry::default();
+    let mut found = DavEntry::default();
+    let mut status_ok = false;
+    loop {
+        let event = reader.read_event().map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        match event {
+            Event::Start(start) => {
+                let name = start.local_name().as_ref().to_vec();
+                match name.as_slice() {
+                    b"response" => entry = DavEntry::default(),
+                    b"propstat" => (found, status_ok) = (DavEntry::default(), false),
+                    b"collection" => found.is_dir = true,
+                    _ => {}
+                }
+                stack.push(name);
+            }
+            Event::Empty(empty) if empty.local_name().as_ref() == b"collection" => found.is_dir = true,
+            Event::Text(text) => {
+                let text = text.unescape().map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                match stack.last().map(Vec::as_slice) {
+                    Some(b"href") if stack.iter().rev().nth(1).is_some_and(|parent| parent == b"response") => entry.path = href_path(&text),
+                    Some(b"status") => status_ok = text.split_whitespace().nth(1) == Some("200"),
+                    Some(b"getcontentlength") => found.len = text.trim().parse().ok(),
+                    Some(b"getlastmodified") => found.modified = httpdate::parse_http_date(text.trim()).ok(),
+                    Some(b"getetag") => found.etag = Some(text.into_owned()),
+                    _ => {}
+                }
+            }
+            Event::End(end) => {
+                stack.pop();
+                match end.local_name().as_ref() {
+                    b"propstat" if status_ok => {
+                        let found = std::mem::take(&mut found);
+                        entry.is_dir |= found.is_dir;
+                        entry.len = entry.len.or(found.len);
+                        entry.modified = entry.modified.or(found.modified);
+                        entry.etag = entry.etag.take().or(found.etag);
+                    }
+                    b"response" => entries.push(std::mem::take(&mut entry)),
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+/// Login and password for `host` from a `.netrc`: the first `machine` entry
+/// for the host, with the given login if any, else the `default` entry.
+fn netrc_login(netrc: &str, host: &str, user: Option<&str>) -> Option<(String, String)> {
+    struct Machine<'a> {
+        name: Option<&'a str>,
+        login: Option<&'a str>,
+        password: Option<&'a str>,
+    }
+    let mut machines: Vec<Machine> = Vec::new();
+    let mut tokens = netrc.split_whitespace();
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" => machines.push(Machine { name: tokens.next(), login: None, password: None }),
+            "default" => machines.push(Machine { name: None, login: None, password: None }),
+            "login" => if let Some(machine) = machines.last_mut() { machine.login = tokens.next() },
+            "password" => if let Some(machine) = machines.last_mut() { machine.password = tokens.next() },
+            _ => {}
+        }
+    }
+    let usable = |machine: &&Machine| machine.password.is_some() && (user.is_none() || machine.login == user);
+    let machine = machines.iter().filter(usable).find(|machine| machine.name == Some(host)).or_else(|| machines.iter().filter(usable).find(|machine| machine.name.is_none()))?;
+    Some((machine.login.or(user)?.to_string(), machine.password?.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeMap, HashMap};
+    use std::io::{BufRead, BufReader};
+    use std::net::{TcpListener, TcpStream};
+
+    /// Contents by path on the test server; collections have none.
+    type Files = BTreeMap<PathBuf, Option<Vec<u8>>>;
+
+    const LISTING: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response>
+    <d:href>/share/</d:href>
+    <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>http://nas:8080/share/My%20Photos/</d:href>
+    <d:propstat>
+      <d:prop><d:resourcetype><d:collection></d:collection></d:resourcetype><d:getlastmodified>Tue, 15 Nov 1994 08:12:31 GMT</d:getlastmodified></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/share/notes%20&amp;%20todo.txt</d:href>
+    <d:propstat><d:prop><d:resourcetype/><d:getcontentlength>42</d:getcontentlength><d:getetag>"abc"</d:getetag></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>
+    <d:propstat><d:prop><d:getlastmodified/></d:prop><d:status>HTTP/1.1 404 Not Found</d:status></d:propstat>
+  </d:response>
+</d:multistatus>"#;
+
+    #[test]
+    fn multistatus_entries_keep_only_successful_properties() {
+        let entries = parse_multistatus(LISTING).unwrap();
+        let paths: Vec<&Path> = entries.iter().map(|entry| entry.path.as_path()).collect();
+        assert_eq!(paths, [Path::new("/share"), Path::new("/share/My Photos"), Path::new("/share/notes & todo.txt")]);
+        assert!(entries[0].is_dir && entries[1].is_dir && !entries[2].is_dir);
+        assert_eq!(entries[1].modified, Some(SystemTime::UNIX_EPOCH + Duration::from_secs(784887151)));
+        assert_eq!(entries[2].len, Some(42));
+        assert_eq!(entries[2].modified, None);
+        assert_eq!(entries[2].etag.as_deref(), Some("\"abc\""));
+    }
+
+    #[test]
+    fn hrefs_decode_to_paths() {
+        assert_eq!(href_path("https://host/a%2Fb/c%FF/"), PathBuf::from(OsString::from_vec(b"/a/b/c\xFF".to_vec())));
+        assert_eq!(href_path("/"), Path::new("/"));
+        assert_eq!(href_path("http://host"), Path::new("/"));
+    }
+
+    #[test]
+    fn netrc_prefers_the_matching_machine() {
+        let netrc = "machine other login x password y\nmachine nas login ada password s3cret\nmachine nas login bob password hunter2\ndefault login anon password guest";
+        assert_eq!(netrc_login(netrc, "nas", None), Some(("ada".to_string(), "s3cret".to_string())));
+        assert_eq!(netrc_login(netrc, "nas", Some("bob")), Some(("bob".to_string(), "hunter2".to_string())));
+        assert_eq!(netrc_login(netrc, "elsewhere", None), Some(("anon".to_string(), "guest".to_string())));
+        assert_eq!(netrc_login("machine nas login ada", "nas", None), None);
+    }
+
+    /// A WebDAV server on loopback keeping its files in memory, with just
+    /// enough of the protocol for `DavFs`.
+    fn serve() -> RemoteUrl {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut files: Files = [(PathBuf::from("/"), None)].into();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                let _ = answer(&mut files, stream);
+            }
+        });
+        RemoteUrl::parse(&format!("dav://127.0.0.1:{}/", port)).unwrap()
+    }
+
+    fn answer(files: &mut Files, mut stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let mut words = line.split_whitespace();
+        let (method, path) = (words.next().unwrap_or_default().to_string(), href_path(words.next().unwrap_or("/")));
+        let mut headers = HashMap::new();
+        loop {
+            line.clear();
+            reader.read_line(&mut line)?;
+            let Some((name, value)) = line.trim_end().split_once(": ") else { break };
+            headers.insert(name.to_ascii_lowercase(), value.to_string());
+        }
+        let mut body = vec![0; headers.get("content-length").and_then(|len| len.parse().ok()).unwrap_or(0)];
+        reader.read_exact(&mut body)?;
+
+        let parent_is_dir = |files: &Files, path: &Path| path.parent().is_some_and(|parent| matches!(files.get(parent), Some(None)));
+        let (status, content) = match method.as_str() {
+            "PROPFIND" if files.contains_key(&path) => {
+                let depth_one = headers.get("depth").is_some_and(|depth| depth == "1");
+                let mut xml = String::from(r#"<?xml version="1.0"?><d:multistatus xmlns:d="DAV:">"#);
+                for (entry, data) in files.iter().filter(|(entry, _)| **entry == path || (depth_one && entry.parent() == Some(path.as_path()))) {
+                    let (resource, len) = match data {
+                        Some(data) => (String::new(), data.len()),
+                        None => ("<d:collection/>".to_string(), 0),
+                    };
+                    let href = percent_encode(entry.as_os_str().as_bytes(), SEGMENT);
+                    xml.push_str(&format!("<d:response><d:href>{}</d:href><d:propstat><d:prop><d:resourcetype>{}</d:resourcetype><d:getcontentlength>{}</d:getcontentlength></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>", href, resource, len));
+                }
+                xml.push_str("</d:multistatus>");
+                ("207 Multi-Status", xml.into_bytes())
+            }
+            "GET" => match files.get(&path) {
+                Some(Some(data)) => ("200 OK", data.clone()),
+                _ => ("404 Not Found", Vec::new()),
+            },
+            "PUT" if parent_is_dir(files, &path) => {
+                files.insert(path, Some(body));
+                ("201 Created", Vec::new())
+            }
+            "MKCOL" if files.contains_key(&path) => ("405 Method Not Allowed", Vec::new()),
+            "MKCOL" if parent_is_dir(files, &path) => {
+                files.insert(path, None);
+                ("201 Created", Vec::new())
+            }
+            "DELETE" if files.contains_key(&path) => {
+                files.retain(|entry, _| !entry.starts_with(&path));
+                ("204 No Content", Vec::new())
+            }
+            "MOVE" if files.contains_key(&path) => {
+                let to = href_path(headers.get("destination").map_or("/", String::as_str));
+                if files.contains_key(&to) && headers.get("overwrite").is_some_and(|overwrite| overwrite == "F") {
+                    ("412 Precondition Failed", Vec::new())
+                } else {
+                    let moved: Vec<PathBuf> = files.keys().filter(|entry| entry.starts_with(&path)).cloned().collect();
+                    for entry in moved {
+                        let data = files.remove(&entry).unwrap();
+                        files.insert(to.join(entry.strip_prefix(&path).unwrap()), data);
+                    }
+                    ("201 Created", Vec::new())
+                }
+            }
+            "PROPFIND" | "DELETE" | "MOVE" => ("404 Not Found", Vec::new()),
+            _ => ("409 Conflict", Vec::new()),
+        };
+        write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, content.len())?;
+        stream.write_all(&content)
+    }
+
+    #[test]
+    fn round_trip_against_a_loopback_server() {
+        let url = serve();
+        let fs = DavFs::connect(&url).unwrap();
+        let dir = url.path.join("datara test");
+        fs.mkdir(&dir).unwrap();
+        assert_eq!(fs.mkdir(&dir).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        for name in ["a.txt", "c.txt"] {
+            let mut upload = fs.open_write(&dir.join(name)).unwrap();
+            upload.write_all(b"hello").unwrap();
+            upload.flush().unwrap();
+        }
+        fs.rename(&dir.join("a.txt"), &dir.join("b.txt")).unwrap();
+        // Renaming never replaces what is there
+        assert_eq!(fs.rename(&dir.join("b.txt"), &dir.join("c.txt")).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+
+        let mut names: Vec<OsString> = fs.list(&dir).unwrap().into_iter().map(|entry| entry.name).collect();
+        names.sort();
+        assert_eq!(names, ["b.txt", "c.txt"]);
+        assert_eq!(fs.stat(&dir.join("b.txt")).unwrap().len, 5);
+        assert!(fs.stat(&dir).unwrap().is_dir);
+        assert_eq!(fs.read_head(&dir.join("b.txt"), 4).unwrap(), b"hell");
+        assert_eq!(fs.remove(&dir).unwrap_err().kind(), io::ErrorKind::DirectoryNotEmpty);
+
+        fs.remove(&dir.join("b.txt")).unwrap();
+        fs.remove(&dir.join("c.txt")).unwrap();
+        fs.remove(&dir).unwrap();
+        assert_eq!(fs.stat(&dir).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    /// Gives up after the first chunk, like a cancelled job.
+    struct CancelPartway;
+
+    impl crate::vfs::Progress for CancelPartway {
+        fn start(&self, _total_bytes: u64) {}
+
+        fn advance(&self, _name: &Path, _bytes: u64) -> io::Result<()> {
+            Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"))
+        }
+    }
+
+    #[test]
+    fn abandoned_uploads_are_not_sent() {
+        let url = serve();
+        let fs = DavFs::connect(&url).unwrap();
+        let source = crate::vfs::MemoryFs::default();
+        source.add_file("/big.bin", vec![7; 200 * 1024]).add_file("/empty", "");
+
+        let target = url.path.join("big.bin");
+        assert!(crate::vfs::copy_tree(&source, Path::new("/big.bin"), &fs, &target, &CancelPartway).is_err());
+        assert_eq!(fs.stat(&target).unwrap_err().kind(), io::ErrorKind::NotFound);
+        // Finished copies are sent, even when there was nothing to write
+        crate::vfs::copy_tree(&source, Path::new("/empty"), &fs, &url.path.join("empty"), &()).unwrap();
+        assert_eq!(fs.stat(&url.path.join("empty")).unwrap().len, 0);
+    }
+}
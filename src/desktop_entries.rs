@@ -0,0 +1,459 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::openers::split_command;
+
+/// An installed application, parsed from the `[Desktop Entry]` group of a `.desktop` file.
+#[derive(Debug, Clone)]
+pub struct DesktopEntry {
+    /// Desktop file ID, e.g. `org.gnome.gedit.desktop`.
+    pub id: String,
+    pub name: String,
+    pub exec: String,
+    pub icon: Option<String>,
+    pub terminal: bool,
+    pub mime_types: Vec<String>,
+    pub path: PathBuf,
+}
+
+impl DesktopEntry {
+    pub fn parse(id: &str, path: &Path, contents: &str) -> Option<Self> {
+        let mut in_main_group = false;
+        let mut fields: HashMap<&str, &str> = HashMap::new();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                in_main_group = line == "[Desktop Entry]";
+                continue;
+            }
+            if !in_main_group || line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                fields.entry(key.trim()).or_insert(value.trim());
+            }
+        }
+        if fields.get("Type").copied() != Some("Application") || fields.get("Hidden").copied() == Some("true") {
+            return None;
+        }
+        let exec = unescape_string(fields.get("Exec")?);
+        let mime_types = fields
+            .get("MimeType")
+            .map(|value| value.split(';').filter(|m| !m.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default();
+        Some(Self {
+            id: id.to_string(),
+            name: unescape_string(fields.get("Name")?),
+            exec,
+            icon: fields.get("Icon").map(|icon| unescape_string(icon)),
+            terminal: fields.get("Terminal").copied() == Some("true"),
+            mime_types,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Expands the `Exec` field codes for `file`. Programs whose `Exec` takes no
+    /// file argument get the file appended, as most file managers do.
+    pub fn command(&self, file: &Path, terminal_command: &str) -> Option<(OsString, Vec<OsString>)> {
+        let mut argv: Vec<OsString> = Vec::new();
+        if self.terminal {
+            argv.extend(split_command(terminal_command).into_iter().map(OsString::from));
+        }
+        let mut has_file_code = false;
+        for word in split_command(&self.exec) {
+            match word.as_str() {
+                "%f" | "%F" | "%u" | "%U" => {
+                    argv.push(file.as_os_str().to_os_string());
+                    has_file_code = true;
+                }
+                "%i" => {
+                    if let Some(icon) = &self.icon {
+                        argv.push("--icon".into());
+                        argv.push(icon.into());
+                    }
+                }
+                _ => {
+                    let mut arg = OsString::new();
+                    let mut chars = word.chars();
+                    while let Some(c) = chars.next() {
+                        if c != '%' {
+                            arg.push(c.encode_utf8(&mut [0; 4]));
+                            continue;
+                        }
+                        match chars.next() {
+                            Some('f' | 'F' | 'u' | 'U') => {
+                                arg.push(file.as_os_str());
+                                has_file_code = true;
+                            }
+                            Some('c') => arg.push(&self.name),
+                            Some('k') => arg.push(self.path.as_os_str()),
+                            Some('%') => arg.push("%"),
+                            // Deprecated and unknown codes are removed
+                            _ => {}
+                        }
+                    }
+                    if !arg.is_empty() {
+                        argv.push(arg);
+                    }
+                }
+            }
+        }
+        if !has_file_code {
+            argv.push(file.as_os_str().to_os_string());
+        }
+        let mut argv = argv.into_iter();
+        let program = argv.next()?;
+        Some((program, argv.collect()))
+    }
+}
+
+/// Undoes the `\s`, `\n`, `\t`, `\r` and `\\` escapes of desktop entry string values.
+fn unescape_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => out.push(' '),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                // Keep other escapes for the Exec quoting rules
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[derive(Debug, Default)]
+struct MimeApps {
+    defaults: HashMap<String, Vec<String>>,
+    added: HashMap<String, Vec<String>>,
+    removed: HashMap<String, Vec<String>>,
+}
+
+impl MimeApps {
+    /// Merges one `mimeapps.list`; files read earlier take precedence.
+    fn merge(&mut self, contents: &str) {
+        let mut section = "";
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                section = line;
+                continue;
+            }
+            let Some((mime, apps)) = line.split_once('=') else { continue };
+            let apps: Vec<String> = apps.split(';').map(str::trim).filter(|a| !a.is_empty()).map(str::to_string).collect();
+            let map = match section {
+                "[Default Applications]" => &mut self.defaults,
+                "[Added Associations]" => &mut self.added,
+                "[Removed Associations]" => &mut self.removed,
+                _ => continue,
+            };
+            map.entry(mime.trim().to_string()).or_default().extend(apps);
+        }
+    }
+}
+
+fn env_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    match std::env::var_os(var) {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)),
+    }
+}
+
+fn env_dirs(var: &str, fallback: &str) -> Vec<PathBuf> {
+    let value = std::env::var(var).ok().filter(|v| !v.is_empty()).unwrap_or_else(|| fallback.to_string());
+    value.split(':').filter(|d| !d.is_empty()).map(PathBuf::from).collect()
+}
+
+fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = env_dir("XDG_DATA_HOME", ".local/share").into_iter().collect();
+    dirs.extend(env_dirs("XDG_DATA_DIRS", "/usr/local/share:/usr/share"));
+    dirs
+}
+
+fn user_mimeapps_path() -> Option<PathBuf> {
+    env_dir("XDG_CONFIG_HOME", ".config").map(|dir| dir.join("mimeapps.list"))
+}
+
+/// Installed applications and the user's MIME associations.
+pub struct AppRegistry {
+    entries: HashMap<String, DesktopEntry>,
+    mimeapps: MimeApps,
+}
+
+impl AppRegistry {
+    pub fn load() -> Self {
+        let mut entries = HashMap::new();
+        for data_dir in data_dirs() {
+            let apps_dir = data_dir.join("applications");
+            collect_entries(&apps_dir, &apps_dir, &mut entries);
+        }
+
+        let mut mimeapps = MimeApps::default();
+        let mut lists: Vec<PathBuf> = user_mimeapps_path().into_iter().collect();
+        lists.extend(env_dirs("XDG_CONFIG_DIRS", "/etc/xdg").into_iter().map(|d| d.join("mimeapps.list")));
+        lists.extend(data_dirs().into_iter().map(|d| d.join("applications").join("mimeapps.list")));
+        for list in lists {
+            if let Ok(contents) = std::fs::read_to_string(&list) {
+                mimeapps.merge(&contents);
+            }
+        }
+        Self { entries, mimeapps }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&DesktopEntry> {
+        self.entries.get(id)
+    }
+
+    pub fn default_for(&self, mime: &str) -> Option<&DesktopEntry> {
+        self.apps_for(mime).into_iter().next()
+    }
+
+    /// Applications able to open `mime`, the configured default first.
+    pub fn apps_for(&self, mime: &str) -> Vec<&DesktopEntry> {
+        let removed = self.mimeapps.removed.get(mime);
+        let is_removed = |id: &str| removed.is_some_and(|r| r.iter().any(|x| x == id));
+        let mut supporting: Vec<&DesktopEntry> = self.entries.values().filter(|e| e.mime_types.iter().any(|m| m == mime)).collect();
+        // Any text editor can open other text types
+        if supporting.is_empty() && mime.starts_with("text/") {
+            supporting = self.entries.values().filter(|e| e.mime_types.iter().any(|m| m == "text/plain")).collect();
+        }
+        supporting.sort_by_key(|e| e.name.to_lowercase());
+
+        let mut ids: Vec<&str> = Vec::new();
+        let candidates = self.mimeapps.defaults.get(mime).into_iter().flatten()
+            .chain(self.mimeapps.added.get(mime).into_iter().flatten())
+            .map(String::as_str)
+            .chain(supporting.into_iter().map(|e| e.id.as_str()));
+        for id in candidates {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        ids.into_iter().filter(|id| !is_removed(id)).filter_map(|id| self.entries.get(id)).collect()
+    }
+
+    /// Records `id` as the default for `mime` in the user's `mimeapps.list`.
+    pub fn set_default(&mut self, mime: &str, id: &str) -> std::io::Result<()> {
+        let path = user_mimeapps_path().ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no config directory"))?;
+        let contents = std::fs::read_to_string(&path).unwrap_or_default();
+        let updated = set_default_in_list(&contents, mime, id);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("list.datara-tmp");
+        std::fs::write(&tmp, updated)?;
+        std::fs::rename(&tmp, &path)?;
+        self.mimeapps.defaults.insert(mime.to_string(), vec![id.to_string()]);
+        Ok(())
+    }
+}
+
+fn collect_entries(root: &Path, dir: &Path, entries: &mut HashMap<String, DesktopEntry>) {
+    let Ok(read_dir) = std::fs::read_dir(dir) else { return };
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_entries(root, &path, entries);
+            continue;
+        }
+        if path.extension().and_then(|e| e.to_str()) != Some("desktop") {
+            continue;
+        }
+        // Desktop file IDs use '-' for subdirectories
+        let Ok(relative) = path.strip_prefix(root) else { continue };
+        let id = relative.to_string_lossy().replace('/', "-");
+        // Earlier data dirs take precedence
+        if entries.contains_key(&id) {
+            continue;
+        }
+        if let Ok(contents) = std::fs::read_to_string(&path)
+            && let Some(desktop_entry) = DesktopEntry::parse(&id, &path, &contents) {
+            entries.insert(id, desktop_entry);
+        }
+    }
+}
+
+/// Rewrites `mime=` in `[Default Applications]`, keeping every other line untouched.
+fn set_default_in_list(contents: &str, mime: &str, id: &str) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut in_defaults = false;
+    let mut section_found = false;
+    let mut written = false;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            if in_defaults && !written {
+                out.push(format!("{}={};", mime, id));
+                written = true;
+            }
+            in_defaults = trimmed == "[Default Applications]";
+            section_found |= in_defaults;
+        } else if in_defaults && trimmed.split_once('=').is_some_and(|(key, _)| key.trim() == mime) {
+            if !written {
+                out.push(format!("{}={};", mime, id));
+                written = true;
+            }
+            continue;
+        }
+        out.push(line.to_string());
+    }
+    if !written {
+        if !section_found {
+            if out.last().is_some_and(|l| !l.trim().is_empty()) {
+                out.push(String::new());
+            }
+            out.push("[Default Applications]".to_string());
+        }
+        out.push(format!("{}={};", mime, id));
+    }
+    out.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, contents: &str) -> DesktopEntry {
+        DesktopEntry::parse(id, Path::new("/usr/share/applications").join(id).as_path(), contents).unwrap()
+    }
+
+    fn app(id: &str, name: &str, exec: &str, mime_types: &str) -> DesktopEntry {
+        entry(id, &format!("[Desktop Entry]\nType=Application\nName={}\nExec={}\nMimeType={}\n", name, exec, mime_types))
+    }
+
+    fn registry(entries: Vec<DesktopEntry>, lists: &[&str]) -> AppRegistry {
+        let mut mimeapps = MimeApps::default();
+        for list in lists {
+            mimeapps.merge(list);
+        }
+        AppRegistry { entries: entries.into_iter().map(|e| (e.id.clone(), e)).collect(), mimeapps }
+    }
+
+    fn ids(apps: Vec<&DesktopEntry>) -> Vec<&str> {
+        apps.into_iter().map(|e| e.id.as_str()).collect()
+    }
+
+    fn command(entry: &DesktopEntry, file: &str) -> Vec<String> {
+        let (program, args) = entry.command(Path::new(file), "xterm -e").unwrap();
+        std::iter::once(program).chain(args).map(|a| a.into_string().unwrap()).collect()
+    }
+
+    #[test]
+    fn parses_the_main_group_only() {
+        let parsed = entry("viewer.desktop", "# comment\n[Desktop Entry]\nType=Application\nName=Image\\sViewer\nName=Ignored\nExec=viewer %f\nIcon=viewer\nTerminal=true\nMimeType=image/png;image/jpeg;\n\n[Desktop Action new]\nName=New Window\nExec=viewer --new\n");
+        assert_eq!(parsed.name, "Image Viewer");
+        assert_eq!(parsed.exec, "viewer %f");
+        assert_eq!(parsed.icon.as_deref(), Some("viewer"));
+        assert!(parsed.terminal);
+        assert_eq!(parsed.mime_types, ["image/png", "image/jpeg"]);
+    }
+
+    #[test]
+    fn skips_hidden_and_non_application_entries() {
+        let path = Path::new("x.desktop");
+        assert!(DesktopEntry::parse("x", path, "[Desktop Entry]\nType=Link\nName=X\nExec=x\n").is_none());
+        assert!(DesktopEntry::parse("x", path, "[Desktop Entry]\nType=Application\nName=X\nExec=x\nHidden=true\n").is_none());
+        assert!(DesktopEntry::parse("x", path, "[Desktop Entry]\nType=Application\nName=X\n").is_none());
+        assert!(DesktopEntry::parse("x", path, "[Other]\nType=Application\nName=X\nExec=x\n").is_none());
+    }
+
+    #[test]
+    fn expands_file_codes() {
+        let file = "/tmp/a b.txt";
+        assert_eq!(command(&app("e.desktop", "Editor", "editor %f", ""), file), ["editor", file]);
+        assert_eq!(command(&app("e.desktop", "Editor", "editor %F", ""), file), ["editor", file]);
+        assert_eq!(command(&app("e.desktop", "Editor", "editor %u", ""), file), ["editor", file]);
+        assert_eq!(command(&app("e.desktop", "Editor", "editor %U --new", ""), file), ["editor", file, "--new"]);
+        assert_eq!(command(&app("e.desktop", "Editor", "editor --file=%f", ""), file), ["editor", "--file=/tmp/a b.txt"]);
+        // No file code: the file is appended
+        assert_eq!(command(&app("e.desktop", "Editor", "editor --new", ""), file), ["editor", "--new", file]);
+        // %% is a literal percent sign, deprecated codes are dropped
+        assert_eq!(command(&app("e.desktop", "Editor", "editor 100%% %d %f", ""), file), ["editor", "100%", file]);
+        assert_eq!(command(&app("e.desktop", "My Editor", "editor --class=%c %f", ""), file), ["editor", "--class=My Editor", file]);
+    }
+
+    #[test]
+    fn terminal_apps_run_in_the_terminal_command() {
+        let top = entry("top.desktop", "[Desktop Entry]\nType=Application\nName=Top\nExec=htop\nTerminal=true\n");
+        assert_eq!(command(&top, "/tmp/x"), ["xterm", "-e", "htop", "/tmp/x"]);
+    }
+
+    #[test]
+    fn unquotes_and_unescapes_exec_arguments() {
+        let quoted = app("q.desktop", "Q", r#"sh -c "echo \\"$0\\" \\\\ done" %f"#, "");
+        assert_eq!(quoted.exec, r#"sh -c "echo \"$0\" \\ done" %f"#);
+        assert_eq!(command(&quoted, "/tmp/x"), ["sh", "-c", r#"echo "$0" \ done"#, "/tmp/x"]);
+        let spaced = app("s.desktop", "S", "'/opt/My App/run' --title \"a\\sb\" %u", "");
+        assert_eq!(command(&spaced, "/tmp/x"), ["/opt/My App/run", "--title", "a b", "/tmp/x"]);
+    }
+
+    #[test]
+    fn unescape_keeps_unknown_escapes() {
+        assert_eq!(unescape_string(r"a\sb\tc\\d\;e\"), "a b\tc\\d\\;e\\");
+    }
+
+    #[test]
+    fn defaults_come_first_and_earlier_lists_win() {
+        let registry = registry(
+            vec![
+                app("a.desktop", "Alpha", "a", "text/plain;"),
+                app("b.desktop", "Beta", "b", "text/plain;"),
+                app("c.desktop", "Gamma", "c", "text/plain;"),
+                app("d.desktop", "Delta", "d", "image/png;"),
+            ],
+            &[
+                "[Default Applications]\ntext/plain=c.desktop;\n",
+                "[Default Applications]\ntext/plain=b.desktop;\n[Added Associations]\ntext/plain=d.desktop;missing.desktop;\n",
+            ],
+        );
+        assert_eq!(ids(registry.apps_for("text/plain")), ["c.desktop", "b.desktop", "d.desktop", "a.desktop"]);
+        assert_eq!(registry.default_for("text/plain").map(|e| e.id.as_str()), Some("c.desktop"));
+        // Other text types fall back to the text editors
+        assert_eq!(ids(registry.apps_for("text/x-rust")), ["a.desktop", "b.desktop", "c.desktop"]);
+        assert!(registry.apps_for("video/mp4").is_empty());
+    }
+
+    #[test]
+    fn removed_associations_hide_apps_even_when_default() {
+        let registry = registry(
+            vec![app("a.desktop", "Alpha", "a", "image/png;"), app("b.desktop", "Beta", "b", "image/png;")],
+            &[
+                "[Removed Associations]\nimage/png=a.desktop;\n",
+                "[Default Applications]\nimage/png=a.desktop;\n[Unknown]\nimage/png=b.desktop;\n",
+            ],
+        );
+        assert_eq!(ids(registry.apps_for("image/png")), ["b.desktop"]);
+    }
+
+    #[test]
+    fn set_default_rewrites_only_the_default_line() {
+        let list = "[Added Associations]\ntext/plain=a.desktop;\n\n[Default Applications]\ntext/plain=old.desktop;\nimage/png=v.desktop;\n";
+        assert_eq!(
+            set_default_in_list(list, "text/plain", "new.desktop"),
+            "[Added Associations]\ntext/plain=a.desktop;\n\n[Default Applications]\ntext/plain=new.desktop;\nimage/png=v.desktop;\n"
+        );
+        // Missing key: appended at the end of the section
+        assert_eq!(
+            set_default_in_list("[Default Applications]\nimage/png=v.desktop;\n[Added Associations]\n", "text/plain", "e.desktop"),
+            "[Default Applications]\nimage/png=v.desktop;\ntext/plain=e.desktop;\n[Added Associations]\n"
+        );
+        // Missing section: added after the other groups
+        assert_eq!(
+            set_default_in_list("[Added Associations]\ntext/plain=a.desktop;", "text/plain", "e.desktop"),
+            "[Added Associations]\ntext/plain=a.desktop;\n\n[Default Applications]\ntext/plain=e.desktop;\n"
+        );
+        assert_eq!(set_default_in_list("", "text/plain", "e.desktop"), "[Default Applications]\ntext/plain=e.desktop;\n");
+    }
+}
@@ -0,0 +1,924 @@
+use eframe::egui;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::browser::{self, Browser, Entry};
+use crate::file_kind::FileKind;
+use crate::file_type::{self, Detector, FileType};
+use crate::thumbnails::{self, ThumbnailCache, ThumbnailSize};
+use crate::vfs::{LocalFs, Vfs};
+
+const FOLDER_ICON: &[u8] = include_bytes!("icons/Folder/icons8-folder-48.png");
+const FILE_ICON: &[u8] = include_bytes!("icons/File/icons8-file-48.png");
+
+type EntryPredicate = Box<dyn Fn(&Entry) -> bool>;
+
+#[derive(Debug, Clone, Copy)]
+enum Detection {
+    NotRequested,
+    Pending,
+    Done(FileType),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionMode {
+    Single,
+    /// Ctrl+click adds or removes entries.
+    Multiple,
+}
+
+/// Something the user did in a `FileBrowser` during one frame.
+#[derive(Debug)]
+pub enum BrowserEvent {
+    /// The pointer moved onto an entry.
+    Hovered(PathBuf),
+    /// A click changed the selection to these entries.
+    Selected(Vec<PathBuf>),
+    /// A file was clicked, or double-clicked, to open it.
+    Opened(PathBuf),
+    /// A folder was entered, or finished loading in the background; the
+    /// listing failed if this is an error.
+    Navigated(io::Result<()>),
+}
+
+/// What `FileBrowser::ui` reports for a frame.
+#[derive(Debug, Default)]
+pub struct BrowserResponse {
+    pub events: Vec<BrowserEvent>,
+    /// The entry under the pointer.
+    pub hovered: Option<PathBuf>,
+}
+
+impl BrowserResponse {
+    /// The file opened this frame, if any.
+    pub fn opened(&self) -> Option<&Path> {
+        self.events.iter().find_map(|event| match event {
+            BrowserEvent::Opened(path) => Some(path.as_path()),
+            _ => None,
+        })
+    }
+
+    /// The new selection, if a click changed it this frame.
+    pub fn selected(&self) -> Option<&[PathBuf]> {
+        self.events.iter().rev().find_map(|event| match event {
+            BrowserEvent::Selected(paths) => Some(paths.as_slice()),
+            _ => None,
+        })
+    }
+}
+
+/// Datara's grid and list of neon cards as a widget for any egui app: the
+/// folder being browsed, its navigation and selection, and how it is drawn.
+///
+/// ```no_run
+/// # use datara::file_browser::{FileBrowser, SelectionMode};
+/// # fn show(ui: &mut eframe::egui::Ui, files: &mut FileBrowser) {
+/// let mut files = FileBrowser::new("/srv/assets".into())
+///     .with_root("/srv/assets".into())
+///     .with_filter(|entry| entry.is_dir || entry.path.extension().is_some_and(|ext| ext == "png"))
+///     .with_selection_mode(SelectionMode::Multiple);
+/// // then, every frame:
+/// if let Some(path) = files.ui(ui).opened() {
+///     println!("open {}", path.display());
+/// }
+/// # }
+/// ```
+pub struct FileBrowser {
+    pub browser: Browser,
+    /// Folder the user can't leave, if any.
+    root: Option<PathBuf>,
+    /// Which entries are listed.
+    filter: Option<EntryPredicate>,
+    /// Which listed entries can be selected and opened; folders can always
+    /// be entered.
+    selectable: Option<EntryPredicate>,
+    pub selection_mode: SelectionMode,
+    /// A single click opens, entering folders; otherwise it selects and a
+    /// double click opens.
+    pub open_on_click: bool,
+    /// Selected entries in the order they were clicked.
+    selection: Vec<PathBuf>,
+    pub grid_view: bool,
+    pub ui_scale: f32,
+    pub max_items_per_row: i32,
+    pub horizontal_spacing: f32,
+    pub vertical_spacing: f32,
+    /// Thumbnails replace the icons of local images.
+    pub show_thumbnails: bool,
+    /// Scroll the selected entry into view on the next frame.
+    scroll_to_selected: bool,
+    /// Detected content types, parallel to `browser.entries`; requested
+    /// once an entry is on screen.
+    entry_types: Vec<Detection>,
+    detector: Option<Detector>,
+    thumbnails: Option<ThumbnailCache>,
+    folder_icon: Option<egui::TextureHandle>,
+    file_icon: Option<egui::TextureHandle>,
+    /// Index of the entry under the pointer.
+    hovered: Option<usize>,
+    /// List folders that aren't on the local disk on a worker thread.
+    background_listing: bool,
+    /// The listing of the current folder, while a worker reads it.
+    listing: Option<Receiver<io::Result<Vec<Entry>>>>,
+    /// For waking the window when a listing arrives; set by `ui`.
+    ctx: Option<egui::Context>,
+}
+
+impl FileBrowser {
+    /// Browses `dir` on the local filesystem.
+    pub fn new(dir: PathBuf) -> Self {
+        Self::with_vfs(Arc::new(LocalFs), dir)
+    }
+
+    /// Browses `dir` on `vfs`; the folder is listed right away.
+    pub fn with_vfs(vfs: Arc<dyn Vfs>, dir: PathBuf) -> Self {
+        let mut files = Self {
+            browser: Browser::with_vfs(vfs, dir),
+            root: None,
+            filter: None,
+            selectable: None,
+            selection_mode: SelectionMode::Single,
+            open_on_click: false,
+            selection: Vec::new(),
+            grid_view: true,
+            ui_scale: 1.0,
+            max_items_per_row: 3,
+            horizontal_spacing: 16.0,
+            vertical_spacing: 12.0,
+            show_thumbnails: true,
+            scroll_to_selected: false,
+            entry_types: Vec::new(),
+            detector: None,
+            thumbnails: None,
+            folder_icon: None,
+            file_icon: None,
+            hovered: None,
+            background_listing: false,
+            listing: None,
+            ctx: None,
+        };
+        // Errors show as an empty folder; `read_dir` reports them
+        let _ = files.read_dir();
+        files
+    }
+
+    /// Keeps the user inside `root`, moving there if the current folder is
+    /// outside it.
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        let outside = !self.browser.current_dir.starts_with(&root);
+        self.root = Some(root.clone());
+        if outside {
+            let _ = self.navigate_to(root, false);
+        }
+        self
+    }
+
+    /// Lists only the entries `filter` accepts.
+    pub fn with_filter(mut self, filter: impl Fn(&Entry) -> bool + 'static) -> Self {
+        self.filter = Some(Box::new(filter));
+        let _ = self.read_dir();
+        self
+    }
+
+    /// Lets only the entries `selectable` accepts be selected or opened.
+    pub fn with_selectable(mut self, selectable: impl Fn(&Entry) -> bool + 'static) -> Self {
+        self.selectable = Some(Box::new(selectable));
+        self
+    }
+
+    pub fn with_selection_mode(mut self, mode: SelectionMode) -> Self {
+        self.selection_mode = mode;
+        self
+    }
+
+    pub fn with_open_on_click(mut self, open_on_click: bool) -> Self {
+        self.open_on_click = open_on_click;
+        self
+    }
+
+    /// Lists folders without a local path, like those on a server, on a
+    /// worker thread. Navigating then returns at once and the listing
+    /// arrives as a `BrowserEvent::Navigated`.
+    pub fn with_background_listing(mut self, background_listing: bool) -> Self {
+        self.background_listing = background_listing;
+        self
+    }
+
+    pub fn current_dir(&self) -> &Path {
+        &self.browser.current_dir
+    }
+
+    pub fn selection(&self) -> &[PathBuf] {
+        &self.selection
+    }
+
+    /// Selects `path`, or nothing, and scrolls it into view.
+    pub fn select(&mut self, path: Option<PathBuf>) {
+        self.selection = path.iter().cloned().collect();
+        self.scroll_to_selected = path.is_some();
+        self.browser.selected_path = path;
+    }
+
+    /// The entry under the pointer during the last frame.
+    pub fn hovered(&self) -> Option<&Entry> {
+        self.hovered.and_then(|i| self.browser.entries.get(i))
+    }
+
+    fn within_root(&self, path: &Path) -> bool {
+        self.root.as_ref().is_none_or(|root| path.starts_with(root))
+    }
+
+    /// Lists the current folder again, dropping the selection.
+    pub fn read_dir(&mut self) -> io::Result<()> {
+        self.list()
+    }
+
+    /// Moves to `path`, which must be inside the root; see
+    /// `Browser::navigate_to`.
+    pub fn navigate_to(&mut self, path: PathBuf, push_history: bool) -> io::Result<()> {
+        if !self.within_root(&path) {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{} is outside {}", path.display(), self.root.as_deref().unwrap_or(Path::new("/")).display())));
+        }
+        self.browser.move_to(path, push_history);
+        self.list()
+    }
+
+    pub fn switch_vfs(&mut self, vfs: Arc<dyn Vfs>, dir: PathBuf) -> io::Result<()> {
+        self.browser.move_to_vfs(vfs, dir);
+        self.list()
+    }
+
+    /// Whether there is a parent folder to go up to.
+    pub fn can_go_up(&self) -> bool {
+        self.browser.current_dir.parent().is_some_and(|parent| self.within_root(parent))
+    }
+
+    pub fn navigate_up(&mut self) -> io::Result<()> {
+        match self.browser.current_dir.parent() {
+            Some(parent) if self.can_go_up() => self.navigate_to(parent.to_path_buf(), true),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn navigate_back(&mut self) -> io::Result<()> {
+        if !self.browser.move_back() {
+            return Ok(());
+        }
+        self.list()
+    }
+
+    pub fn navigate_forward(&mut self) -> io::Result<()> {
+        if !self.browser.move_forward() {
+            return Ok(());
+        }
+        self.list()
+    }
+
+    /// Whether the current folder is still being listed in the background.
+    pub fn is_loading(&self) -> bool {
+        self.listing.is_some()
+    }
+
+    /// Lists the current folder, or starts listing it on a worker thread;
+    /// see `with_background_listing`.
+    fn list(&mut self) -> io::Result<()> {
+        let dir = self.browser.current_dir.clone();
+        if !self.background_listing || self.browser.vfs.local_path(&dir).is_some() {
+            self.listing = None;
+            let result = self.browser.read_dir();
+            self.listed();
+            return result;
+        }
+        let (sender, receiver) = mpsc::channel();
+        let (vfs, show_hidden, ctx) = (Arc::clone(&self.browser.vfs), self.browser.show_hidden, self.ctx.clone());
+        std::thread::spawn(move || {
+            let listing = browser::list_dir(vfs.as_ref(), &dir, show_hidden);
+            // Fails when the user moved on before the listing arrived
+            if sender.send(listing).is_ok()
+                && let Some(ctx) = ctx {
+                ctx.request_repaint();
+            }
+        });
+        self.listing = Some(receiver);
+        let result = self.browser.set_listing(Ok(Vec::new()));
+        self.listed();
+        result
+    }
+
+    /// Shows the background listing once it has arrived.
+    fn poll_listing(&mut self) -> Option<io::Result<()>> {
+        let listing = match self.listing.as_ref()?.try_recv() {
+            Ok(listing) => listing,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => Err(io::Error::other("listing the folder failed")),
+        };
+        self.listing = None;
+        // Keep what was selected while loading, e.g. by restoring a tab
+        let selected = self.browser.selected_path.clone();
+        let result = self.browser.set_listing(listing);
+        self.listed();
+        if result.is_ok() && selected.is_some() {
+            self.select(selected);
+        }
+        Some(result)
+    }
+
+    /// Resets the per-listing state after `browser` listed a folder.
+    fn listed(&mut self) {
+        if let Some(filter) = &self.filter {
+            self.browser.entries.retain(|entry| filter(entry));
+        }
+        self.entry_types = vec![Detection::NotRequested; self.browser.entries.len()];
+        self.selection.clear();
+        self.hovered = None;
+        if let Some(detector) = &mut self.detector {
+            detector.clear();
+        }
+        if let Some(thumbnails) = &mut self.thumbnails {
+            thumbnails.clear();
+        }
+    }
+
+    /// A click on entry `index`; `toggle` is Ctrl being held.
+    fn click(&mut self, index: usize, double: bool, toggle: bool) -> Vec<BrowserEvent> {
+        let Some(entry) = self.browser.entries.get(index).cloned() else { return Vec::new() };
+        self.browser.selected_path = Some(entry.path.clone());
+        let open = double || self.open_on_click;
+        if open && entry.is_dir {
+            return vec![BrowserEvent::Navigated(self.navigate_to(entry.path, true))];
+        }
+        if self.selectable.as_ref().is_some_and(|selectable| !selectable(&entry)) {
+            return Vec::new();
+        }
+        let mut events = Vec::new();
+        // The second click of a double click keeps what the first selected
+        if !(double && self.selection.contains(&entry.path)) {
+            if toggle && self.selection_mode == SelectionMode::Multiple {
+                match self.selection.iter().position(|path| *path == entry.path) {
+                    Some(i) => {
+                        self.selection.remove(i);
+                    }
+                    None => self.selection.push(entry.path.clone()),
+                }
+            } else {
+                self.selection = vec![entry.path.clone()];
+            }
+            events.push(BrowserEvent::Selected(self.selection.clone()));
+        }
+        if open {
+            events.push(BrowserEvent::Opened(entry.path));
+        }
+        events
+    }
+
+    /// The detected type of entry `index`, asking the detector for it the
+    /// first time the entry is `visible`; until then the extension's guess.
+    /// The detected type of a listed file, without reading it again; `None`
+    /// when `path` isn't listed or hasn't been detected yet.
+    pub fn detected_type(&self, path: &Path) -> Option<FileType> {
+        let index = self.browser.entries.iter().position(|entry| entry.path == path)?;
+        match self.entry_types.get(index)? {
+            Detection::Done(file_type) => Some(*file_type),
+            _ => None,
+        }
+    }
+
+    fn entry_type(&mut self, index: usize, visible: bool) -> Option<FileType> {
+        let entry = &self.browser.entries[index];
+        if entry.is_dir {
+            return None;
+        }
+        match self.entry_types[index] {
+            Detection::Done(file_type) => return Some(file_type),
+            Detection::NotRequested if visible => {
+                if let Some(detector) = &self.detector {
+                    detector.request(index, Arc::clone(&self.browser.vfs), entry.path.clone());
+                    self.entry_types[index] = Detection::Pending;
+                }
+            }
+            _ => {}
+        }
+        file_type::guess(&entry.path)
+    }
+
+    fn load_icons(&mut self, ctx: &egui::Context) {
+        if self.folder_icon.is_none() {
+            // Load folder icon
+            if let Ok(image) = image::load_from_memory(FOLDER_ICON) {
+                let rgba_image = image.to_rgba8();
+                let size = [rgba_image.width() as usize, rgba_image.height() as usize];
+                let pixels = rgba_image.into_raw();
+                let color_image = egui::ColorImage::from_rgba_unmultiplied(size, &pixels);
+                self.folder_icon = Some(ctx.load_texture("folder_icon", color_image, egui::TextureOptions::default()));
+            }
+        }
+        
+        if self.file_icon.is_none() {
+            // Load file icon
+            if let Ok(image) = image::load_from_memory(FILE_ICON) {
+                let rgba_image = image.to_rgba8();
+                let size = [rgba_image.width() as usize, rgba_image.height() as usize];
+                let pixels = rgba_image.into_raw();
+                let color_image = egui::ColorImage::from_rgba_unmultiplied(size, &pixels);
+                self.file_icon = Some(ctx.load_texture("file_icon", color_image, egui::TextureOptions::default()));
+            }
+        }
+    }
+
+    /// Draws the current folder; see `ui_with_menu`.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> BrowserResponse {
+        self.ui_with_menu(ui, |_, _, _| {})
+    }
+
+    /// Draws the current folder, with `context_menu` filling the menu of a
+    /// right-clicked entry. It gets the entry's type as detected so far; the
+    /// extension's guess until the detector has run, `None` for folders.
+    pub fn ui_with_menu(&mut self, ui: &mut egui::Ui, mut context_menu: impl FnMut(&mut egui::Ui, &Entry, Option<FileType>)) -> BrowserResponse {
+        self.load_icons(ui.ctx());
+        self.thumbnails.get_or_insert_with(|| ThumbnailCache::new(ui.ctx())).poll(ui.ctx());
+        let ctx = ui.ctx().clone();
+        let detector = self.detector.get_or_insert_with(|| Detector::new(move || ctx.request_repaint()));
+        for (index, file_type) in detector.poll() {
+            if let Some(detection) = self.entry_types.get_mut(index) {
+                *detection = Detection::Done(file_type);
+            }
+        }
+
+        let mut events = Vec::new();
+        self.ctx = Some(ui.ctx().clone());
+        if let Some(result) = self.poll_listing() {
+            events.push(BrowserEvent::Navigated(result));
+        }
+        let mut click: Option<(usize, bool)> = None;
+        let base_green = egui::Color32::from_rgb(0, 255, 0);
+        let hover_green = egui::Color32::from_rgb(120, 255, 120);
+        let hover_stroke = egui::Stroke { width: 1.0 * self.ui_scale, color: hover_green };
+        let selected_fill = egui::Color32::from_rgba_unmultiplied(0, 255, 0, 24);
+
+        // Add margin around the entire content area
+        let margin = 16.0 * self.ui_scale;
+        ui.add_space(margin);
+
+        if self.is_loading() {
+            ui.horizontal(|ui| {
+                ui.add_space(margin);
+                ui.spinner();
+                ui.label(egui::RichText::new("Loading…").monospace().color(base_green));
+            });
+        } else if self.grid_view {
+            // Grid view with neon bordered cards and metadata
+            let card_height = 80.0 * self.ui_scale;
+            let horizontal_spacing = self.horizontal_spacing * self.ui_scale;
+            let vertical_spacing = self.vertical_spacing * self.ui_scale;
+            let available_width = ui.available_width() - (margin * 2.0);
+            let columns = self.max_items_per_row as usize;
+            // Calculate item width based on available space and max items per row
+            let desired_width = (available_width - (horizontal_spacing * (columns - 1) as f32)) / columns as f32;
+
+            egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysVisible)
+                .show(ui, |ui| {
+                    egui::Grid::new("files_grid").num_columns(columns).spacing(egui::vec2(horizontal_spacing, vertical_spacing)).show(ui, |ui| {
+                for i in 0..self.browser.entries.len() {
+                    let (alloc_id, alloc_rect) = ui.allocate_space(egui::vec2(desired_width, card_height - margin));
+                    // Adjust rect to add left margin and ensure right margin
+                    let adjusted_rect = alloc_rect.translate(egui::vec2(margin, 0.0));
+                    let file_type = self.entry_type(i, ui.is_rect_visible(adjusted_rect));
+                    let entry = &self.browser.entries[i];
+                    let name_plain = entry.display_name();
+                    let (is_dir, size_opt, date_opt) = (entry.is_dir, entry.len, entry.modified);
+                    let response = ui
+                        .interact(adjusted_rect, alloc_id, egui::Sense::click())
+                        .on_hover_cursor(egui::CursorIcon::PointingHand);
+                    if self.selection.contains(&entry.path) {
+                        ui.painter().rect_filled(adjusted_rect, 0.0, selected_fill);
+                        if self.scroll_to_selected {
+                            response.scroll_to_me(Some(egui::Align::Center));
+                            self.scroll_to_selected = false;
+                        }
+                    }
+
+                    // Sharp neon border and hover inner border
+                    let round = 0.0;
+                    ui.painter().rect_stroke(adjusted_rect, round, hover_stroke);
+                    if response.hovered() {
+                        ui.painter().rect_stroke(adjusted_rect.shrink(2.0), round, hover_stroke);
+                        if self.hovered != Some(i) {
+                            events.push(BrowserEvent::Hovered(entry.path.clone()));
+                            self.hovered = Some(i);
+                        }
+                    } else if self.hovered == Some(i) {
+                        self.hovered = None;
+                    }
+
+                    // Content positions (vertically centered name + vector icon)
+                    let left = adjusted_rect.left() + 12.0 * self.ui_scale;
+                    let center_y = adjusted_rect.center().y;
+
+                    // Icon (custom PNG), or an image thumbnail when one is available
+                    let icon_size = 28.0 * self.ui_scale;
+                    let local = if self.show_thumbnails && file_type.is_some_and(|t| thumbnails::is_thumbnailable(t.mime)) { self.browser.vfs.local_path(&entry.path) } else { None };
+                    let thumbnail = match local {
+                        Some(local) => {
+                            let size = ThumbnailSize::for_pixels(icon_size * ui.ctx().pixels_per_point());
+                            self.thumbnails.as_mut().and_then(|t| t.get(&local, size))
+                        }
+                        None => None,
+                    };
+                    if let Some(thumb_texture) = thumbnail {
+                        let thumb_size = thumb_texture.size_vec2();
+                        let fit = (icon_size / thumb_size.x).min(icon_size / thumb_size.y);
+                        let icon_rect = egui::Rect::from_center_size(
+                            egui::pos2(left + icon_size * 0.5, center_y),
+                            thumb_size * fit
+                        );
+                        ui.painter().image(thumb_texture.id(), icon_rect, egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)), egui::Color32::WHITE);
+                    } else if let Some(icon_texture) = if is_dir { &self.folder_icon } else { &self.file_icon } {
+                        let icon_rect = egui::Rect::from_center_size(
+                            egui::pos2(left + icon_size * 0.5, center_y),
+                            egui::vec2(icon_size, icon_size)
+                        );
+                        ui.painter().image(icon_texture.id(), icon_rect, egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)), base_green);
+                        if let Some(file_type) = file_type {
+                            draw_kind_tag(ui.painter(), icon_rect, file_type.kind, self.ui_scale);
+                        }
+                    } else {
+                        // Fallback to emoji if custom icon not loaded
+                        let icon = if is_dir { "📁" } else { "📄" };
+                        ui.painter().text(
+                            egui::pos2(left.floor(), center_y.floor()),
+                            egui::Align2::LEFT_CENTER,
+                            icon,
+                            egui::FontId::monospace(icon_size),
+                            base_green,
+                        );
+                    }
+
+                    // Name (no icon in text) with scrolling
+                    let name_x = left + icon_size + 12.0 * self.ui_scale;
+                    let name_width = adjusted_rect.right() - name_x - 8.0 * self.ui_scale;
+                    let font_size = 15.0 * self.ui_scale;
+                    let time = ui.ctx().input(|i| i.time) as f32;
+                    let display_name = scrolling_text(&name_plain, name_width, font_size, response.hovered(), time);
+                    let name_text = if is_dir { egui::RichText::new(display_name).strong().monospace() } else { egui::RichText::new(display_name).monospace() };
+                    ui.painter().text(
+                        egui::pos2(name_x.floor(), center_y.floor()),
+                        egui::Align2::LEFT_CENTER,
+                        name_text.text(),
+                        egui::FontId::monospace(font_size),
+                        base_green,
+                    );
+
+                    // Metadata line (date · size) with scrolling
+                    let meta_y = (adjusted_rect.bottom() - 10.0 * self.ui_scale).floor();
+                    let meta = metadata_line(is_dir, size_opt, date_opt);
+                    let meta_width = adjusted_rect.right() - name_x - 8.0 * self.ui_scale;
+                    let meta_font_size = 11.0 * self.ui_scale;
+                    let display_meta = scrolling_text(&meta, meta_width, meta_font_size, response.hovered(), time);
+                    ui.painter().text(
+                        egui::pos2(name_x.floor(), meta_y),
+                        egui::Align2::LEFT_BOTTOM,
+                        display_meta,
+                        egui::FontId::monospace(meta_font_size),
+                        base_green,
+                    );
+
+                    response.context_menu(|ui| context_menu(ui, entry, file_type));
+                    if response.clicked() || response.double_clicked() {
+                        click = Some((i, response.double_clicked()));
+                    }
+
+                    let last_col = (i + 1) % columns == 0;
+                    if last_col { ui.end_row(); }
+                }
+                });
+            });
+            // Add bottom margin after grid scroll area
+            ui.add_space(margin);
+        } else {
+            // List view with sharp bordered rows and vector icons
+            egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysVisible)
+                .show(ui, |ui| {
+                // Add top margin for list view
+                ui.add_space(margin);
+
+                let row_h = 56.0 * self.ui_scale;
+                let vertical_spacing = self.vertical_spacing * self.ui_scale;
+                let available_width = ui.available_width() - (margin * 2.0);
+                for i in 0..self.browser.entries.len() {
+                    // Add vertical margin between rows
+                    if i > 0 { ui.add_space(vertical_spacing); }
+
+                    let (row_id, row_rect) = ui.allocate_space(egui::vec2(available_width, row_h - margin));
+                    // Adjust rect to add left margin
+                    let adjusted_rect = row_rect.translate(egui::vec2(margin, 0.0));
+                    let file_type = self.entry_type(i, ui.is_rect_visible(adjusted_rect));
+                    let entry = &self.browser.entries[i];
+                    let is_dir = entry.is_dir;
+                    let name_plain = entry.display_name();
+                    let response = ui
+                        .interact(adjusted_rect, row_id, egui::Sense::click())
+                        .on_hover_cursor(egui::CursorIcon::PointingHand);
+                    if self.selection.contains(&entry.path) {
+                        ui.painter().rect_filled(adjusted_rect, 0.0, selected_fill);
+                        if self.scroll_to_selected {
+                            response.scroll_to_me(Some(egui::Align::Center));
+                            self.scroll_to_selected = false;
+                        }
+                    }
+
+                    // Sharp border
+                    ui.painter().rect_stroke(adjusted_rect, 0.0, hover_stroke);
+                    if response.hovered() {
+                        ui.painter().rect_stroke(adjusted_rect.shrink(2.0), 0.0, hover_stroke);
+                        if self.hovered != Some(i) {
+                            events.push(BrowserEvent::Hovered(entry.path.clone()));
+                            self.hovered = Some(i);
+                        }
+                    } else if self.hovered == Some(i) {
+                        self.hovered = None;
+                    }
+
+                    // Icon and name centered vertically
+                    let left = adjusted_rect.left() + 12.0 * self.ui_scale;
+                    let cy = adjusted_rect.center().y;
+                    let icon_size = 26.0 * self.ui_scale;
+                    if let Some(icon_texture) = if is_dir { &self.folder_icon } else { &self.file_icon } {
+                        let icon_rect = egui::Rect::from_center_size(
+                            egui::pos2(left + icon_size * 0.5, cy),
+                            egui::vec2(icon_size, icon_size)
+                        );
+                        ui.painter().image(icon_texture.id(), icon_rect, egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)), base_green);
+                        if let Some(file_type) = file_type {
+                            draw_kind_tag(ui.painter(), icon_rect, file_type.kind, self.ui_scale);
+                        }
+                    } else {
+                        // Fallback to emoji if custom icon not loaded
+                        let icon = if is_dir { "📁" } else { "📄" };
+                        ui.painter().text(
+                            egui::pos2(left.floor(), cy.floor()),
+                            egui::Align2::LEFT_CENTER,
+                            icon,
+                            egui::FontId::monospace(icon_size),
+                            base_green,
+                        );
+                    }
+
+                    // Type column on the right
+                    let type_width = 190.0 * self.ui_scale;
+                    let type_str = match file_type {
+                        Some(file_type) => file_type.mime,
+                        None if is_dir => "inode/directory",
+                        None => "…",
+                    };
+                    let type_font_size = 11.0 * self.ui_scale;
+                    let display_type = truncate_text(type_str, type_width, type_font_size);
+                    ui.painter().text(
+                        egui::pos2((adjusted_rect.right() - 12.0 * self.ui_scale).floor(), cy.floor()),
+                        egui::Align2::RIGHT_CENTER,
+                        display_type,
+                        egui::FontId::monospace(type_font_size),
+                        base_green,
+                    );
+
+                    let name_x = left + icon_size + 12.0 * self.ui_scale;
+                    let name_width = adjusted_rect.right() - name_x - type_width - 20.0 * self.ui_scale;
+                    let font_size = 15.0 * self.ui_scale;
+                    let time = ui.ctx().input(|i| i.time) as f32;
+                    let display_name = scrolling_text(&name_plain, name_width, font_size, response.hovered(), time);
+                    let name_text = if is_dir { egui::RichText::new(display_name).strong().monospace() } else { egui::RichText::new(display_name).monospace() };
+                    ui.painter().text(
+                        egui::pos2(name_x.floor(), cy.floor()),
+                        egui::Align2::LEFT_CENTER,
+                        name_text.text(),
+                        egui::FontId::monospace(font_size),
+                        base_green,
+                    );
+
+                    // Add metadata (date · size) to list view with scrolling
+                    let meta = metadata_line(is_dir, entry.len, entry.modified);
+                    let meta_width = adjusted_rect.right() - name_x - type_width - 20.0 * self.ui_scale;
+                    let meta_font_size = 11.0 * self.ui_scale;
+                    let meta_y = cy + 12.0 * self.ui_scale;
+                    let display_meta = scrolling_text(&meta, meta_width, meta_font_size, response.hovered(), time);
+                    ui.painter().text(
+                        egui::pos2(name_x.floor(), meta_y),
+                        egui::Align2::LEFT_TOP,
+                        display_meta,
+                        egui::FontId::monospace(meta_font_size),
+                        base_green,
+                    );
+
+                    response.context_menu(|ui| context_menu(ui, entry, file_type));
+                    if response.clicked() || response.double_clicked() {
+                        click = Some((i, response.double_clicked()));
+                    }
+                }
+            });
+        }
+
+        if let Some((index, double)) = click {
+            let toggle = ui.input(|i| i.modifiers.command);
+            events.extend(self.click(index, double, toggle));
+        }
+        BrowserResponse { events, hovered: self.hovered().map(|entry| entry.path.clone()) }
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+    const GB: f64 = MB * 1024.0;
+    let b = bytes as f64;
+    if b >= GB { format!("{} GB", (b / GB) as u64) }
+    else if b >= MB { format!("{} MB", (b / MB) as u64) }
+    else if b >= KB { format!("{} KB", (b / KB) as u64) }
+    else { format!("{} B", bytes) }
+}
+
+pub fn format_date(time: SystemTime) -> String {
+    // Fallback to RFC3339-like; egui has no tz; keep simple
+    let datetime: chrono::DateTime<chrono::Local> = time.into();
+    datetime.format("%b %d, %Y %H:%M").to_string()
+}
+
+/// `date  ·  size`, or just the date for folders.
+fn metadata_line(is_dir: bool, size: Option<u64>, modified: Option<SystemTime>) -> String {
+    let date_str = modified.map(format_date).unwrap_or_default();
+    let size_str = size.map(format_size).unwrap_or_default();
+    if is_dir {
+        date_str
+    } else if !date_str.is_empty() && !size_str.is_empty() {
+        format!("{}  ·  {}", date_str, size_str)
+    } else {
+        format!("{}{}", date_str, size_str)
+    }
+}
+
+/// Category tag drawn over the generic file icon.
+fn draw_kind_tag(painter: &egui::Painter, icon_rect: egui::Rect, kind: FileKind, scale: f32) {
+    let tag = kind.tag();
+    if tag.is_empty() {
+        return;
+    }
+    painter.text(
+        egui::pos2(icon_rect.center().x, icon_rect.center().y + icon_rect.height() * 0.12),
+        egui::Align2::CENTER_CENTER,
+        tag,
+        egui::FontId::monospace(7.0 * scale),
+        egui::Color32::from_rgb(0, 12, 0),
+    );
+}
+
+fn truncate_text(text: &str, max_width: f32, font_size: f32) -> String {
+    // Simple character-based truncation with proper UTF-8 handling
+    let char_width = font_size * 0.6; // Approximate character width
+    let max_chars = (max_width / char_width) as usize;
+
+    // Convert to chars for proper UTF-8 handling
+    let chars: Vec<char> = text.chars().collect();
+
+    if chars.len() <= max_chars {
+        text.to_string()
+    } else {
+        let truncate_at = max_chars.saturating_sub(3);
+        let truncated: String = chars[..truncate_at].iter().collect();
+        format!("{}...", truncated)
+    }
+}
+
+/// Text that doesn't fit is truncated, or scrolls back and forth while
+/// hovered.
+fn scrolling_text(text: &str, max_width: f32, font_size: f32, is_hovered: bool, time: f32) -> String {
+    let char_width = font_size * 0.6;
+    let max_chars = (max_width / char_width) as usize;
+
+    // Convert to chars for proper UTF-8 handling
+    let chars: Vec<char> = text.chars().collect();
+
+    if chars.len() <= max_chars {
+        return text.to_string();
+    }
+
+    if is_hovered {
+        // Use a simple time-based approach that starts immediately
+        let total_text_width = chars.len() as f32 * char_width;
+        let visible_width = max_chars as f32 * char_width;
+        let scroll_range = total_text_width - visible_width;
+
+        // Calculate scroll position based on time with immediate start
+        let cycle_time = 4.0; // seconds for full cycle (2s right, 2s left)
+        let normalized_time = (time % cycle_time) / cycle_time;
+
+        let scroll_offset = if normalized_time < 0.5 {
+            // First half: scroll right (0 to max)
+            normalized_time * 2.0 * scroll_range
+        } else {
+            // Second half: scroll left (max to 0)
+            (1.0 - (normalized_time - 0.5) * 2.0) * scroll_range
+        };
+
+        let start_char = (scroll_offset / char_width) as usize;
+        let visible_chars = max_chars;
+
+        if start_char + visible_chars <= chars.len() {
+            chars[start_char..start_char + visible_chars].iter().collect()
+        } else {
+            // Wrap around
+            let end_part: String = chars[start_char..].iter().collect();
+            let remaining = visible_chars - (chars.len() - start_char);
+            let start_part: String = chars[..remaining].iter().collect();
+            format!("{}{}", end_part, start_part)
+        }
+    } else {
+        // Show truncated text with ellipsis
+        truncate_text(text, max_width, font_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+
+    /// `/root` holding `a/inner/`, `b.txt`, `c.png` and `d.png`.
+    fn files() -> FileBrowser {
+        let fs = MemoryFs::default();
+        fs.add_dir("/root/a/inner").add_file("/root/b.txt", "b").add_file("/root/c.png", "c").add_file("/root/d.png", "d");
+        FileBrowser::with_vfs(Arc::new(fs), PathBuf::from("/root"))
+    }
+
+    fn index(files: &FileBrowser, name: &str) -> usize {
+        files.browser.entries.iter().position(|entry| entry.name == name).unwrap()
+    }
+
+    #[test]
+    fn root_and_filter_limit_what_is_shown() {
+        let mut files = files().with_root(PathBuf::from("/root")).with_filter(|entry| entry.is_dir || entry.path.extension().is_some_and(|ext| ext == "png"));
+        let names: Vec<String> = files.browser.entries.iter().map(Entry::display_name).collect();
+        assert_eq!(names, ["a", "c.png", "d.png"]);
+
+        assert!(!files.can_go_up());
+        files.navigate_up().unwrap();
+        assert_eq!(files.current_dir(), Path::new("/root"));
+        assert_eq!(files.navigate_to(PathBuf::from("/"), true).unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(files.current_dir(), Path::new("/root"));
+
+        files.navigate_to(PathBuf::from("/root/a"), true).unwrap();
+        assert!(files.can_go_up());
+    }
+
+    #[test]
+    fn clicks_select_and_open() {
+        let mut files = files().with_selection_mode(SelectionMode::Multiple).with_selectable(|entry| entry.name != "b.txt");
+        let (b, c, d) = (index(&files, "b.txt"), index(&files, "c.png"), index(&files, "d.png"));
+
+        assert!(files.click(b, false, false).is_empty());
+        assert!(matches!(files.click(c, false, false).as_slice(), [BrowserEvent::Selected(paths)] if paths == &[PathBuf::from("/root/c.png")]));
+        files.click(d, false, true);
+        assert_eq!(files.selection(), [PathBuf::from("/root/c.png"), PathBuf::from("/root/d.png")]);
+
+        // A double click opens without dropping the rest of the selection
+        assert!(matches!(files.click(d, true, false).as_slice(), [BrowserEvent::Opened(path)] if path == Path::new("/root/d.png")));
+        assert_eq!(files.selection().len(), 2);
+
+        assert!(matches!(files.click(index(&files, "a"), true, false).as_slice(), [BrowserEvent::Navigated(Ok(()))]));
+        assert_eq!(files.current_dir(), Path::new("/root/a"));
+        assert!(files.selection().is_empty());
+    }
+
+    #[test]
+    fn background_listings_arrive_as_events() {
+        let mut files = files().with_background_listing(true);
+        files.navigate_to(PathBuf::from("/root/a"), true).unwrap();
+        assert!(files.is_loading());
+        assert!(files.browser.entries.is_empty());
+        files.select(Some(PathBuf::from("/root/a/inner")));
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let mut result = None;
+        while result.is_none() && std::time::Instant::now() < deadline {
+            result = files.poll_listing();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(matches!(result, Some(Ok(()))));
+        assert!(!files.is_loading());
+        assert_eq!(files.browser.entries.len(), 1);
+        assert_eq!(files.selection(), [PathBuf::from("/root/a/inner")]);
+    }
+
+    #[test]
+    fn open_on_click_enters_folders_at_once() {
+        let mut files = files().with_open_on_click(true);
+        let events = files.click(index(&files, "b.txt"), false, false);
+        assert!(matches!(events.as_slice(), [BrowserEvent::Selected(_), BrowserEvent::Opened(path)] if path == Path::new("/root/b.txt")));
+        files.click(index(&files, "a"), false, false);
+        assert_eq!(files.current_dir(), Path::new("/root/a"));
+    }
+}
@@ -0,0 +1,111 @@
+use std::path::Path;
+
+/// Broad category of a file, derived from its MIME type.
+/// Used for icons, previews and the built-in viewers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Text,
+    Markdown,
+    Video,
+    Image,
+    Pdf,
+    Audio,
+    Archive,
+    /// Executables and raw data such as firmware dumps.
+    Binary,
+    Other,
+}
+
+impl FileKind {
+    pub fn from_mime(mime: &str) -> Self {
+        let (major, minor) = mime.split_once('/').unwrap_or((mime, ""));
+        match (major, minor) {
+            ("text", "markdown") => FileKind::Markdown,
+            ("text", _) => FileKind::Text,
+            ("image", _) => FileKind::Image,
+            ("video", _) => FileKind::Video,
+            ("audio", _) => FileKind::Audio,
+            ("application", "pdf") => FileKind::Pdf,
+            ("application", "json" | "xml" | "yaml" | "toml" | "javascript" | "x-shellscript" | "x-php" | "x-ruby" | "x-perl") => FileKind::Text,
+            ("application", "zip" | "gzip" | "x-tar" | "x-xz" | "zstd" | "x-bzip2" | "x-7z-compressed") => FileKind::Archive,
+            ("application", "octet-stream" | "x-executable" | "x-sharedlib" | "x-msdownload") => FileKind::Binary,
+            _ => FileKind::Other,
+        }
+    }
+
+    /// Short tag drawn on file icons.
+    pub fn tag(self) -> &'static str {
+        match self {
+            FileKind::Text => "TXT",
+            FileKind::Markdown => "MD",
+            FileKind::Video => "VID",
+            FileKind::Image => "IMG",
+            FileKind::Pdf => "PDF",
+            FileKind::Audio => "AUD",
+            FileKind::Archive => "ARC",
+            FileKind::Binary => "BIN",
+            FileKind::Other => "",
+        }
+    }
+}
+
+/// MIME type guessed from the extension alone; `None` for unknown or missing extensions.
+pub fn mime_from_extension(path: &Path) -> Option<&'static str> {
+    let extension = path.extension().and_then(|ext| ext.to_str())?;
+    let mime = match extension.to_lowercase().as_str() {
+        "txt" | "log" | "ini" | "cfg" | "conf" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "rs" => "text/rust",
+        "py" => "text/x-python",
+        "js" => "text/javascript",
+        "html" => "text/html",
+        "css" => "text/css",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "yml" | "yaml" => "application/yaml",
+        "toml" => "application/toml",
+        "c" | "h" => "text/x-c",
+        "cpp" | "hpp" => "text/x-c++",
+        "java" => "text/x-java",
+        "go" => "text/x-go",
+        "php" => "application/x-php",
+        "rb" => "application/x-ruby",
+        "sh" | "bash" | "zsh" | "fish" => "application/x-shellscript",
+        "mp4" | "m4v" => "video/mp4",
+        "avi" => "video/x-msvideo",
+        "mkv" => "video/x-matroska",
+        "mov" => "video/quicktime",
+        "wmv" => "video/x-ms-wmv",
+        "flv" => "video/x-flv",
+        "webm" => "video/webm",
+        "3gp" => "video/3gpp",
+        "ogv" => "video/ogg",
+        "mpeg" | "mpg" => "video/mpeg",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "tiff" | "tif" => "image/tiff",
+        "ico" => "image/vnd.microsoft.icon",
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/x-wav",
+        "flac" => "audio/flac",
+        "ogg" => "audio/ogg",
+        "aac" => "audio/aac",
+        "m4a" => "audio/mp4",
+        "wma" => "audio/x-ms-wma",
+        "zip" => "application/zip",
+        "tar" => "application/x-tar",
+        "gz" => "application/gzip",
+        "xz" => "application/x-xz",
+        "zst" => "application/zstd",
+        "bz2" => "application/x-bzip2",
+        "7z" => "application/x-7z-compressed",
+        "bin" | "img" | "rom" | "fw" | "dat" | "elf" | "raw" | "dump" => "application/octet-stream",
+        _ => return None,
+    };
+    Some(mime)
+}
@@ -0,0 +1,294 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+
+use crate::file_kind::{self, FileKind};
+use crate::vfs::{LocalFs, Vfs};
+
+/// How many leading bytes are read for sniffing; enough for the tar header magic at 257.
+const SNIFF_BYTES: usize = 512;
+
+/// Detected type of a file: a MIME type plus the broad category derived from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileType {
+    pub mime: &'static str,
+    pub kind: FileKind,
+}
+
+impl FileType {
+    fn from_mime(mime: &'static str) -> Self {
+        Self { mime, kind: FileKind::from_mime(mime) }
+    }
+}
+
+/// Detects the type of `path` from its content, falling back to the extension
+/// when the content is not conclusive (plain text formats have no magic).
+pub fn detect(path: &Path) -> FileType {
+    detect_in(&LocalFs, path)
+}
+
+/// `detect` for a file on another filesystem.
+pub fn detect_in(vfs: &dyn Vfs, path: &Path) -> FileType {
+    let head = vfs.read_head(path, SNIFF_BYTES as u64).unwrap_or_default();
+    detect_bytes(&head, path)
+}
+
+/// The type the extension suggests, for showing until `detect` has run;
+/// `None` when the extension is unknown or missing.
+pub fn guess(path: &Path) -> Option<FileType> {
+    file_kind::mime_from_extension(path).map(FileType::from_mime)
+}
+
+struct Job {
+    generation: u64,
+    index: usize,
+    vfs: Arc<dyn Vfs>,
+    path: PathBuf,
+}
+
+struct Done {
+    generation: u64,
+    index: usize,
+    file_type: FileType,
+}
+
+/// Runs `detect_in` on a worker thread, since sniffing reads from the file
+/// and on remote filesystems that is a network round trip. Requests are
+/// keyed by an index the caller chooses, e.g. the position in a listing.
+pub struct Detector {
+    /// Bumped by `clear`; the worker skips jobs from older generations.
+    generation: Arc<AtomicU64>,
+    jobs: Sender<Job>,
+    done: Receiver<Done>,
+}
+
+impl Detector {
+    /// Starts the worker, which calls `wake` after each result.
+    pub fn new(wake: impl Fn() + Send + 'static) -> Self {
+        let (job_tx, job_rx) = mpsc::channel::<Job>();
+        let (done_tx, done_rx) = mpsc::channel::<Done>();
+        let generation = Arc::new(AtomicU64::new(0));
+        let current = Arc::clone(&generation);
+        std::thread::spawn(move || {
+            for job in job_rx {
+                if job.generation != current.load(Ordering::Relaxed) {
+                    continue;
+                }
+                let file_type = detect_in(job.vfs.as_ref(), &job.path);
+                if done_tx.send(Done { generation: job.generation, index: job.index, file_type }).is_err() {
+                    return;
+                }
+                wake();
+            }
+        });
+        Self { generation, jobs: job_tx, done: done_rx }
+    }
+
+    /// Drops the queued requests and any results not yet polled.
+    pub fn clear(&mut self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn request(&self, index: usize, vfs: Arc<dyn Vfs>, path: PathBuf) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let _ = self.jobs.send(Job { generation, index, vfs, path });
+    }
+
+    /// The types detected since the last call, by index.
+    pub fn poll(&self) -> Vec<(usize, FileType)> {
+        let generation = self.generation.load(Ordering::Relaxed);
+        self.done.try_iter().filter(|done| done.generation == generation).map(|done| (done.index, done.file_type)).collect()
+    }
+}
+
+pub fn detect_bytes(head: &[u8], path: &Path) -> FileType {
+    if let Some(mime) = sniff_magic(head).or_else(|| sniff_shebang(head)) {
+        return FileType::from_mime(mime);
+    }
+    if let Some(mime) = file_kind::mime_from_extension(path) {
+        return FileType::from_mime(mime);
+    }
+    if head.is_empty() {
+        return FileType::from_mime("application/x-zerosize");
+    }
+    if sniff_markup(head) == Some("image/svg+xml") {
+        return FileType::from_mime("image/svg+xml");
+    }
+    if looks_like_text(head) {
+        return FileType::from_mime(sniff_markup(head).unwrap_or("text/plain"));
+    }
+    FileType::from_mime("application/octet-stream")
+}
+
+fn sniff_magic(head: &[u8]) -> Option<&'static str> {
+    let at = |offset: usize, magic: &[u8]| head.get(offset..offset + magic.len()) == Some(magic);
+    let mime = if at(0, b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else if at(0, b"\xff\xd8\xff") {
+        "image/jpeg"
+    } else if at(0, b"GIF87a") || at(0, b"GIF89a") {
+        "image/gif"
+    } else if at(0, b"RIFF") && at(8, b"WEBP") {
+        "image/webp"
+    } else if at(0, b"RIFF") && at(8, b"WAVE") {
+        "audio/x-wav"
+    } else if at(0, b"RIFF") && at(8, b"AVI ") {
+        "video/x-msvideo"
+    } else if at(0, b"II*\0") || at(0, b"MM\0*") {
+        "image/tiff"
+    } else if at(0, b"BM") && head.len() >= 26 && at(14, &[40, 0, 0, 0]) {
+        "image/bmp"
+    } else if at(0, b"\0\0\x01\0") && head.len() >= 22 {
+        "image/vnd.microsoft.icon"
+    } else if at(0, b"%PDF-") {
+        "application/pdf"
+    } else if at(0, b"PK\x03\x04") || at(0, b"PK\x05\x06") {
+        "application/zip"
+    } else if at(0, b"\x1f\x8b") {
+        "application/gzip"
+    } else if at(0, b"\xfd7zXZ\0") {
+        "application/x-xz"
+    } else if at(0, b"\x28\xb5\x2f\xfd") {
+        "application/zstd"
+    } else if at(0, b"BZh") {
+        "application/x-bzip2"
+    } else if at(0, b"7z\xbc\xaf\x27\x1c") {
+        "application/x-7z-compressed"
+    } else if at(257, b"ustar") {
+        "application/x-tar"
+    } else if at(0, b"\x7fELF") {
+        // e_type 3 is ET_DYN (shared objects and PIE executables alike)
+        if head.get(16) == Some(&3) { "application/x-sharedlib" } else { "application/x-executable" }
+    } else if at(0, b"MZ") {
+        "application/x-msdownload"
+    } else if at(0, b"SQLite format 3\0") {
+        "application/vnd.sqlite3"
+    } else if at(0, b"fLaC") {
+        "audio/flac"
+    } else if at(0, b"OggS") {
+        "audio/ogg"
+    } else if at(0, b"ID3") || at(0, b"\xff\xfb") || at(0, b"\xff\xf3") {
+        "audio/mpeg"
+    } else if at(4, b"ftyp") {
+        if at(8, b"M4A ") { "audio/mp4" } else if at(8, b"qt  ") { "video/quicktime" } else { "video/mp4" }
+    } else if at(0, b"\x1a\x45\xdf\xa3") {
+        if head.windows(4).any(|w| w == b"webm") { "video/webm" } else { "video/x-matroska" }
+    } else {
+        return None;
+    };
+    Some(mime)
+}
+
+/// `#!/usr/bin/env python3` and friends.
+fn sniff_shebang(head: &[u8]) -> Option<&'static str> {
+    let rest = head.strip_prefix(b"#!")?;
+    let line = rest.split(|&b| b == b'\n').next()?;
+    let line = std::str::from_utf8(line).ok()?;
+    let mut words = line.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        interpreter = words.find(|w| !w.starts_with('-'))?;
+    }
+    let name = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    let mime = match name {
+        "sh" | "bash" | "dash" | "zsh" | "ksh" | "fish" => "application/x-shellscript",
+        "python" => "text/x-python",
+        "perl" => "application/x-perl",
+        "ruby" => "application/x-ruby",
+        "node" | "nodejs" | "deno" => "text/javascript",
+        "php" => "application/x-php",
+        _ => "text/plain",
+    };
+    Some(mime)
+}
+
+fn sniff_markup(head: &[u8]) -> Option<&'static str> {
+    let text = String::from_utf8_lossy(head);
+    let start = text.trim_start_matches('\u{feff}').trim_start().to_ascii_lowercase();
+    if start.starts_with("<svg") || (start.starts_with("<?xml") && start.contains("<svg")) {
+        Some("image/svg+xml")
+    } else if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        Some("text/html")
+    } else if start.starts_with("<?xml") {
+        Some("application/xml")
+    } else {
+        None
+    }
+}
+
+fn looks_like_text(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => true,
+        // A multi-byte character cut off by the sniff window is still text
+        Err(err) => err.error_len().is_none() && head.len() - err.valid_up_to() < 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn magic_numbers_identify_formats() {
+        assert_eq!(sniff_magic(b"\x89PNG\r\n\x1a\n...."), Some("image/png"));
+        assert_eq!(sniff_magic(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff_magic(b"RIFF\0\0\0\0WAVEfmt "), Some("audio/x-wav"));
+        assert_eq!(sniff_magic(b"\0\0\0\x20ftypM4A "), Some("audio/mp4"));
+        assert_eq!(sniff_magic(b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\x03\0"), Some("application/x-sharedlib"));
+        assert_eq!(sniff_magic(b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\x02\0"), Some("application/x-executable"));
+        let mut tar = vec![0; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(sniff_magic(&tar), Some("application/x-tar"));
+        // Too short for the BMP header it starts like
+        assert_eq!(sniff_magic(b"BM"), None);
+        assert_eq!(sniff_magic(b"hello"), None);
+    }
+
+    #[test]
+    fn text_survives_a_cut_multibyte_character() {
+        assert!(looks_like_text(b"plain words\n"));
+        assert!(looks_like_text("caf\u{e9}".as_bytes()));
+        assert!(looks_like_text(&"caf\u{e9}".as_bytes()[..4]));
+        assert!(!looks_like_text(b"caf\xe9 au lait"));
+        assert!(!looks_like_text(b"text\0with a NUL"));
+    }
+
+    #[test]
+    fn content_beats_the_extension() {
+        let mime = |head: &[u8], name: &str| detect_bytes(head, Path::new(name)).mime;
+        assert_eq!(mime(b"\x89PNG\r\n\x1a\n", "photo.txt"), "image/png");
+        assert_eq!(mime(b"#!/usr/bin/env -S python3 -u\n", "run"), "text/x-python");
+        assert_eq!(mime(b"fn main() {}\n", "main.rs"), "text/rust");
+        assert_eq!(mime(b"", "empty"), "application/x-zerosize");
+        assert_eq!(mime(b"<?xml version=\"1.0\"?>\n<svg/>", "logo"), "image/svg+xml");
+        assert_eq!(mime(b"<!DOCTYPE html>", "index"), "text/html");
+        assert_eq!(mime(b"just notes\n", "README"), "text/plain");
+        assert_eq!(mime(b"\x01\x02\0\x03", "blob"), "application/octet-stream");
+        assert_eq!(detect_bytes(b"hello", Path::new("notes.md")).kind, FileKind::Markdown);
+    }
+
+    #[test]
+    fn detector_reports_the_current_generation_only() {
+        let fs = MemoryFs::default();
+        fs.add_file("/notes", "plain words").add_file("/photo.txt", &b"\x89PNG\r\n\x1a\n"[..]);
+        let vfs: Arc<dyn Vfs> = Arc::new(fs);
+        let mut detector = Detector::new(|| {});
+
+        detector.request(0, Arc::clone(&vfs), PathBuf::from("/notes"));
+        detector.clear();
+        detector.request(1, Arc::clone(&vfs), PathBuf::from("/photo.txt"));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut detected = Vec::new();
+        while detected.is_empty() && Instant::now() < deadline {
+            detected = detector.poll();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(detected, vec![(1, FileType::from_mime("image/png"))]);
+    }
+}
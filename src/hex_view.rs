@@ -0,0 +1,567 @@
+use eframe::egui;
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+const BYTES_PER_ROW: usize = 16;
+const MAX_FILE_SIZE: u64 = 256 * 1024 * 1024;
+// Column layout in characters: "00000000  xx xx xx xx xx xx xx xx  xx xx xx xx xx xx xx xx  |ascii...........|"
+const HEX_COLUMN: usize = 10;
+const ASCII_COLUMN: usize = HEX_COLUMN + BYTES_PER_ROW * 3 + 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SearchMode {
+    Hex,
+    Text,
+}
+
+/// Hex viewer with an optional overwrite editor for a single file.
+pub struct HexView {
+    pub open: bool,
+    path: PathBuf,
+    data: Vec<u8>,
+    modified: BTreeSet<usize>,
+    error: Option<String>,
+    status: Option<String>,
+    /// Selected byte range, inclusive; `selection.0` is the cursor.
+    selection: Option<(usize, usize)>,
+    high_nibble_pending: bool,
+    editing: bool,
+    little_endian: bool,
+    goto_input: String,
+    search_input: String,
+    search_mode: SearchMode,
+    scroll_to_row: Option<usize>,
+    /// Closing was asked for with unsaved edits; waiting for an answer.
+    confirm_close: bool,
+}
+
+impl HexView {
+    pub fn new(path: &Path) -> Self {
+        let mut view = Self {
+            open: true,
+            path: path.to_path_buf(),
+            data: Vec::new(),
+            modified: BTreeSet::new(),
+            error: None,
+            status: None,
+            selection: None,
+            high_nibble_pending: true,
+            editing: false,
+            little_endian: true,
+            goto_input: String::new(),
+            search_input: String::new(),
+            search_mode: SearchMode::Hex,
+            scroll_to_row: None,
+            confirm_close: false,
+        };
+        match std::fs::metadata(path) {
+            Ok(meta) if meta.len() > MAX_FILE_SIZE => {
+                view.error = Some(format!("File is too large for the hex viewer ({} bytes)", meta.len()));
+            }
+            _ => match std::fs::read(path) {
+                Ok(data) => view.data = data,
+                Err(err) => view.error = Some(format!("Failed to read file: {}", err)),
+            },
+        }
+        view
+    }
+
+    /// Whether there are edits that haven't been saved.
+    pub fn is_dirty(&self) -> bool {
+        !self.modified.is_empty()
+    }
+
+    /// Closes the viewer, first asking whether to save any edits.
+    pub fn request_close(&mut self) {
+        if self.is_dirty() {
+            self.confirm_close = true;
+        } else {
+            self.open = false;
+        }
+    }
+
+    /// Whether the save-or-discard prompt is showing.
+    pub fn is_asking(&self) -> bool {
+        self.confirm_close
+    }
+
+    fn select(&mut self, offset: usize, extend: bool) {
+        let offset = offset.min(self.data.len().saturating_sub(1));
+        self.selection = match (extend, self.selection) {
+            (true, Some((start, _))) => Some((start, offset)),
+            _ => Some((offset, offset)),
+        };
+        self.high_nibble_pending = true;
+    }
+
+    fn selection_range(&self) -> Option<(usize, usize)> {
+        self.selection.map(|(a, b)| (a.min(b), a.max(b)))
+    }
+
+    fn goto(&mut self) {
+        match parse_offset(&self.goto_input) {
+            Some(offset) if offset < self.data.len() => {
+                self.select(offset, false);
+                self.scroll_to_row = Some(offset / BYTES_PER_ROW);
+                self.status = None;
+            }
+            Some(offset) => self.status = Some(format!("Offset 0x{:x} is past the end of the file", offset)),
+            None => self.status = Some("Invalid offset".to_string()),
+        }
+    }
+
+    fn search(&mut self) {
+        let pattern = match self.search_mode {
+            SearchMode::Hex => match parse_hex_pattern(&self.search_input) {
+                Some(pattern) => pattern,
+                None => {
+                    self.status = Some("Invalid hex pattern".to_string());
+                    return;
+                }
+            },
+            SearchMode::Text => self.search_input.as_bytes().to_vec(),
+        };
+        if pattern.is_empty() {
+            return;
+        }
+        let start = self.selection.map(|(cursor, _)| cursor + 1).unwrap_or(0);
+        let found = find(&self.data, &pattern, start).or_else(|| find(&self.data, &pattern, 0));
+        match found {
+            Some(offset) => {
+                self.selection = Some((offset, offset + pattern.len() - 1));
+                self.scroll_to_row = Some(offset / BYTES_PER_ROW);
+                self.status = Some(format!("Found at 0x{:08x}", offset));
+            }
+            None => self.status = Some("Pattern not found".to_string()),
+        }
+    }
+
+    fn save(&mut self) {
+        match write_atomically(&self.path, &self.data) {
+            Ok(()) => {
+                self.modified.clear();
+                self.status = Some("Saved".to_string());
+            }
+            Err(err) => self.status = Some(format!("Failed to save: {}", err)),
+        }
+    }
+
+    /// Overwrites one nibble at the cursor, advancing after the low nibble.
+    fn type_nibble(&mut self, nibble: u8) {
+        let Some((cursor, _)) = self.selection else { return };
+        let Some(byte) = self.data.get_mut(cursor) else { return };
+        if self.high_nibble_pending {
+            *byte = (*byte & 0x0f) | (nibble << 4);
+            self.high_nibble_pending = false;
+        } else {
+            *byte = (*byte & 0xf0) | nibble;
+            self.high_nibble_pending = true;
+            if cursor + 1 < self.data.len() {
+                self.selection = Some((cursor + 1, cursor + 1));
+            }
+        }
+        self.modified.insert(cursor);
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let title = paths::display_file_name(&self.path);
+        let dirty = if self.modified.is_empty() { "" } else { " *" };
+        let mut open = self.open;
+        egui::Window::new(format!("Hex — {}{}", title, dirty))
+            .id(egui::Id::new("hex_view"))
+            .open(&mut open)
+            .default_size(egui::vec2(860.0, 600.0))
+            .resizable(true)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                if let Some(err) = &self.error {
+                    ui.colored_label(egui::Color32::RED, err);
+                    return;
+                }
+                self.toolbar(ui);
+                ui.separator();
+                ui.horizontal_top(|ui| {
+                    let inspector_width = 240.0;
+                    ui.allocate_ui(egui::vec2(ui.available_width() - inspector_width, ui.available_height()), |ui| {
+                        self.rows(ui);
+                    });
+                    ui.separator();
+                    ui.vertical(|ui| self.inspector(ui));
+                });
+            });
+        if !open {
+            self.request_close();
+        }
+        if self.confirm_close {
+            self.close_prompt(ctx);
+        } else if self.open && self.editing {
+            self.handle_typing(ctx);
+        }
+    }
+
+    /// Asks whether to save, discard or keep the edits when closing.
+    fn close_prompt(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Unsaved changes")
+            .id(egui::Id::new("hex_view_close"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!("{} has {} edited bytes that were not saved.", paths::display_file_name(&self.path), self.modified.len()));
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        self.save();
+                        // A failed save stays open with its status showing
+                        self.confirm_close = false;
+                        self.open = !self.modified.is_empty();
+                    }
+                    if ui.button("Discard").clicked() {
+                        self.confirm_close = false;
+                        self.open = false;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.confirm_close = false;
+                    }
+                });
+            });
+    }
+
+    fn toolbar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Goto:");
+            let goto = ui.add(egui::TextEdit::singleline(&mut self.goto_input).desired_width(100.0).hint_text("0x0"));
+            if ui.button("Go").clicked() || (goto.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))) {
+                self.goto();
+            }
+            ui.separator();
+            ui.label("Find:");
+            let search = ui.add(egui::TextEdit::singleline(&mut self.search_input).desired_width(160.0));
+            ui.selectable_value(&mut self.search_mode, SearchMode::Hex, "Hex");
+            ui.selectable_value(&mut self.search_mode, SearchMode::Text, "Text");
+            if ui.button("Next").clicked() || (search.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))) {
+                self.search();
+            }
+            ui.separator();
+            ui.checkbox(&mut self.editing, "Edit");
+            if ui.add_enabled(!self.modified.is_empty(), egui::Button::new("Save")).clicked() {
+                self.save();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(format!("{} bytes", self.data.len())).small());
+            if let Some(status) = &self.status {
+                ui.separator();
+                ui.label(egui::RichText::new(status).small());
+            }
+        });
+    }
+
+    fn rows(&mut self, ui: &mut egui::Ui) {
+        let font = egui::FontId::monospace(13.0);
+        let row_height = ui.fonts(|f| f.row_height(&font));
+        let char_width = ui.fonts(|f| f.glyph_width(&font, '0'));
+        let green = egui::Color32::from_rgb(0, 255, 0);
+        let dim_green = egui::Color32::from_rgb(0, 150, 0);
+        let edited = egui::Color32::from_rgb(255, 200, 0);
+        let selection_fill = egui::Color32::from_rgba_unmultiplied(0, 255, 0, 60);
+        let total_rows = self.data.len().div_ceil(BYTES_PER_ROW);
+
+        let mut scroll = egui::ScrollArea::vertical().auto_shrink([false; 2]);
+        if let Some(row) = self.scroll_to_row.take() {
+            scroll = scroll.vertical_scroll_offset(row as f32 * (row_height + ui.spacing().item_spacing.y));
+        }
+        let selection = self.selection_range();
+        let mut clicked: Option<(usize, bool)> = None;
+
+        scroll.show_rows(ui, row_height, total_rows, |ui, row_range| {
+            for row in row_range {
+                let row_start = row * BYTES_PER_ROW;
+                let row_bytes = &self.data[row_start..(row_start + BYTES_PER_ROW).min(self.data.len())];
+                let width = char_width * (ASCII_COLUMN + BYTES_PER_ROW + 2) as f32;
+                let (rect, response) = ui.allocate_exact_size(egui::vec2(width, row_height), egui::Sense::click());
+                let painter = ui.painter();
+                let x_of = |col: usize| rect.left() + col as f32 * char_width;
+
+                painter.text(rect.left_top(), egui::Align2::LEFT_TOP, format!("{:08x}", row_start), font.clone(), dim_green);
+                for (i, &byte) in row_bytes.iter().enumerate() {
+                    let offset = row_start + i;
+                    let hex_x = x_of(hex_col(i));
+                    let ascii_x = x_of(ASCII_COLUMN + 1 + i);
+                    if let Some((start, end)) = selection
+                        && (start..=end).contains(&offset) {
+                        let hex_rect = egui::Rect::from_min_size(egui::pos2(hex_x, rect.top()), egui::vec2(char_width * 2.0, row_height));
+                        let ascii_rect = egui::Rect::from_min_size(egui::pos2(ascii_x, rect.top()), egui::vec2(char_width, row_height));
+                        painter.rect_filled(hex_rect, 0.0, selection_fill);
+                        painter.rect_filled(ascii_rect, 0.0, selection_fill);
+                    }
+                    let color = if self.modified.contains(&offset) { edited } else { green };
+                    painter.text(egui::pos2(hex_x, rect.top()), egui::Align2::LEFT_TOP, format!("{:02x}", byte), font.clone(), color);
+                    let ch = if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' };
+                    painter.text(egui::pos2(ascii_x, rect.top()), egui::Align2::LEFT_TOP, ch, font.clone(), color);
+                }
+                painter.text(egui::pos2(x_of(ASCII_COLUMN), rect.top()), egui::Align2::LEFT_TOP, "|", font.clone(), dim_green);
+                painter.text(egui::pos2(x_of(ASCII_COLUMN + 1 + BYTES_PER_ROW), rect.top()), egui::Align2::LEFT_TOP, "|", font.clone(), dim_green);
+
+                if response.clicked()
+                    && let Some(pos) = response.interact_pointer_pos() {
+                    let col = ((pos.x - rect.left()) / char_width) as usize;
+                    if let Some(i) = byte_at_column(col) && i < row_bytes.len() {
+                        let extend = ui.input(|input| input.modifiers.shift);
+                        clicked = Some((row_start + i, extend));
+                    }
+                }
+            }
+        });
+
+        if let Some((offset, extend)) = clicked {
+            self.select(offset, extend);
+        }
+    }
+
+    fn inspector(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Inspector");
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.little_endian, true, "LE");
+            ui.selectable_value(&mut self.little_endian, false, "BE");
+        });
+        ui.separator();
+        let Some((start, end)) = self.selection_range() else {
+            ui.label("No selection");
+            return;
+        };
+        ui.label(egui::RichText::new(format!("Offset 0x{:08x} ({})", start, start)).monospace());
+        if end > start {
+            ui.label(egui::RichText::new(format!("Selected {} bytes", end - start + 1)).monospace());
+        }
+        ui.separator();
+        let bytes = &self.data[start..self.data.len().min(start + 8)];
+        egui::Grid::new("hex_inspector").num_columns(2).show(ui, |ui| {
+            for (label, value) in inspect(bytes, self.little_endian) {
+                ui.label(egui::RichText::new(label).monospace());
+                ui.label(egui::RichText::new(value).monospace());
+                ui.end_row();
+            }
+        });
+    }
+
+    fn handle_typing(&mut self, ctx: &egui::Context) {
+        if self.selection.is_none() || ctx.memory(|m| m.focused().is_some()) {
+            return;
+        }
+        let typed: Vec<u8> = ctx.input(|i| {
+            i.events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::Text(text) => Some(text.clone()),
+                    _ => None,
+                })
+                .flat_map(|text| text.chars().filter_map(|c| c.to_digit(16)).map(|d| d as u8).collect::<Vec<_>>())
+                .collect()
+        });
+        for nibble in typed {
+            self.type_nibble(nibble);
+        }
+    }
+}
+
+fn hex_col(i: usize) -> usize {
+    HEX_COLUMN + i * 3 + if i >= BYTES_PER_ROW / 2 { 1 } else { 0 }
+}
+
+/// Maps a character column of a row back to the byte index it shows.
+fn byte_at_column(col: usize) -> Option<usize> {
+    if col > ASCII_COLUMN && col <= ASCII_COLUMN + BYTES_PER_ROW {
+        return Some(col - ASCII_COLUMN - 1);
+    }
+    (0..BYTES_PER_ROW).find(|&i| col >= hex_col(i) && col < hex_col(i) + 2)
+}
+
+/// Replaces the contents of `path` through a temporary file beside it, so a
+/// failed write leaves the original intact. Symlinks are followed and the
+/// file's permissions kept; read-only files, such as local copies of remote
+/// files, are refused rather than replaced.
+fn write_atomically(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let path = std::fs::canonicalize(path)?;
+    let permissions = std::fs::metadata(&path)?.permissions();
+    if permissions.readonly() {
+        return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "the file is read-only"));
+    }
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".datara-tmp");
+    let tmp = path.with_file_name(name);
+    let result = (|| {
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(data)?;
+        file.set_permissions(permissions)?;
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(&tmp, &path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Accepts `0x1f`, `1fh` style hex and plain decimal offsets.
+pub fn parse_offset(input: &str) -> Option<usize> {
+    let input = input.trim();
+    if let Some(hex) = input.strip_prefix("0x").or_else(|| input.strip_prefix("0X")) {
+        usize::from_str_radix(hex, 16).ok()
+    } else if let Some(hex) = input.strip_suffix('h') {
+        usize::from_str_radix(hex, 16).ok()
+    } else {
+        input.parse().ok()
+    }
+}
+
+/// Parses `de ad be ef` / `deadbeef` into bytes.
+pub fn parse_hex_pattern(input: &str) -> Option<Vec<u8>> {
+    let digits: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    let digits = digits.strip_prefix("0x").unwrap_or(&digits);
+    if !digits.is_ascii() || !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok())
+        .collect()
+}
+
+pub fn find(haystack: &[u8], needle: &[u8], start: usize) -> Option<usize> {
+    if needle.is_empty() || start >= haystack.len() {
+        return None;
+    }
+    haystack[start..].windows(needle.len()).position(|w| w == needle).map(|pos| pos + start)
+}
+
+/// Interprets the bytes at the cursor as the common integer and float types.
+pub fn inspect(bytes: &[u8], little_endian: bool) -> Vec<(&'static str, String)> {
+    fn take<const N: usize>(bytes: &[u8], little_endian: bool) -> Option<[u8; N]> {
+        let mut arr: [u8; N] = bytes.get(..N)?.try_into().ok()?;
+        if !little_endian {
+            arr.reverse();
+        }
+        Some(arr)
+    }
+    let mut rows = Vec::new();
+    if let Some(b) = take::<1>(bytes, true) {
+        rows.push(("u8", u8::from_le_bytes(b).to_string()));
+        rows.push(("i8", i8::from_le_bytes(b).to_string()));
+        rows.push(("bin", format!("{:08b}", b[0])));
+    }
+    if let Some(b) = take::<2>(bytes, little_endian) {
+        rows.push(("u16", u16::from_le_bytes(b).to_string()));
+        rows.push(("i16", i16::from_le_bytes(b).to_string()));
+    }
+    if let Some(b) = take::<4>(bytes, little_endian) {
+        rows.push(("u32", u32::from_le_bytes(b).to_string()));
+        rows.push(("i32", i32::from_le_bytes(b).to_string()));
+        rows.push(("f32", f32::from_le_bytes(b).to_string()));
+    }
+    if let Some(b) = take::<8>(bytes, little_endian) {
+        rows.push(("u64", u64::from_le_bytes(b).to_string()));
+        rows.push(("i64", i64::from_le_bytes(b).to_string()));
+        rows.push(("f64", f64::from_le_bytes(b).to_string()));
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn value<'a>(rows: &'a [(&'static str, String)], name: &str) -> Option<&'a str> {
+        rows.iter().find(|(row, _)| *row == name).map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn offsets_accept_hex_and_decimal() {
+        assert_eq!(parse_offset("0x1f"), Some(0x1f));
+        assert_eq!(parse_offset("0X1F"), Some(0x1f));
+        assert_eq!(parse_offset(" 1fh "), Some(0x1f));
+        assert_eq!(parse_offset("31"), Some(31));
+        assert_eq!(parse_offset(""), None);
+        assert_eq!(parse_offset("0x"), None);
+        assert_eq!(parse_offset("1g"), None);
+        assert_eq!(parse_offset("-1"), None);
+    }
+
+    #[test]
+    fn hex_patterns_ignore_spaces_and_reject_odd_digits() {
+        assert_eq!(parse_hex_pattern("de ad be ef"), Some(vec![0xde, 0xad, 0xbe, 0xef]));
+        assert_eq!(parse_hex_pattern("0xDEAD"), Some(vec![0xde, 0xad]));
+        assert_eq!(parse_hex_pattern(""), Some(Vec::new()));
+        assert_eq!(parse_hex_pattern("abc"), None);
+        assert_eq!(parse_hex_pattern("zz"), None);
+        assert_eq!(parse_hex_pattern("é1"), None);
+    }
+
+    #[test]
+    fn find_searches_from_the_start_offset() {
+        let haystack = b"abcabc";
+        assert_eq!(find(haystack, b"bc", 0), Some(1));
+        assert_eq!(find(haystack, b"bc", 2), Some(4));
+        assert_eq!(find(haystack, b"bc", 5), None);
+        assert_eq!(find(haystack, b"bc", 100), None);
+        assert_eq!(find(haystack, b"", 0), None);
+        assert_eq!(find(b"ab", b"abc", 0), None);
+    }
+
+    #[test]
+    fn inspect_follows_the_byte_order() {
+        let bytes = [0x01, 0x02, 0x03, 0x04, 0x00, 0x00, 0x00, 0x80];
+        let little = inspect(&bytes, true);
+        assert_eq!(value(&little, "u8"), Some("1"));
+        assert_eq!(value(&little, "bin"), Some("00000001"));
+        assert_eq!(value(&little, "u16"), Some("513"));
+        assert_eq!(value(&little, "u32"), Some("67305985"));
+        assert_eq!(value(&little, "i64"), Some(i64::from_le_bytes(bytes).to_string().as_str()));
+        let big = inspect(&bytes, false);
+        assert_eq!(value(&big, "u8"), Some("1"));
+        assert_eq!(value(&big, "u16"), Some("258"));
+        assert_eq!(value(&big, "u32"), Some("16909060"));
+        assert_eq!(value(&big, "u64"), Some(u64::from_be_bytes(bytes).to_string().as_str()));
+        assert_eq!(value(&inspect(&[0xff], true), "i8"), Some("-1"));
+        assert_eq!(value(&inspect(&[0x00, 0x00, 0x80, 0x3f], true), "f32"), Some("1"));
+    }
+
+    #[test]
+    fn inspect_skips_types_longer_than_the_bytes() {
+        let rows = inspect(&[0x01, 0x02, 0x03], true);
+        assert!(value(&rows, "u16").is_some());
+        assert!(value(&rows, "u32").is_none());
+        assert!(value(&rows, "f64").is_none());
+        assert!(inspect(&[], true).is_empty());
+    }
+
+    #[test]
+    fn saving_replaces_the_file_and_keeps_its_mode() {
+        let dir = std::env::temp_dir().join(format!("datara-hex-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("data.bin");
+        std::fs::write(&file, b"old contents").unwrap();
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o640)).unwrap();
+        let link = dir.join("link.bin");
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+
+        write_atomically(&link, b"new").unwrap();
+        assert_eq!(std::fs::read(&file).unwrap(), b"new");
+        assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(std::fs::metadata(&file).unwrap().permissions().mode() & 0o777, 0o640);
+
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o444)).unwrap();
+        let err = write_atomically(&file, b"newer").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        assert_eq!(std::fs::read(&file).unwrap(), b"new");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,311 @@
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+use crate::file_type;
+use crate::paths;
+
+const SLIDESHOW_INTERVAL: f64 = 3.0;
+const MAX_TEXTURE_SIDE: u32 = 8192;
+const MIN_ZOOM: f32 = 0.02;
+const MAX_ZOOM: f32 = 32.0;
+
+/// Whether the viewer can decode images of type `mime`; vector formats
+/// like SVG need a renderer it doesn't have.
+pub fn can_show(mime: &str) -> bool {
+    matches!(mime, "image/png" | "image/jpeg" | "image/gif" | "image/bmp" | "image/webp" | "image/tiff" | "image/vnd.microsoft.icon")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Zoom {
+    Fit,
+    /// Screen pixels per image pixel; 1.0 is 1:1.
+    Scale(f32),
+}
+
+struct LoadedImage {
+    image: image::DynamicImage,
+    texture: egui::TextureHandle,
+    color_type: image::ColorType,
+    file_size: Option<u64>,
+}
+
+/// In-app viewer for the images of one directory.
+pub struct ImageViewer {
+    pub open: bool,
+    images: Vec<PathBuf>,
+    index: usize,
+    loaded: Option<LoadedImage>,
+    error: Option<String>,
+    zoom: Zoom,
+    pan: egui::Vec2,
+    rotation: u8,
+    slideshow: bool,
+    last_advance: f64,
+    show_info: bool,
+}
+
+impl ImageViewer {
+    /// Opens `path`, collecting its sibling images for next/previous navigation.
+    /// Siblings are picked by extension, since sniffing every file of a large
+    /// folder would stall the UI; one that doesn't decode shows its error.
+    pub fn new(ctx: &egui::Context, path: &Path) -> Self {
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut images: Vec<PathBuf> = std::fs::read_dir(&dir)
+            .map(|read_dir| {
+                read_dir
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|p| file_type::guess(p).is_some_and(|file_type| can_show(file_type.mime)) && p.is_file())
+                    .collect()
+            })
+            .unwrap_or_default();
+        images.sort_by_key(|p| p.file_name().map(|n| n.to_string_lossy().to_lowercase()));
+        let index = match images.iter().position(|p| p == path) {
+            Some(index) => index,
+            None => {
+                images.insert(0, path.to_path_buf());
+                0
+            }
+        };
+
+        let mut viewer = Self {
+            open: true,
+            images,
+            index,
+            loaded: None,
+            error: None,
+            zoom: Zoom::Fit,
+            pan: egui::Vec2::ZERO,
+            rotation: 0,
+            slideshow: false,
+            last_advance: ctx.input(|i| i.time),
+            show_info: false,
+        };
+        viewer.load_current(ctx);
+        viewer
+    }
+
+    fn current_path(&self) -> &Path {
+        &self.images[self.index]
+    }
+
+    fn load_current(&mut self, ctx: &egui::Context) {
+        self.zoom = Zoom::Fit;
+        self.pan = egui::Vec2::ZERO;
+        self.rotation = 0;
+        self.loaded = None;
+        self.error = None;
+        let path = self.current_path().to_path_buf();
+        match image::open(&path) {
+            Ok(image) => {
+                let color_type = image.color();
+                let file_size = std::fs::metadata(&path).ok().map(|m| m.len());
+                let texture = Self::upload(ctx, &image);
+                self.loaded = Some(LoadedImage { image, texture, color_type, file_size });
+            }
+            Err(err) => self.error = Some(format!("Failed to load image: {}", err)),
+        }
+    }
+
+    fn upload(ctx: &egui::Context, image: &image::DynamicImage) -> egui::TextureHandle {
+        let image = if image.width() > MAX_TEXTURE_SIDE || image.height() > MAX_TEXTURE_SIDE {
+            image.resize(MAX_TEXTURE_SIDE, MAX_TEXTURE_SIDE, image::imageops::FilterType::Triangle)
+        } else {
+            image.clone()
+        };
+        let rgba_image = image.to_rgba8();
+        let size = [rgba_image.width() as usize, rgba_image.height() as usize];
+        let color_image = egui::ColorImage::from_rgba_unmultiplied(size, rgba_image.as_raw());
+        ctx.load_texture("image_viewer", color_image, egui::TextureOptions::LINEAR)
+    }
+
+    fn step(&mut self, ctx: &egui::Context, delta: isize) {
+        if self.images.is_empty() {
+            return;
+        }
+        let len = self.images.len() as isize;
+        self.index = (self.index as isize + delta).rem_euclid(len) as usize;
+        self.last_advance = ctx.input(|i| i.time);
+        self.load_current(ctx);
+    }
+
+    fn rotate(&mut self, ctx: &egui::Context) {
+        if let Some(loaded) = &mut self.loaded {
+            loaded.image = loaded.image.rotate90();
+            loaded.texture = Self::upload(ctx, &loaded.image);
+            self.rotation = (self.rotation + 1) % 4;
+            self.pan = egui::Vec2::ZERO;
+        }
+    }
+
+    /// Screen points per image pixel for the current zoom mode.
+    fn points_per_pixel(&self, ctx: &egui::Context, view: egui::Rect, image_size: egui::Vec2) -> f32 {
+        match self.zoom {
+            Zoom::Fit => (view.width() / image_size.x).min(view.height() / image_size.y).min(1.0 / ctx.pixels_per_point()),
+            Zoom::Scale(scale) => scale / ctx.pixels_per_point(),
+        }
+    }
+
+    fn zoom_by(&mut self, ctx: &egui::Context, view: egui::Rect, image_size: egui::Vec2, factor: f32, anchor: Option<egui::Pos2>) {
+        let old = self.points_per_pixel(ctx, view, image_size);
+        let scale = (old * ctx.pixels_per_point() * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.zoom = Zoom::Scale(scale);
+        let new = self.points_per_pixel(ctx, view, image_size);
+        // Keep the point under the cursor fixed while zooming
+        if let Some(anchor) = anchor {
+            let offset = anchor - view.center() - self.pan;
+            self.pan -= offset * (new / old - 1.0);
+        } else {
+            self.pan *= new / old;
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let title = paths::display_file_name(self.current_path());
+        let mut open = self.open;
+        egui::Window::new(format!("Image Viewer — {}", title))
+            .id(egui::Id::new("image_viewer"))
+            .open(&mut open)
+            .default_size(egui::vec2(900.0, 640.0))
+            .resizable(true)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                self.toolbar(ui);
+                ui.separator();
+                self.canvas(ui);
+            });
+        self.open = open;
+
+        if self.open && self.slideshow {
+            let now = ctx.input(|i| i.time);
+            if now - self.last_advance >= SLIDESHOW_INTERVAL {
+                self.step(ctx, 1);
+            }
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(SLIDESHOW_INTERVAL));
+        }
+    }
+
+    fn toolbar(&mut self, ui: &mut egui::Ui) {
+        let ctx = ui.ctx().clone();
+        ui.horizontal(|ui| {
+            if ui.button("◀").on_hover_text("Previous (←)").clicked() {
+                self.step(&ctx, -1);
+            }
+            if ui.button("▶").on_hover_text("Next (→)").clicked() {
+                self.step(&ctx, 1);
+            }
+            ui.label(format!("{}/{}", self.index + 1, self.images.len()));
+            ui.separator();
+            if ui.selectable_label(self.zoom == Zoom::Fit, "Fit").on_hover_text("F").clicked() {
+                self.zoom = Zoom::Fit;
+                self.pan = egui::Vec2::ZERO;
+            }
+            if ui.selectable_label(self.zoom == Zoom::Scale(1.0), "1:1").on_hover_text("1").clicked() {
+                self.zoom = Zoom::Scale(1.0);
+                self.pan = egui::Vec2::ZERO;
+            }
+            if let Zoom::Scale(scale) = self.zoom {
+                ui.label(format!("{:.0}%", scale * 100.0));
+            }
+            ui.separator();
+            if ui.button("⟳").on_hover_text("Rotate (R)").clicked() {
+                self.rotate(&ctx);
+            }
+            let slideshow_label = if self.slideshow { "Slideshow: On" } else { "Slideshow: Off" };
+            if ui.button(slideshow_label).on_hover_text("Space").clicked() {
+                self.slideshow = !self.slideshow;
+                self.last_advance = ctx.input(|i| i.time);
+            }
+            if ui.selectable_label(self.show_info, "Info").on_hover_text("I").clicked() {
+                self.show_info = !self.show_info;
+            }
+        });
+
+        // Keyboard shortcuts, ignored while another widget has focus
+        if ctx.memory(|m| m.focused().is_some()) {
+            return;
+        }
+        let (prev, next, rotate, fit, actual, slideshow, info, close) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::ArrowLeft),
+                i.key_pressed(egui::Key::ArrowRight),
+                i.key_pressed(egui::Key::R),
+                i.key_pressed(egui::Key::F),
+                i.key_pressed(egui::Key::Num1),
+                i.key_pressed(egui::Key::Space),
+                i.key_pressed(egui::Key::I),
+                i.key_pressed(egui::Key::Escape),
+            )
+        });
+        if prev { self.step(&ctx, -1); }
+        if next { self.step(&ctx, 1); }
+        if rotate { self.rotate(&ctx); }
+        if fit {
+            self.zoom = Zoom::Fit;
+            self.pan = egui::Vec2::ZERO;
+        }
+        if actual {
+            self.zoom = Zoom::Scale(1.0);
+            self.pan = egui::Vec2::ZERO;
+        }
+        if slideshow {
+            self.slideshow = !self.slideshow;
+            self.last_advance = ctx.input(|i| i.time);
+        }
+        if info { self.show_info = !self.show_info; }
+        if close { self.open = false; }
+    }
+
+    fn canvas(&mut self, ui: &mut egui::Ui) {
+        let ctx = ui.ctx().clone();
+        let (view, response) = ui.allocate_exact_size(ui.available_size(), egui::Sense::click_and_drag());
+        let painter = ui.painter_at(view);
+        painter.rect_filled(view, 0.0, egui::Color32::BLACK);
+
+        if let Some(err) = &self.error {
+            painter.text(view.center(), egui::Align2::CENTER_CENTER, err, egui::FontId::monospace(14.0), egui::Color32::RED);
+            return;
+        }
+        let Some(loaded) = &self.loaded else { return };
+        let texture_id = loaded.texture.id();
+        let image_size = egui::vec2(loaded.image.width() as f32, loaded.image.height() as f32);
+
+        if response.hovered() {
+            let scroll = ctx.input(|i| i.smooth_scroll_delta.y);
+            if scroll != 0.0 {
+                let anchor = ctx.input(|i| i.pointer.hover_pos());
+                self.zoom_by(&ctx, view, image_size, (scroll / 200.0).exp(), anchor);
+            }
+        }
+        if response.dragged() {
+            self.pan += response.drag_delta();
+        }
+        if response.double_clicked() {
+            self.zoom = if self.zoom == Zoom::Fit { Zoom::Scale(1.0) } else { Zoom::Fit };
+            self.pan = egui::Vec2::ZERO;
+        }
+
+        let scale = self.points_per_pixel(&ctx, view, image_size);
+        let image_rect = egui::Rect::from_center_size(view.center() + self.pan, image_size * scale);
+        painter.image(texture_id, image_rect, egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)), egui::Color32::WHITE);
+
+        if self.show_info {
+            let Some(loaded) = &self.loaded else { return };
+            let mut info = format!(
+                "{}\n{} × {}\n{:?}\nRotation: {}°",
+                paths::display_path(self.current_path()),
+                loaded.image.width(),
+                loaded.image.height(),
+                loaded.color_type,
+                self.rotation as u32 * 90,
+            );
+            if let Some(size) = loaded.file_size {
+                info.push_str(&format!("\n{} bytes", size));
+            }
+            let galley = painter.layout(info, egui::FontId::monospace(12.0), egui::Color32::from_rgb(0, 255, 0), view.width() - 24.0);
+            let text_rect = egui::Rect::from_min_size(view.min + egui::vec2(8.0, 8.0), galley.size()).expand(4.0);
+            painter.rect_filled(text_rect, 0.0, egui::Color32::from_rgba_unmultiplied(0, 12, 0, 210));
+            painter.galley(text_rect.min + egui::vec2(4.0, 4.0), galley, egui::Color32::from_rgb(0, 255, 0));
+        }
+    }
+}
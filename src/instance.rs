@@ -0,0 +1,209 @@
+use std::ffi::OsString;
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+/// How long either side waits for the other before giving up.
+const TIMEOUT: Duration = Duration::from_secs(5);
+const OPEN: &[u8] = b"open";
+const ACK: &[u8] = b"ok";
+
+/// Where the running instance listens: `$XDG_RUNTIME_DIR/datara.sock`. There
+/// is no fallback, since other users could claim a name in a shared
+/// directory like `/tmp`; without a runtime dir every launch stands alone.
+pub fn socket_path() -> Option<PathBuf> {
+    let dir = PathBuf::from(std::env::var_os("XDG_RUNTIME_DIR")?);
+    dir.is_absolute().then(|| dir.join("datara.sock"))
+}
+
+/// A launch handed to the running instance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenRequest {
+    /// The launching shell's directory, opened when there are no paths.
+    pub cwd: PathBuf,
+    /// Absolute paths, each to open in its own tab.
+    pub paths: Vec<PathBuf>,
+    pub select: Option<PathBuf>,
+}
+
+impl OpenRequest {
+    /// `open`, the directory, the selection (empty for none) and the paths,
+    /// each ended by a NUL, which no path can contain.
+    fn encode(&self) -> Vec<u8> {
+        let select = self.select.as_deref().unwrap_or(Path::new(""));
+        let mut bytes = Vec::new();
+        for field in [OPEN, self.cwd.as_os_str().as_bytes(), select.as_os_str().as_bytes()].into_iter().chain(self.paths.iter().map(|path| path.as_os_str().as_bytes())) {
+            bytes.extend_from_slice(field);
+            bytes.push(0);
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut fields = bytes.strip_suffix(b"\0")?.split(|&b| b == 0).map(|field| PathBuf::from(OsString::from_vec(field.to_vec())));
+        if fields.next()?.as_os_str().as_bytes() != OPEN {
+            return None;
+        }
+        let cwd = fields.next().filter(|cwd| cwd.is_absolute())?;
+        let select = fields.next().filter(|select| !select.as_os_str().is_empty());
+        let paths: Vec<PathBuf> = fields.collect();
+        if select.iter().chain(&paths).any(|path| !path.is_absolute()) {
+            return None;
+        }
+        Some(Self { cwd, paths, select })
+    }
+}
+
+pub enum Claim {
+    /// No instance was running; this one now owns the socket.
+    Primary(Server),
+    /// The running instance took the request.
+    Forwarded,
+}
+
+/// Hands `request` to the instance listening on `socket`, or becomes that
+/// instance when there is none.
+pub fn claim(socket: &Path, request: &OpenRequest) -> io::Result<Claim> {
+    match forward(socket, request) {
+        Ok(()) => return Ok(Claim::Forwarded),
+        // Left behind by an instance that crashed
+        Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => std::fs::remove_file(socket)?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+    match UnixListener::bind(socket) {
+        Ok(listener) => Ok(Claim::Primary(Server { listener, path: socket.to_path_buf() })),
+        // Another launch won the race; it is the instance now
+        Err(err) if err.kind() == io::ErrorKind::AddrInUse => forward(socket, request).map(|()| Claim::Forwarded),
+        Err(err) => Err(err),
+    }
+}
+
+fn forward(socket: &Path, request: &OpenRequest) -> io::Result<()> {
+    let mut stream = UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    stream.write_all(&request.encode())?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply)?;
+    if reply != ACK {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "the running instance did not accept the request"));
+    }
+    Ok(())
+}
+
+/// The socket of the running instance, bound but not yet answering.
+pub struct Server {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl Server {
+    /// Answers launches on a new thread, calling `wake` after each request
+    /// so the window can pick it up.
+    pub fn serve(self, wake: impl Fn() + Send + 'static) -> Inbox {
+        let (sender, requests) = mpsc::channel();
+        let listener = self.listener;
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let _ = stream.set_read_timeout(Some(TIMEOUT));
+                let _ = stream.set_write_timeout(Some(TIMEOUT));
+                let mut bytes = Vec::new();
+                if stream.read_to_end(&mut bytes).is_err() {
+                    continue;
+                }
+                let Some(request) = OpenRequest::decode(&bytes) else { continue };
+                if sender.send(request).is_err() {
+                    break;
+                }
+                let _ = stream.write_all(ACK);
+                wake();
+            }
+        });
+        Inbox { requests, path: self.path }
+    }
+}
+
+/// Requests from later launches; removes the socket when dropped.
+pub struct Inbox {
+    requests: Receiver<OpenRequest>,
+    path: PathBuf,
+}
+
+impl Inbox {
+    /// The requests that arrived since the last call; call once per frame.
+    pub fn poll(&self) -> Vec<OpenRequest> {
+        self.requests.try_iter().collect()
+    }
+}
+
+impl Drop for Inbox {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use std::time::Instant;
+
+    fn socket(test: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("datara-{}-{}.sock", test, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn request(paths: &[&Path]) -> OpenRequest {
+        OpenRequest { cwd: PathBuf::from("/home/ada"), paths: paths.iter().map(PathBuf::from).collect(), select: None }
+    }
+
+    #[test]
+    fn requests_survive_encoding() {
+        let odd = PathBuf::from(OsStr::from_bytes(b"/srv/caf\xe9 new\nline"));
+        let mut sent = request(&[Path::new("/tmp"), &odd]);
+        assert_eq!(OpenRequest::decode(&sent.encode()), Some(sent.clone()));
+        sent.select = Some(PathBuf::from("/tmp/a.txt"));
+        assert_eq!(OpenRequest::decode(&sent.encode()), Some(sent.clone()));
+        assert_eq!(OpenRequest::decode(&request(&[]).encode()), Some(request(&[])));
+
+        assert_eq!(OpenRequest::decode(b"open\0/home\0\0relative\0"), None);
+        assert_eq!(OpenRequest::decode(b"close\0/home\0\0"), None);
+        assert_eq!(OpenRequest::decode(b"open\0/home\0\0/cut"), None);
+    }
+
+    #[test]
+    fn second_launch_hands_its_paths_over() {
+        let path = socket("handoff");
+        let Claim::Primary(server) = claim(&path, &request(&[])).unwrap() else { panic!("nothing should be running yet") };
+        let inbox = server.serve(|| {});
+
+        let sent = request(&[Path::new("/srv/assets")]);
+        assert!(matches!(claim(&path, &sent).unwrap(), Claim::Forwarded));
+        let deadline = Instant::now() + TIMEOUT;
+        let mut received = Vec::new();
+        while received.is_empty() && Instant::now() < deadline {
+            received = inbox.poll();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(received, vec![sent]);
+
+        drop(inbox);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn stale_sockets_are_taken_over() {
+        let path = socket("stale");
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+        let Claim::Primary(server) = claim(&path, &request(&[])).unwrap() else { panic!("the old socket has no listener") };
+        drop(server.serve(|| {}));
+    }
+}
@@ -0,0 +1,130 @@
+use eframe::egui;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crate::notifications::{Notification, Severity};
+use crate::paths;
+use crate::vfs::Progress;
+
+/// Progress of a running job, shared with its thread.
+pub struct JobProgress {
+    done: AtomicU64,
+    total: AtomicU64,
+    /// File being worked on.
+    current: Mutex<PathBuf>,
+    cancelled: AtomicBool,
+    ctx: egui::Context,
+}
+
+impl JobProgress {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl Progress for JobProgress {
+    fn start(&self, total_bytes: u64) {
+        self.total.store(total_bytes, Ordering::Relaxed);
+    }
+
+    fn advance(&self, name: &Path, bytes: u64) -> io::Result<()> {
+        if self.is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+        self.done.fetch_add(bytes, Ordering::Relaxed);
+        if let Ok(mut current) = self.current.lock()
+            && *current != name {
+            *current = name.to_path_buf();
+        }
+        self.ctx.request_repaint();
+        Ok(())
+    }
+}
+
+struct Job {
+    title: String,
+    progress: Arc<JobProgress>,
+    handle: JoinHandle<Notification>,
+}
+
+/// Long file operations running on their own threads, each reporting a
+/// notification when it ends.
+#[derive(Default)]
+pub struct Jobs {
+    running: Vec<Job>,
+}
+
+impl Jobs {
+    /// Runs `work` on a new thread. It should stop early, with any result,
+    /// once `JobProgress::is_cancelled` turns true.
+    pub fn spawn(&mut self, ctx: &egui::Context, title: String, work: impl FnOnce(&JobProgress) -> Notification + Send + 'static) {
+        let progress = Arc::new(JobProgress {
+            done: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+            current: Mutex::new(PathBuf::new()),
+            cancelled: AtomicBool::new(false),
+            ctx: ctx.clone(),
+        });
+        let thread_progress = progress.clone();
+        let handle = std::thread::spawn(move || {
+            let notification = work(&thread_progress);
+            thread_progress.ctx.request_repaint();
+            notification
+        });
+        self.running.push(Job { title, progress, handle });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.running.is_empty()
+    }
+
+    /// Collects the notifications of finished jobs; call once per frame.
+    pub fn poll(&mut self) -> Vec<Notification> {
+        let mut finished = Vec::new();
+        let mut i = 0;
+        while i < self.running.len() {
+            if !self.running[i].handle.is_finished() {
+                i += 1;
+                continue;
+            }
+            let job = self.running.remove(i);
+            finished.push(match job.handle.join() {
+                Ok(notification) => notification,
+                Err(_) => Notification::new(Severity::Error, format!("{} crashed", job.title)),
+            });
+        }
+        finished
+    }
+
+    /// Bottom panel with a progress bar and Cancel button per job.
+    pub fn show(&mut self, ctx: &egui::Context, bg: egui::Color32) {
+        if self.running.is_empty() {
+            return;
+        }
+        egui::TopBottomPanel::bottom("jobs")
+            .frame(egui::Frame::default().fill(bg).inner_margin(6.0))
+            .show(ctx, |ui| {
+                for job in &self.running {
+                    ui.horizontal(|ui| {
+                        let progress = &job.progress;
+                        let (done, total) = (progress.done.load(Ordering::Relaxed), progress.total.load(Ordering::Relaxed));
+                        ui.label(egui::RichText::new(&job.title).monospace());
+                        let mut bar = egui::ProgressBar::new(if total > 0 { done as f32 / total as f32 } else { 0.0 }).desired_width(200.0);
+                        bar = if total > 0 { bar.show_percentage() } else { bar.animate(true) };
+                        ui.add(bar);
+                        if progress.is_cancelled() {
+                            ui.label("Cancelling…");
+                        } else if ui.button("Cancel").clicked() {
+                            progress.cancelled.store(true, Ordering::Relaxed);
+                        }
+                        if let Ok(current) = progress.current.lock() {
+                            ui.label(egui::RichText::new(paths::display_path(&current)).small());
+                        }
+                    });
+                }
+            });
+    }
+}
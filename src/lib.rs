@@ -0,0 +1,31 @@
+//! Datara's file-browsing core and the egui widgets built on it. The `datara`
+//! binary is the window that ties them together.
+
+pub mod archive;
+pub mod archive_ops;
+pub mod browser;
+pub mod cli;
+pub mod config;
+pub mod config_store;
+pub mod control;
+pub mod dav;
+pub mod desktop_entries;
+pub mod file_browser;
+pub mod file_kind;
+pub mod file_type;
+pub mod hex_view;
+pub mod image_viewer;
+pub mod instance;
+pub mod jobs;
+pub mod markdown;
+pub mod notifications;
+pub mod openers;
+pub mod paths;
+pub mod pick;
+pub mod portal;
+pub mod preview;
+pub mod processes;
+pub mod remote;
+pub mod sftp;
+pub mod thumbnails;
+pub mod vfs;
@@ -1,338 +1,1629 @@
 use eframe::egui;
-use std::path::PathBuf;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
 use std::time::SystemTime;
-use std::process::Command;
 
-struct DataraApp {
+use serde_json::{Value, json};
+
+use datara::{cli, config, file_type, openers, paths, pick, portal};
+use datara::archive::{self, ArchiveMounts};
+use datara::archive_ops::{self, CompressFormat, Conflict};
+use datara::browser::Entry;
+use datara::cli::{LaunchOptions, Overrides};
+use datara::config::Config;
+use datara::config_store::{ConfigEvent, ConfigStore};
+use datara::control::{Call, Command, Control, Event, Reply, RpcError};
+use datara::desktop_entries::AppRegistry;
+use datara::file_browser::{self, BrowserEvent, FileBrowser, SelectionMode};
+use datara::file_type::FileType;
+use datara::hex_view::HexView;
+use datara::image_viewer::ImageViewer;
+use datara::instance::{self, Claim, Inbox, OpenRequest};
+use datara::jobs::Jobs;
+use datara::markdown::MarkdownView;
+use datara::notifications::{Notification, NotificationAction, Notifications, Severity};
+use datara::openers::{Builtin, MatchKind, OpenAction, OpenerRule};
+use datara::pick::{PickOutcome, PickState, PickTarget};
+use datara::preview::{Preview, PreviewContent, PreviewLoader};
+use datara::processes::{LaunchFailure, LaunchKind, ProcessSupervisor};
+use datara::remote::{Location, RemoteUrl, Remotes};
+use datara::vfs::{self, LocalFs, Vfs};
+
+/// Deferred result of an entry's context menu.
+enum EntryAction {
+    Open(PathBuf, bool),
+    OpenWith(PathBuf, String),
+    SetDefault(String, String),
+    OpenInNewTab(PathBuf),
+    /// Copy an archive member out, next to the archive.
+    Extract(PathBuf),
+    Compress(PathBuf),
+    ExtractHere(PathBuf),
+    ExtractToFolder(PathBuf),
+    /// Copy a remote file or folder into the downloads folder.
+    Download(PathBuf),
+    Rename(PathBuf),
+    Delete(PathBuf),
+}
+
+/// What to do with a file once a job has copied it to the local disk.
+enum AfterFetch {
+    Open,
+    /// Open with this application, or `xdg-open` when empty.
+    OpenWith(String),
+}
+
+/// A connection being opened on a worker thread.
+struct Connecting {
+    url: RemoteUrl,
+    result: Receiver<std::io::Result<Arc<dyn Vfs>>>,
+}
+
+/// The filesystem the listed entries are on, which decides what their
+/// context menu offers.
+#[derive(Clone, Copy)]
+enum Place<'a> {
+    Local,
+    /// Inside the archive at this path.
+    Archive(&'a Path),
+    Remote,
+}
+
+impl<'a> Place<'a> {
+    fn of(remote: bool, archive_root: Option<&'a Path>) -> Self {
+        match archive_root {
+            _ if remote => Place::Remote,
+            Some(root) => Place::Archive(root),
+            None => Place::Local,
+        }
+    }
+}
+
+/// Settings of the "Compress" window.
+struct CompressDialog {
+    sources: Vec<PathBuf>,
+    /// Archive name without the extension.
+    name: String,
+    /// The suggested name byte for byte; used while `name` still shows it,
+    /// so a name that isn't UTF-8 isn't replaced by its `\xNN` display form.
+    suggested: OsString,
+    format: CompressFormat,
+    level: u32,
+}
+
+/// The "Extract here" window, which asks what to do about existing files.
+struct ExtractDialog {
+    archive: PathBuf,
+    conflict: Conflict,
+}
+
+/// Navigation state of a tab. The active tab's state lives in `DataraApp`
+/// itself and is copied back here when switching away.
+struct Tab {
+    vfs: Arc<dyn Vfs>,
+    /// Server of a remote tab; `vfs` is the connection to it.
+    remote: Option<RemoteUrl>,
     current_dir: PathBuf,
-    entries: Vec<std::fs::DirEntry>,
     history: Vec<PathBuf>,
     future: Vec<PathBuf>,
-    grid_view: bool,
-    error: Option<String>,
+    selected_path: Option<PathBuf>,
+}
+
+impl Tab {
+    fn new(vfs: Arc<dyn Vfs>, remote: Option<RemoteUrl>, current_dir: PathBuf, selected_path: Option<PathBuf>) -> Self {
+        Self { vfs, remote, current_dir, history: Vec::new(), future: Vec::new(), selected_path }
+    }
+
+    fn title(&self) -> String {
+        tab_title(self.remote.as_ref(), &self.current_dir)
+    }
+}
+
+/// The folder name, or the host at the root of a server.
+fn tab_title(remote: Option<&RemoteUrl>, dir: &Path) -> String {
+    match remote {
+        Some(url) if dir.parent().is_none() => url.host.clone(),
+        _ => paths::display_file_name(dir),
+    }
+}
+
+struct DataraApp {
+    files: FileBrowser,
+    /// The local filesystem with archives enterable as folders; `files.browser.vfs`.
+    archives: Arc<ArchiveMounts>,
+    /// The archive the current folder is in, if any.
+    archive_root: Option<PathBuf>,
+    /// Server of the active tab, with the path `/`; `files.browser.vfs` is then
+    /// the connection to it instead of `archives`.
+    remote: Option<RemoteUrl>,
+    remotes: Arc<Remotes>,
+    connecting: Option<Connecting>,
+    /// A `--control` navigation, answered once the folder is listed, and the
+    /// notification mark to report errors from.
+    navigate_reply: Option<(Reply, u64)>,
+    /// Files fetched by jobs for opening, sent back to the UI thread.
+    fetched: Receiver<(PathBuf, AfterFetch)>,
+    fetch_sender: Sender<(PathBuf, AfterFetch)>,
+    /// Text of the address bar while it is being edited.
+    location_edit: Option<String>,
+    bookmarks: Vec<String>,
     ui_scale: f32,
-    max_items_per_row: i32,
     show_scanlines: bool,
-    show_hidden: bool,
-    folder_icon: Option<egui::TextureHandle>,
-    file_icon: Option<egui::TextureHandle>,
-    last_hovered_item: Option<usize>,
-    scrolling_text: Option<(usize, f32)>, // (item_index, scroll_offset)
-    horizontal_spacing: f32,
-    vertical_spacing: f32,
     show_settings: bool,
+    show_preview: bool,
+    preview: Option<Preview>,
+    preview_loader: Option<PreviewLoader>,
+    image_viewer: Option<ImageViewer>,
+    hex_view: Option<HexView>,
+    /// The window is to close once the hex editor's unsaved edits are dealt with.
+    quit_after_hex_view: bool,
+    markdown_view: Option<MarkdownView>,
+    opener_rules: Vec<OpenerRule>,
+    terminal_command: String,
+    app_registry: Option<AppRegistry>,
+    processes: ProcessSupervisor,
+    notifications: Notifications,
+    /// Local file whose "choose another application" dialog is open, and
+    /// its type, detected once when the dialog opened.
+    choose_app_for: Option<(PathBuf, &'static str)>,
+    jobs: Jobs,
+    compress_dialog: Option<CompressDialog>,
+    extract_dialog: Option<ExtractDialog>,
+    /// Entry being renamed, and the new name typed so far.
+    rename_dialog: Option<(PathBuf, String)>,
+    /// Entry waiting for the delete to be confirmed.
+    delete_confirm: Option<PathBuf>,
+    config_store: ConfigStore,
+    /// Settings as read from the config file, before command-line overrides.
+    file_config: Config,
+    overrides: Overrides,
+    tabs: Vec<Tab>,
+    active_tab: usize,
+    /// Set in `--pick` mode.
+    pick: Option<PickState>,
+    /// Launches forwarded by later invocations, when this is the single instance.
+    instance: Option<Inbox>,
+    /// Set with `--control`.
+    control: Option<Control>,
+    /// Tab and location last sent to control subscribers.
+    reported_location: (usize, String),
+    reported_selection: Option<PathBuf>,
 }
 
 impl DataraApp {
-    fn new(start_dir: PathBuf) -> Self {
+    fn new(options: LaunchOptions, pick_outcome: PickOutcome) -> Self {
+        let start_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+        let archives = Arc::new(ArchiveMounts::new(Arc::new(LocalFs)));
+        let mut files = FileBrowser::with_vfs(archives.clone(), start_dir.clone()).with_open_on_click(true).with_background_listing(true);
+        if let Some(pick) = &options.pick {
+            let (shows, accepts) = (pick.clone(), pick.clone());
+            files = files
+                .with_open_on_click(false)
+                .with_selection_mode(if pick.multiple { SelectionMode::Multiple } else { SelectionMode::Single })
+                .with_filter(move |entry| shows.shows(&entry.path, entry.is_dir))
+                .with_selectable(move |entry| accepts.accepts(&entry.path, entry.is_dir));
+        }
+        let (fetch_sender, fetched) = mpsc::channel();
         let mut app = Self {
-            current_dir: start_dir,
-            entries: Vec::new(),
-            history: Vec::new(),
-            future: Vec::new(),
-            grid_view: true,
-            error: None,
+            files,
+            archives,
+            archive_root: None,
+            remote: None,
+            remotes: Arc::default(),
+            connecting: None,
+            navigate_reply: None,
+            fetched,
+            fetch_sender,
+            location_edit: None,
+            bookmarks: Vec::new(),
             ui_scale: 1.0,
-            max_items_per_row: 3,
             show_scanlines: false,
-            show_hidden: false,
-            folder_icon: None,
-            file_icon: None,
-            last_hovered_item: None,
-            scrolling_text: None,
-            horizontal_spacing: 16.0,
-            vertical_spacing: 12.0,
             show_settings: false,
+            show_preview: false,
+            preview: None,
+            preview_loader: None,
+            image_viewer: None,
+            hex_view: None,
+            quit_after_hex_view: false,
+            markdown_view: None,
+            opener_rules: openers::default_rules(),
+            terminal_command: config::DEFAULT_TERMINAL_COMMAND.to_string(),
+            app_registry: None,
+            processes: ProcessSupervisor::default(),
+            notifications: Notifications::default(),
+            choose_app_for: None,
+            jobs: Jobs::default(),
+            compress_dialog: None,
+            extract_dialog: None,
+            rename_dialog: None,
+            delete_confirm: None,
+            config_store: ConfigStore::new(options.config_path.or_else(config::default_path)),
+            file_config: Config::default(),
+            overrides: options.overrides.clone(),
+            tabs: Vec::new(),
+            active_tab: 0,
+            pick: options.pick.map(|pick| PickState::new(pick, pick_outcome)),
+            instance: None,
+            control: None,
+            reported_location: (0, String::new()),
+            reported_selection: None,
         };
-        app.read_dir();
         app.load_settings();
+        if let Some(grid_view) = options.overrides.grid_view {
+            app.files.grid_view = grid_view;
+        }
+        if let Some(show_hidden) = options.overrides.show_hidden {
+            app.files.browser.show_hidden = show_hidden;
+        }
+        if let Some(ui_scale) = options.overrides.ui_scale {
+            app.ui_scale = ui_scale;
+        }
+
+        app.push_launch_tabs(&options.paths, options.select, start_dir);
+        app.load_tab(0);
+        if let Some(pick) = &mut app.pick {
+            match app.files.browser.selected_path.clone() {
+                Some(selected) if pick.options.accepts(&selected, selected.is_dir()) => pick.picked(&selected),
+                _ => app.files.select(None),
+            }
+        }
         app
     }
 
-    fn read_dir(&mut self) {
-        self.entries.clear();
-        self.error = None;
-        match std::fs::read_dir(&self.current_dir) {
-            Ok(read_dir) => {
-                for entry in read_dir.flatten() {
-                    if !self.show_hidden {
-                        let name = entry.file_name();
-                        if let Some(s) = name.to_str() {
-                            if s.starts_with('.') { continue; }
+    /// Adds a tab for each launch path, or one for `fallback_dir` when none
+    /// could be opened, selecting `select` in the tab showing its folder.
+    fn push_launch_tabs(&mut self, launch_paths: &[PathBuf], select: Option<PathBuf>, fallback_dir: PathBuf) {
+        let first = self.tabs.len();
+        let select = select.map(|file| std::path::absolute(&file).unwrap_or(file));
+        for path in launch_paths {
+            let path = std::path::absolute(path).unwrap_or_else(|_| path.clone());
+            match std::fs::metadata(&path) {
+                Ok(meta) if meta.is_dir() => self.tabs.push(Tab::new(self.archives.clone(), None, path, None)),
+                Ok(_) => {
+                    let dir = path.parent().map(PathBuf::from).unwrap_or_else(|| fallback_dir.clone());
+                    self.tabs.push(Tab::new(self.archives.clone(), None, dir, Some(path)));
+                }
+                Err(err) => self.notifications.push(Notification::new(Severity::Error, format!("Cannot open {}: {}", paths::display_path(&path), err)).with_path(&path)),
+            }
+        }
+        if self.tabs.len() == first {
+            let dir = select.as_ref().and_then(|file| file.parent()).map(PathBuf::from).unwrap_or(fallback_dir);
+            self.tabs.push(Tab::new(self.archives.clone(), None, dir, None));
+        }
+        if let Some(file) = select {
+            let tab = self.tabs[first..].iter().position(|tab| file.parent() == Some(tab.current_dir.as_path())).unwrap_or(0);
+            self.tabs[first + tab].selected_path = Some(file);
+        }
+    }
+
+    /// Opens what a later launch handed over and brings the window forward.
+    fn open_forwarded(&mut self, ctx: &egui::Context, request: OpenRequest) {
+        self.store_tab();
+        let first = self.tabs.len();
+        self.push_launch_tabs(&request.paths, request.select, request.cwd);
+        self.load_tab(first);
+        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+    }
+
+    /// Whether `path` is the current folder or one of its entries.
+    fn is_shown(&self, path: &Path) -> bool {
+        path == self.files.browser.current_dir || path.parent() == Some(self.files.browser.current_dir.as_path())
+    }
+
+    /// Fails with the first error notification pushed since `mark`, so a
+    /// control command reports what the window would have shown.
+    fn reported_errors(&self, mark: u64) -> Result<(), RpcError> {
+        match self.notifications.errors_since(mark).next() {
+            Some(error) => Err(RpcError::failed(error.message.clone())),
+            None => Ok(()),
+        }
+    }
+
+    fn view_settings(&self) -> Value {
+        json!({ "grid": self.files.grid_view, "show_hidden": self.files.browser.show_hidden, "scale": self.ui_scale })
+    }
+
+    /// Carries out a `--control` command. Commands that change files run as
+    /// jobs and answer when they end.
+    fn run_command(&mut self, ctx: &egui::Context, call: Call) {
+        let Call { command, reply } = call;
+        let outcome = match command {
+            Command::Navigate { location } => {
+                let mark = self.notifications.mark();
+                self.go_to_location(ctx, &location);
+                if self.connecting.is_some() || self.files.is_loading() {
+                    self.navigate_reply = Some((reply, mark));
+                    return;
+                }
+                self.reported_errors(mark).map(|()| json!({ "location": self.location() }))
+            }
+            Command::CurrentDir {} => Ok(json!({
+                "location": self.location(),
+                "path": paths::display_path(&self.files.browser.current_dir),
+                "tab": self.active_tab,
+                "selected": self.files.browser.selected_path.as_deref().map(paths::display_path),
+            })),
+            Command::List {} => {
+                let entries: Vec<Value> = self.files.browser.entries.iter().map(|entry| json!({
+                    "name": entry.display_name(),
+                    "path": paths::display_path(&entry.path),
+                    "is_dir": entry.is_dir,
+                    "size": entry.len,
+                    "modified": entry.modified.and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok()).map(|age| age.as_secs()),
+                })).collect();
+                Ok(json!({ "location": self.location(), "entries": entries }))
+            }
+            Command::Select { path: None } => {
+                self.files.select(None);
+                Ok(Value::Null)
+            }
+            Command::Select { path: Some(path) } => {
+                if self.files.browser.entries.iter().any(|entry| entry.path == path) {
+                    self.files.select(Some(path));
+                    Ok(Value::Null)
+                } else {
+                    Err(RpcError::failed(format!("{} is not in the current folder", paths::display_path(&path))))
+                }
+            }
+            Command::Open { path } => match self.files.browser.vfs.stat(&path) {
+                Ok(meta) => {
+                    let mark = self.notifications.mark();
+                    self.apply_entry_action(ctx, EntryAction::Open(path, meta.is_dir));
+                    self.reported_errors(mark).map(|()| Value::Null)
+                }
+                Err(err) => Err(err.into()),
+            },
+            Command::Mkdir { path } => {
+                self.start_mkdir(ctx, path, Some(reply));
+                return;
+            }
+            Command::Rename { path, to } => {
+                self.start_rename(ctx, path, to, Some(reply));
+                return;
+            }
+            Command::Delete { path } => {
+                self.start_delete(ctx, path, Some(reply));
+                return;
+            }
+            Command::Copy { path, to } => {
+                self.start_copy(ctx, path, to, Some(reply));
+                return;
+            }
+            Command::SetView { scale: Some(scale), .. } if !(0.5..=3.0).contains(&scale) => Err(RpcError::invalid_params("scale must be from 0.5 to 3.0")),
+            Command::SetView { grid, show_hidden, scale } => {
+                self.files.grid_view = grid.unwrap_or(self.files.grid_view);
+                self.ui_scale = scale.unwrap_or(self.ui_scale);
+                if let Some(show_hidden) = show_hidden
+                    && show_hidden != self.files.browser.show_hidden {
+                    self.files.browser.show_hidden = show_hidden;
+                    self.read_dir();
+                }
+                Ok(self.view_settings())
+            }
+        };
+        reply.send(outcome);
+    }
+
+    /// Tells control subscribers about navigation and selection changes,
+    /// whatever caused them.
+    fn report_events(&mut self) {
+        let Some(control) = &self.control else { return };
+        let location = (self.active_tab, self.location());
+        if location != self.reported_location {
+            control.notify(&Event::Navigated { location: location.1.clone(), tab: location.0 });
+            self.reported_location = location;
+        }
+        if self.files.browser.selected_path != self.reported_selection {
+            control.notify(&Event::Selected { path: self.files.browser.selected_path.as_deref().map(paths::display_path) });
+            self.reported_selection = self.files.browser.selected_path.clone();
+        }
+    }
+
+    /// Confirms the picked entries; with nothing picked, a folder pick takes the current folder.
+    fn pick_confirm(&mut self, ctx: &egui::Context) {
+        let Some(pick) = &mut self.pick else { return };
+        let mut paths = self.files.selection().to_vec();
+        if pick.options.save_name.is_some() {
+            let name = pick.name.trim();
+            if !paths::is_plain_name(name) {
+                return;
+            }
+            paths = vec![self.files.browser.current_dir.join(name)];
+        } else if paths.is_empty() && pick.options.target != PickTarget::Files {
+            paths.push(self.files.browser.current_dir.clone());
+        }
+        if paths.is_empty() {
+            return;
+        }
+        pick.confirm(paths);
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+
+    /// Bottom bar of `--pick` mode with the selection and Cancel/Select.
+    fn pick_bar(&mut self, ctx: &egui::Context, bg: egui::Color32) {
+        let Some(pick) = &mut self.pick else { return };
+        let mut confirm = false;
+        let mut cancel = false;
+        egui::TopBottomPanel::bottom("pick_bar")
+            .frame(egui::Frame::default().fill(bg).inner_margin(8.0))
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(pick.options.describe()).strong());
+                    ui.separator();
+                    if pick.options.save_name.is_some() {
+                        let response = ui.add(egui::TextEdit::singleline(&mut pick.name).desired_width(280.0));
+                        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            confirm = true;
+                        }
+                        if self.files.browser.current_dir.join(pick.name.trim()).is_file() {
+                            ui.colored_label(egui::Color32::from_rgb(255, 200, 0), "⚠ Replaces the existing file");
                         }
                     }
-                    self.entries.push(entry);
-                }
-                self.entries.sort_by(|a, b| {
-                    let a_meta = a.metadata();
-                    let b_meta = b.metadata();
-                    let a_is_dir = a_meta.as_ref().map(|m| m.is_dir()).unwrap_or(false);
-                    let b_is_dir = b_meta.as_ref().map(|m| m.is_dir()).unwrap_or(false);
-                    match (a_is_dir, b_is_dir) {
-                        (true, false) => std::cmp::Ordering::Less,
-                        (false, true) => std::cmp::Ordering::Greater,
-                        _ => a.file_name().to_string_lossy().to_lowercase().cmp(&b.file_name().to_string_lossy().to_lowercase()),
-                    }
+                    let summary = match self.files.selection() {
+                        _ if pick.options.save_name.is_some() => format!("in {}", paths::display_path(&self.files.browser.current_dir)),
+                        [] if pick.options.target != PickTarget::Files => format!("{} (current folder)", paths::display_file_name(&self.files.browser.current_dir)),
+                        [] => "Nothing selected".to_string(),
+                        [one] => paths::display_file_name(one),
+                        many => format!("{} selected", many.len()),
+                    };
+                    ui.label(egui::RichText::new(summary).monospace());
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let (can_confirm, label) = match pick.options.save_name {
+                            Some(_) => (paths::is_plain_name(pick.name.trim()), "Save"),
+                            None => (!self.files.selection().is_empty() || pick.options.target != PickTarget::Files, "Select"),
+                        };
+                        if ui.add_enabled(can_confirm, egui::Button::new(label)).clicked() {
+                            confirm = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            cancel = true;
+                        }
+                    });
                 });
-            }
-            Err(err) => {
-                self.error = Some(format!("Failed to read dir: {}", err));
-            }
+            });
+        if !ctx.memory(|m| m.focused().is_some()) {
+            ctx.input(|i| {
+                confirm |= i.key_pressed(egui::Key::Enter);
+                cancel |= i.key_pressed(egui::Key::Escape);
+            });
+        }
+        if confirm {
+            self.pick_confirm(ctx);
+        } else if cancel {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
         }
     }
 
-    fn navigate_to(&mut self, path: PathBuf, push_history: bool) {
-        if push_history {
-            self.history.push(self.current_dir.clone());
-            self.future.clear();
+    fn store_tab(&mut self) {
+        if let Some(tab) = self.tabs.get_mut(self.active_tab) {
+            tab.vfs = self.files.browser.vfs.clone();
+            tab.remote = self.remote.clone();
+            tab.current_dir = self.files.browser.current_dir.clone();
+            tab.history = self.files.browser.history.clone();
+            tab.future = self.files.browser.future.clone();
+            tab.selected_path = self.files.browser.selected_path.clone();
         }
-        self.current_dir = path;
+    }
+
+    fn load_tab(&mut self, index: usize) {
+        self.active_tab = index;
+        let tab = &self.tabs[index];
+        self.files.browser.vfs = tab.vfs.clone();
+        self.remote = tab.remote.clone();
+        self.files.browser.current_dir = tab.current_dir.clone();
+        self.files.browser.history = tab.history.clone();
+        self.files.browser.future = tab.future.clone();
+        let selected_path = tab.selected_path.clone();
         self.read_dir();
+        self.files.select(selected_path);
+    }
+
+    fn switch_tab(&mut self, index: usize) {
+        if index != self.active_tab && index < self.tabs.len() {
+            self.store_tab();
+            self.load_tab(index);
+        }
+    }
+
+    fn open_tab(&mut self, dir: PathBuf, selected_path: Option<PathBuf>) {
+        self.store_tab();
+        self.tabs.push(Tab::new(self.files.browser.vfs.clone(), self.remote.clone(), dir, selected_path));
+        self.load_tab(self.tabs.len() - 1);
+    }
+
+    fn close_tab(&mut self, index: usize) {
+        if self.tabs.len() <= 1 || index >= self.tabs.len() {
+            return;
+        }
+        self.store_tab();
+        self.tabs.remove(index);
+        let active = if index < self.active_tab { self.active_tab - 1 } else { self.active_tab.min(self.tabs.len() - 1) };
+        self.load_tab(active);
+    }
+
+    fn read_dir(&mut self) {
+        let result = self.files.read_dir();
+        self.listed(result);
+    }
+
+    /// Resets the per-listing view state after `files` listed a folder.
+    fn listed(&mut self, result: std::io::Result<()>) {
+        self.preview = None;
+        if let Some(loader) = &mut self.preview_loader {
+            loader.cancel();
+        }
+        self.archive_root = if self.remote.is_none() { self.archives.archive_root(&self.files.browser.current_dir) } else { None };
+        if let Err(err) = result {
+            // Anything but a plain refusal may mean the connection dropped;
+            // the next visit then connects again
+            if let Some(url) = &self.remote
+                && !matches!(err.kind(), std::io::ErrorKind::NotFound | std::io::ErrorKind::PermissionDenied) {
+                self.remotes.disconnect(url);
+            }
+            self.notifications.push(Notification::new(Severity::Error, format!("Failed to read dir: {}", err)).with_path(&self.files.browser.current_dir));
+        }
+    }
+
+    fn navigate_to(&mut self, path: PathBuf, push_history: bool) {
+        let result = self.files.navigate_to(path, push_history);
+        self.listed(result);
     }
 
     fn navigate_up(&mut self) {
-        if let Some(parent) = self.current_dir.parent() {
-            self.navigate_to(parent.to_path_buf(), true);
+        if self.files.can_go_up() {
+            let result = self.files.navigate_up();
+            self.listed(result);
         }
     }
 
     fn navigate_back(&mut self) {
-        if let Some(prev) = self.history.pop() {
-            self.future.push(self.current_dir.clone());
-            self.current_dir = prev;
-            self.read_dir();
+        if self.files.browser.can_go_back() {
+            let result = self.files.navigate_back();
+            self.listed(result);
         }
     }
 
     fn navigate_forward(&mut self) {
-        if let Some(next) = self.future.pop() {
-            self.history.push(self.current_dir.clone());
-            self.current_dir = next;
-            self.read_dir();
+        if self.files.browser.can_go_forward() {
+            let result = self.files.navigate_forward();
+            self.listed(result);
         }
     }
 
-    fn entry_label(entry: &std::fs::DirEntry) -> String {
-        let name = entry.file_name().to_string_lossy().to_string();
-        match entry.metadata() {
-            Ok(meta) if meta.is_dir() => format!("📁 {}", name),
-            Ok(_) => format!("📄 {}", name),
-            Err(_) => name,
+    /// The current folder as the address bar shows it.
+    fn location(&self) -> String {
+        match &self.remote {
+            Some(url) => url.with_path(&self.files.browser.current_dir).to_string(),
+            None => paths::display_path(&self.files.browser.current_dir),
         }
     }
 
-    fn entry_name(entry: &std::fs::DirEntry) -> String {
-        entry.file_name().to_string_lossy().to_string()
+    /// Goes to a path or URL typed into the address bar or kept as a
+    /// bookmark. A new server is connected to on a worker thread; the tab
+    /// switches to it once it answers.
+    fn go_to_location(&mut self, ctx: &egui::Context, text: &str) {
+        let location = match Location::parse(text) {
+            Ok(location) => location,
+            Err(err) => {
+                self.notifications.error(err);
+                return;
+            }
+        };
+        // Whatever was typed last wins over a connection still being opened
+        self.connecting = None;
+        match location {
+            Location::Local(path) if self.remote.is_none() => self.navigate_to(path, true),
+            Location::Local(path) => {
+                self.remote = None;
+                let result = self.files.switch_vfs(self.archives.clone(), path);
+                self.listed(result);
+            }
+            Location::Remote(_) if self.pick.is_some() => self.notifications.error("Only local files can be picked"),
+            Location::Remote(url) => {
+                let root = url.with_path(Path::new("/"));
+                if self.remote.as_ref() == Some(&root) {
+                    self.navigate_to(url.path, true);
+                    return;
+                }
+                let (sender, result) = mpsc::channel();
+                let (remotes, ctx, target) = (self.remotes.clone(), ctx.clone(), url.clone());
+                std::thread::spawn(move || {
+                    // Fails when another location was chosen meanwhile
+                    if sender.send(remotes.connect(&target)).is_ok() {
+                        ctx.request_repaint();
+                    }
+                });
+                self.connecting = Some(Connecting { url, result });
+            }
+        }
     }
 
-    fn entry_info(entry: &std::fs::DirEntry) -> (bool, Option<u64>, Option<SystemTime>) {
-        match entry.metadata() {
-            Ok(meta) => {
-                let is_dir = meta.is_dir();
-                let len = if is_dir { None } else { Some(meta.len()) };
-                let modified = meta.modified().ok();
-                (is_dir, len, modified)
+    /// Switches to the server once the connection opened by `go_to_location`
+    /// is ready.
+    fn poll_connecting(&mut self) {
+        let Some(connecting) = &self.connecting else { return };
+        let result = match connecting.result.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err(std::io::Error::other("the connection attempt failed")),
+        };
+        let Some(Connecting { url, .. }) = self.connecting.take() else { return };
+        match result {
+            Ok(vfs) => {
+                self.remote = Some(url.with_path(Path::new("/")));
+                let result = self.files.switch_vfs(vfs, url.path);
+                self.listed(result);
             }
-            Err(_) => (false, None, None),
+            Err(err) => self.notifications.error(format!("Cannot connect to {}: {}", url.root(), err)),
         }
     }
 
-    fn format_size(bytes: u64) -> String {
-        const KB: f64 = 1024.0;
-        const MB: f64 = KB * 1024.0;
-        const GB: f64 = MB * 1024.0;
-        let b = bytes as f64;
-        if b >= GB { format!("{} GB", (b / GB) as u64) }
-        else if b >= MB { format!("{} MB", (b / MB) as u64) }
-        else if b >= KB { format!("{} KB", (b / KB) as u64) }
-        else { format!("{} B", bytes) }
+    /// Answers a `--control` navigation once connecting and listing are done.
+    fn answer_navigate(&mut self) {
+        if self.connecting.is_some() || self.files.is_loading() {
+            return;
+        }
+        if let Some((reply, mark)) = self.navigate_reply.take() {
+            reply.send(self.reported_errors(mark).map(|()| json!({ "location": self.location() })));
+        }
     }
 
-    fn format_date(time: SystemTime) -> String {
-        // Fallback to RFC3339-like; egui has no tz; keep simple
-        let datetime: chrono::DateTime<chrono::Local> = time.into();
-        datetime.format("%b %d, %Y %H:%M").to_string()
+    /// Breadcrumbs, or a field to type a path or URL into after clicking ✎
+    /// or pressing Ctrl+L. Enter goes there; Escape or clicking away cancels.
+    fn address_bar(&mut self, ui: &mut egui::Ui) {
+        if ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::L)) {
+            self.location_edit = Some(self.location());
+        }
+        let Some(text) = &mut self.location_edit else {
+            if ui.small_button("✎").on_hover_text("Type a path or an sftp://, dav:// or davs:// URL (Ctrl+L)").clicked() {
+                self.location_edit = Some(self.location());
+            }
+            if let Some(dir) = self.breadcrumbs(ui) {
+                self.navigate_to(dir, true);
+            }
+            return;
+        };
+        let response = ui.add(egui::TextEdit::singleline(text).desired_width(420.0 * self.ui_scale).font(egui::TextStyle::Monospace));
+        if response.lost_focus() {
+            let go = ui.input(|i| i.key_pressed(egui::Key::Enter));
+            let text = self.location_edit.take().unwrap_or_default();
+            if go {
+                self.go_to_location(ui.ctx(), &text);
+            }
+        } else if !response.has_focus() {
+            response.request_focus();
+        }
     }
 
-    fn load_icons(&mut self, ctx: &egui::Context) {
-        if self.folder_icon.is_none() {
-            // Load folder icon
-            if let Ok(image_data) = std::fs::read("src/icons/Folder/icons8-folder-48.png") {
-                if let Ok(image) = image::load_from_memory(&image_data) {
-                    let rgba_image = image.to_rgba8();
-                    let size = [rgba_image.width() as usize, rgba_image.height() as usize];
-                    let pixels = rgba_image.into_raw();
-                    let color_image = egui::ColorImage::from_rgba_unmultiplied(size, &pixels);
-                    self.folder_icon = Some(ctx.load_texture("folder_icon", color_image, egui::TextureOptions::default()));
-                }
+    /// The ☆ menu: bookmark the current folder or go to a bookmark.
+    fn bookmarks_menu(&mut self, ui: &mut egui::Ui) {
+        let location = self.location();
+        let mut add = false;
+        let mut remove = None;
+        let mut go_to = None;
+        ui.menu_button("☆", |ui| {
+            if ui.add_enabled(!self.bookmarks.contains(&location), egui::Button::new("Bookmark this folder")).clicked() {
+                add = true;
+                ui.close_menu();
+            }
+            if !self.bookmarks.is_empty() {
+                ui.separator();
+            }
+            for (i, bookmark) in self.bookmarks.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.button(egui::RichText::new(bookmark).monospace()).clicked() {
+                        go_to = Some(bookmark.clone());
+                        ui.close_menu();
+                    }
+                    if ui.small_button("✖").on_hover_text("Remove bookmark").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+        });
+        if add {
+            // The escaped display form wouldn't lead back to the folder
+            if self.remote.is_none() && self.files.browser.current_dir.to_str().is_none() {
+                self.notifications.error("Folders whose path is not valid UTF-8 can't be bookmarked");
+            } else {
+                self.bookmarks.push(location);
             }
         }
-        
-        if self.file_icon.is_none() {
-            // Load file icon
-            if let Ok(image_data) = std::fs::read("src/icons/File/icons8-file-48.png") {
-                if let Ok(image) = image::load_from_memory(&image_data) {
-                    let rgba_image = image.to_rgba8();
-                    let size = [rgba_image.width() as usize, rgba_image.height() as usize];
-                    let pixels = rgba_image.into_raw();
-                    let color_image = egui::ColorImage::from_rgba_unmultiplied(size, &pixels);
-                    self.file_icon = Some(ctx.load_texture("file_icon", color_image, egui::TextureOptions::default()));
+        if let Some(i) = remove {
+            self.bookmarks.remove(i);
+        }
+        if let Some(location) = go_to {
+            self.go_to_location(ui.ctx(), &location);
+        }
+    }
+
+    /// One button per folder of the current path; the archive boundary is
+    /// marked so it's clear where the real filesystem ends.
+    fn breadcrumbs(&self, ui: &mut egui::Ui) -> Option<PathBuf> {
+        let mut clicked = None;
+        let ancestors: Vec<&std::path::Path> = self.files.browser.current_dir.ancestors().collect();
+        ui.scope(|ui| {
+            ui.spacing_mut().item_spacing.x = 2.0;
+            for (i, dir) in ancestors.into_iter().rev().enumerate() {
+                let in_archive = self.archive_root.as_deref().is_some_and(|root| dir.starts_with(root));
+                let is_archive = Some(dir) == self.archive_root.as_deref();
+                if i > 1 {
+                    ui.label(egui::RichText::new(if is_archive { "▸" } else { "/" }).monospace());
+                }
+                let name = match &self.remote {
+                    Some(url) if dir.parent().is_none() => format!("{}/", url.root()),
+                    _ if dir.parent().is_none() => paths::display_path(dir),
+                    _ => paths::display_file_name(dir),
+                };
+                let mut text = egui::RichText::new(if is_archive { format!("[{}]", name) } else { name }).monospace();
+                if in_archive {
+                    text = text.color(egui::Color32::from_rgb(255, 200, 0));
+                }
+                if ui.add(egui::Button::new(text).frame(false)).clicked() && dir != self.files.browser.current_dir {
+                    clicked = Some(dir.to_path_buf());
                 }
             }
-        }
+        });
+        clicked
     }
 
-    fn play_hover_sound(&self) {
+    fn play_hover_sound(processes: &mut ProcessSupervisor) {
         // Use system beep command for hover sound (high frequency)
-        let _ = Command::new("beep")
-            .args(&["-f", "800", "-l", "100"])
-            .spawn();
+        let args = ["-f", "800", "-l", "100"].map(OsString::from);
+        processes.spawn(LaunchKind::Sound, OsStr::new("beep"), &args, None);
     }
 
-    fn play_click_sound(&self) {
+    fn play_click_sound(processes: &mut ProcessSupervisor) {
         // Use system beep command for click sound (lower frequency)
-        let _ = Command::new("beep")
-            .args(&["-f", "600", "-l", "150"])
-            .spawn();
-    }
-
-    fn open_file(&self, path: &std::path::Path) {
-        if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
-            let ext_lower = extension.to_lowercase();
-            
-            match ext_lower.as_str() {
-                // Text files - open in VIM
-                "txt" | "md" | "rs" | "py" | "js" | "html" | "css" | "json" | "xml" | "yml" | "yaml" | "toml" | "ini" | "cfg" | "conf" | "log" | "c" | "cpp" | "h" | "hpp" | "java" | "go" | "php" | "rb" | "sh" | "bash" | "zsh" | "fish" => {
-                    let _ = Command::new("gnome-terminal")
-                        .args(&["--", "vim", path.to_str().unwrap_or("")])
-                        .spawn();
-                },
-                // Videos - open in MPV
-                "mp4" | "avi" | "mkv" | "mov" | "wmv" | "flv" | "webm" | "m4v" | "3gp" | "ogv" | "mpeg" | "mpg" => {
-                    let _ = Command::new("mpv")
-                        .arg(path.to_str().unwrap_or(""))
-                        .spawn();
-                },
-                // Images - open in Firefox
-                "jpg" | "jpeg" | "png" | "gif" | "bmp" | "svg" | "webp" | "tiff" | "ico" => {
-                    let _ = Command::new("firefox")
-                        .arg(path.to_str().unwrap_or(""))
-                        .spawn();
-                },
-                // PDFs - open in Firefox
-                "pdf" => {
-                    let _ = Command::new("firefox")
-                        .arg(path.to_str().unwrap_or(""))
-                        .spawn();
-                },
-                // Audio files - open in MPV
-                "mp3" | "wav" | "flac" | "ogg" | "aac" | "m4a" | "wma" => {
-                    let _ = Command::new("mpv")
-                        .arg(path.to_str().unwrap_or(""))
-                        .spawn();
-                },
-                // Default - try to open with system default
-                _ => {
-                    let _ = Command::new("xdg-open")
-                        .arg(path.to_str().unwrap_or(""))
-                        .spawn();
+        let args = ["-f", "600", "-l", "150"].map(OsString::from);
+        processes.spawn(LaunchKind::Sound, OsStr::new("beep"), &args, None);
+    }
+
+    fn open_file(&mut self, ctx: &egui::Context, path: &std::path::Path) {
+        if self.remote.is_none() && archive::is_archive(path) && self.files.browser.vfs.stat(path).is_ok_and(|meta| meta.is_dir) {
+            self.navigate_to(path.to_path_buf(), true);
+            return;
+        }
+        match self.files.browser.vfs.local_path(path) {
+            Some(local) => self.open_local(ctx, &local),
+            None => self.fetch(ctx, path.to_path_buf(), AfterFetch::Open),
+        }
+    }
+
+    /// Opens a file on the local disk with the viewer or program its rule names.
+    fn open_local(&mut self, ctx: &egui::Context, path: &std::path::Path) {
+        let action = openers::find_rule(&self.opener_rules, path)
+            .and_then(|rule| rule.action(path, &self.terminal_command));
+        match action {
+            Some(OpenAction::Builtin(Builtin::ImageViewer)) => {
+                self.image_viewer = Some(ImageViewer::new(ctx, path));
+            },
+            Some(OpenAction::Builtin(Builtin::HexView)) => match &mut self.hex_view {
+                // Replacing it would lose the edits; ask about them first
+                Some(hex_view) if hex_view.is_dirty() => {
+                    hex_view.request_close();
+                    self.notifications.push(Notification::new(Severity::Warning, "Save or discard the hex editor's changes before opening another file").with_path(path));
                 }
+                _ => self.hex_view = Some(HexView::new(path)),
+            },
+            Some(OpenAction::Builtin(Builtin::Markdown)) => {
+                self.markdown_view = Some(MarkdownView::new(path));
+            },
+            Some(OpenAction::Spawn { program, args }) => {
+                self.processes.spawn(LaunchKind::Opener, &program, &args, Some(path));
+            },
+            // No rule matched - use the system default application
+            Some(OpenAction::Builtin(Builtin::SystemDefault)) | None => {
+                self.open_with_default(path);
+            }
+        }
+    }
+
+    /// Copies a remote file or archive member to the local disk as a job, so
+    /// external programs and the viewers can open it; `then` runs once it's there.
+    fn fetch(&mut self, ctx: &egui::Context, path: PathBuf, then: AfterFetch) {
+        let vfs = self.files.browser.vfs.clone();
+        let fetched = self.fetch_sender.clone();
+        let title = format!("Fetching {}", paths::display_file_name(&path));
+        self.jobs.spawn(ctx, title, move |progress| {
+            let name = paths::display_file_name(&path);
+            match vfs::local_copy(vfs.as_ref(), &path, progress) {
+                Ok(local) => {
+                    let notification = Notification::new(Severity::Success, format!("Opened a read-only copy of {}; changes to it are not written back", name)).with_path(&local);
+                    let _ = fetched.send((local, then));
+                    notification
+                }
+                Err(_) if progress.is_cancelled() => Notification::new(Severity::Warning, format!("Cancelled fetching {}", name)).with_path(&path),
+                Err(err) => Notification::new(Severity::Error, format!("Cannot open {}: {}", paths::display_path(&path), err)).with_path(&path),
+            }
+        });
+    }
+
+    /// Opens the files fetched since the last frame.
+    fn open_fetched(&mut self, ctx: &egui::Context) {
+        while let Ok((local, then)) = self.fetched.try_recv() {
+            match then {
+                AfterFetch::Open => self.open_local(ctx, &local),
+                AfterFetch::OpenWith(app_id) => self.open_with(&local, &app_id),
             }
+        }
+    }
+
+    /// Opens a local file with the application `app_id`, or `xdg-open` when empty.
+    fn open_with(&mut self, path: &std::path::Path, app_id: &str) {
+        if app_id.is_empty() {
+            self.open_with_xdg_open(path);
         } else {
-            // No extension - try system default
-            let _ = Command::new("xdg-open")
-                .arg(path.to_str().unwrap_or(""))
-                .spawn();
+            self.open_with_app(path, app_id);
         }
     }
 
-    fn truncate_text(&self, text: &str, max_width: f32, font_size: f32) -> String {
-        // Simple character-based truncation with proper UTF-8 handling
-        let char_width = font_size * 0.6; // Approximate character width
-        let max_chars = (max_width / char_width) as usize;
-        
-        // Convert to chars for proper UTF-8 handling
-        let chars: Vec<char> = text.chars().collect();
-        
-        if chars.len() <= max_chars {
-            text.to_string()
+    /// Copies an archive member next to the archive as a job.
+    fn extract(&mut self, ctx: &egui::Context, path: PathBuf) {
+        let (Some(root), Some(name)) = (&self.archive_root, path.file_name()) else { return };
+        let target = root.parent().unwrap_or(root).join(name);
+        let vfs = self.files.browser.vfs.clone();
+        let title = format!("Extracting {}", paths::display_file_name(&path));
+        self.jobs.spawn(ctx, title, move |progress| {
+            match vfs::copy_tree(vfs.as_ref(), &path, vfs.as_ref(), &target, progress) {
+                Ok(files) => Notification::new(Severity::Success, format!("Extracted {} file{} to {}", files, if files == 1 { "" } else { "s" }, paths::display_path(&target))).with_path(&target),
+                Err(_) if progress.is_cancelled() => Notification::new(Severity::Warning, format!("Cancelled extracting {}; the partial copy was kept", paths::display_file_name(&path))).with_path(&target),
+                Err(err) => Notification::new(Severity::Error, format!("Failed to extract {}: {}", paths::display_file_name(&path), err)).with_path(&target),
+            }
+        });
+    }
+
+    /// Type of a file about to be launched: reused from the file browser when
+    /// it lists the file, sniffed through its filesystem when the file is in
+    /// the browsed folder, else a fetched copy sniffed on the local disk.
+    fn mime_type_of(&self, path: &std::path::Path) -> &'static str {
+        let vfs = &self.files.browser.vfs;
+        if vfs.local_path(path).is_none() {
+            return file_type::detect(path).mime;
+        }
+        match self.files.detected_type(path) {
+            Some(file_type) => file_type.mime,
+            None => file_type::detect_in(vfs.as_ref(), path).mime,
+        }
+    }
+
+    fn app_registry(&mut self) -> &mut AppRegistry {
+        self.app_registry.get_or_insert_with(AppRegistry::load)
+    }
+
+    /// Launches the `mimeapps.list` default for the file, falling back to `xdg-open`.
+    fn open_with_default(&mut self, path: &std::path::Path) {
+        let mime = self.mime_type_of(path);
+        let terminal_command = self.terminal_command.clone();
+        let command = self.app_registry().default_for(mime).and_then(|app| app.command(path, &terminal_command));
+        match command {
+            Some((program, args)) => self.processes.spawn(LaunchKind::Opener, &program, &args, Some(path)),
+            None => self.open_with_xdg_open(path),
+        }
+    }
+
+    fn open_with_xdg_open(&mut self, path: &std::path::Path) {
+        let args = [path.as_os_str().to_os_string()];
+        self.processes.spawn(LaunchKind::Opener, OsStr::new("xdg-open"), &args, Some(path));
+    }
+
+    fn open_with_app(&mut self, path: &std::path::Path, app_id: &str) {
+        let terminal_command = self.terminal_command.clone();
+        let command = self.app_registry().get(app_id).and_then(|app| app.command(path, &terminal_command));
+        match command {
+            Some((program, args)) => self.processes.spawn(LaunchKind::Opener, &program, &args, Some(path)),
+            None => self.notifications.push(
+                Notification::new(Severity::Error, format!("Application {} is not installed or has no command", app_id))
+                    .with_path(path)
+                    .with_action(NotificationAction::ChooseApplication(path.to_path_buf())),
+            ),
+        }
+    }
+
+    /// Context menu of a grid card or list row.
+    /// Context menu of a grid card or list row; `file_type` is what the file
+    /// browser detected, so the menu doesn't read the file every frame.
+    fn entry_context_menu(ui: &mut egui::Ui, registry: &mut Option<AppRegistry>, place: Place, entry: &Entry, file_type: Option<FileType>, action: &mut Option<EntryAction>) {
+        let (path, is_dir) = (entry.path.as_path(), entry.is_dir);
+        if ui.button("Open").clicked() {
+            *action = Some(EntryAction::Open(path.to_path_buf(), is_dir));
+            ui.close_menu();
+        }
+        if let Place::Archive(root) = place {
+            let folder = root.parent().map(paths::display_file_name).unwrap_or_default();
+            if ui.button(format!("Extract to {}", folder)).clicked() {
+                *action = Some(EntryAction::Extract(path.to_path_buf()));
+                ui.close_menu();
+            }
+        } else if let Place::Remote = place {
+            if ui.button("Download").clicked() {
+                *action = Some(EntryAction::Download(path.to_path_buf()));
+                ui.close_menu();
+            }
+            if ui.button("Rename…").clicked() {
+                *action = Some(EntryAction::Rename(path.to_path_buf()));
+                ui.close_menu();
+            }
+            if ui.button("Delete…").clicked() {
+                *action = Some(EntryAction::Delete(path.to_path_buf()));
+                ui.close_menu();
+            }
         } else {
-            let truncate_at = max_chars.saturating_sub(3);
-            let truncated: String = chars[..truncate_at].iter().collect();
-            format!("{}...", truncated)
+            if ui.button("Compress…").clicked() {
+                *action = Some(EntryAction::Compress(path.to_path_buf()));
+                ui.close_menu();
+            }
+            if !is_dir && archive::is_archive(path) {
+                if ui.button("Extract here…").clicked() {
+                    *action = Some(EntryAction::ExtractHere(path.to_path_buf()));
+                    ui.close_menu();
+                }
+                if ui.button("Extract to folder").clicked() {
+                    *action = Some(EntryAction::ExtractToFolder(path.to_path_buf()));
+                    ui.close_menu();
+                }
+            }
+        }
+        if is_dir {
+            if ui.button("Open in New Tab").clicked() {
+                *action = Some(EntryAction::OpenInNewTab(path.to_path_buf()));
+                ui.close_menu();
+            }
+            return;
         }
+        let mime = file_type.map_or("application/octet-stream", |file_type| file_type.mime);
+        let registry = registry.get_or_insert_with(AppRegistry::load);
+        let apps = registry.apps_for(mime);
+        let default_id = apps.first().map(|app| app.id.clone());
+        ui.menu_button("Open with…", |ui| {
+            if apps.is_empty() {
+                ui.label(egui::RichText::new(format!("No applications for {}", mime)).small());
+            }
+            for app in &apps {
+                let label = if Some(&app.id) == default_id.as_ref() { format!("{} (default)", app.name) } else { app.name.clone() };
+                if ui.button(label).clicked() {
+                    *action = Some(EntryAction::OpenWith(path.to_path_buf(), app.id.clone()));
+                    ui.close_menu();
+                }
+            }
+            ui.separator();
+            if ui.button("System default (xdg-open)").clicked() {
+                *action = Some(EntryAction::OpenWith(path.to_path_buf(), String::new()));
+                ui.close_menu();
+            }
+        });
+        ui.menu_button(format!("Set default for {}", mime), |ui| {
+            for app in &apps {
+                if ui.radio(Some(&app.id) == default_id.as_ref(), app.name.as_str()).clicked() {
+                    *action = Some(EntryAction::SetDefault(mime.to_string(), app.id.clone()));
+                    ui.close_menu();
+                }
+            }
+        });
     }
 
-    fn get_scrolling_text(&self, text: &str, max_width: f32, font_size: f32, _item_index: usize, is_hovered: bool, time: f32) -> (String, f32) {
-        let char_width = font_size * 0.6;
-        let max_chars = (max_width / char_width) as usize;
-        
-        // Convert to chars for proper UTF-8 handling
-        let chars: Vec<char> = text.chars().collect();
-        
-        if chars.len() <= max_chars {
-            return (text.to_string(), 0.0);
+    fn apply_entry_action(&mut self, ctx: &egui::Context, action: EntryAction) {
+        match action {
+            EntryAction::Open(path, true) => self.navigate_to(path, true),
+            EntryAction::Open(path, false) => self.open_file(ctx, &path),
+            EntryAction::OpenWith(path, app_id) => match self.files.browser.vfs.local_path(&path) {
+                Some(local) => self.open_with(&local, &app_id),
+                None => self.fetch(ctx, path, AfterFetch::OpenWith(app_id)),
+            },
+            EntryAction::OpenInNewTab(path) => self.open_tab(path, None),
+            EntryAction::Extract(path) => self.extract(ctx, path),
+            EntryAction::Compress(path) => {
+                // Right-clicking part of the selection packs all of it, named after the folder
+                let (sources, suggested) = match self.files.selection() {
+                    selection if selection.len() > 1 && selection.contains(&path) => (selection.to_vec(), self.files.browser.current_dir.file_name()),
+                    _ if path.is_dir() => (vec![path.clone()], path.file_name()),
+                    _ => (vec![path.clone()], path.file_stem()),
+                };
+                let suggested = suggested.unwrap_or(OsStr::new("archive")).to_os_string();
+                let name = paths::display(&suggested);
+                let format = CompressFormat::Zip;
+                self.compress_dialog = Some(CompressDialog { sources, name, suggested, format, level: format.default_level() });
+            }
+            EntryAction::ExtractHere(path) => self.extract_dialog = Some(ExtractDialog { archive: path, conflict: Conflict::KeepBoth }),
+            EntryAction::Download(path) => self.download(ctx, path),
+            EntryAction::Rename(path) => {
+                let name = paths::display_file_name(&path);
+                self.rename_dialog = Some((path, name));
+            }
+            EntryAction::Delete(path) => self.delete_confirm = Some(path),
+            EntryAction::ExtractToFolder(path) => {
+                let dest = archive_ops::extract_folder(&path);
+                self.start_extract(ctx, path, dest, Conflict::Skip);
+            }
+            EntryAction::SetDefault(mime, app_id) => {
+                match self.app_registry().set_default(&mime, &app_id) {
+                    Ok(()) => {
+                        let name = self.app_registry().get(&app_id).map(|app| app.name.clone()).unwrap_or(app_id);
+                        self.notifications.success(format!("{} is now the default for {}", name, mime));
+                    }
+                    Err(err) => self.notifications.error(format!("Failed to set default application: {}", err)),
+                }
+            }
         }
-        
-        if is_hovered {
-            // Use a simple time-based approach that starts immediately
-            let total_text_width = chars.len() as f32 * char_width;
-            let visible_width = max_chars as f32 * char_width;
-            let scroll_range = total_text_width - visible_width;
-            
-            // Calculate scroll position based on time with immediate start
-            let cycle_time = 4.0; // seconds for full cycle (2s right, 2s left)
-            let normalized_time = (time % cycle_time) / cycle_time;
-            
-            let scroll_offset = if normalized_time < 0.5 {
-                // First half: scroll right (0 to max)
-                normalized_time * 2.0 * scroll_range
-            } else {
-                // Second half: scroll left (max to 0)
-                (1.0 - (normalized_time - 0.5) * 2.0) * scroll_range
+    }
+
+    fn report_launch_failure(&mut self, failure: LaunchFailure) {
+        let notification = match failure.kind {
+            LaunchKind::Opener => {
+                let mut notification = Notification::new(Severity::Error, failure.message).with_detail(failure.stderr_tail);
+                if let Some(path) = failure.path {
+                    notification = notification.with_path(&path).with_action(NotificationAction::ChooseApplication(path));
+                }
+                notification
+            }
+            LaunchKind::Sound => Notification::new(Severity::Warning, format!("Sound effects disabled: {}", failure.message)).with_detail(failure.stderr_tail),
+        };
+        self.notifications.push(notification);
+    }
+
+    fn apply_notification_action(&mut self, action: NotificationAction) {
+        match action {
+            NotificationAction::ChooseApplication(path) => {
+                let mime = self.mime_type_of(&path);
+                self.choose_app_for = Some((path, mime));
+            }
+        }
+    }
+
+    fn choose_app_window(&mut self, ctx: &egui::Context) {
+        let Some((path, mime)) = self.choose_app_for.clone() else { return };
+        let mut open = true;
+        // An application id, empty for xdg-open
+        let mut chosen: Option<String> = None;
+        let registry = self.app_registry.get_or_insert_with(AppRegistry::load);
+        egui::Window::new("Open With")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let name = paths::display_file_name(&path);
+                ui.label(egui::RichText::new(format!("{} ({})", name, mime)).monospace());
+                ui.separator();
+                let apps = registry.apps_for(mime);
+                if apps.is_empty() {
+                    ui.label(egui::RichText::new(format!("No applications for {}", mime)).small());
+                }
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for app in apps {
+                        if ui.button(app.name.as_str()).clicked() {
+                            chosen = Some(app.id.clone());
+                        }
+                    }
+                });
+                ui.separator();
+                if ui.button("System default (xdg-open)").clicked() {
+                    chosen = Some(String::new());
+                }
+            });
+        if !open || chosen.is_some() {
+            self.choose_app_for = None;
+        }
+        // The file is already local, e.g. a fetched copy, so it's opened as is
+        if let Some(app_id) = chosen {
+            self.open_with(&path, &app_id);
+        }
+    }
+
+    fn compress_window(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.compress_dialog else { return };
+        let mut open = true;
+        let mut confirmed = false;
+        egui::Window::new("Compress")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Name:");
+                    ui.text_edit_singleline(&mut dialog.name);
+                    ui.label(format!(".{}", dialog.format.extension()));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Format:");
+                    let before = dialog.format;
+                    egui::ComboBox::from_id_source("compress_format")
+                        .selected_text(dialog.format.extension())
+                        .show_ui(ui, |ui| {
+                            for format in CompressFormat::ALL {
+                                ui.selectable_value(&mut dialog.format, format, format.extension());
+                            }
+                        });
+                    if dialog.format != before {
+                        dialog.level = dialog.format.default_level();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Level:");
+                    ui.add(egui::Slider::new(&mut dialog.level, dialog.format.levels()));
+                });
+                ui.separator();
+                if ui.add_enabled(paths::is_plain_name(dialog.name.trim()), egui::Button::new("Compress")).clicked() {
+                    confirmed = true;
+                }
+            });
+        if !open || confirmed {
+            let dialog = self.compress_dialog.take();
+            if confirmed && let Some(dialog) = dialog {
+                self.start_compress(ctx, dialog);
+            }
+        }
+    }
+
+    fn start_compress(&mut self, ctx: &egui::Context, dialog: CompressDialog) {
+        // A `/` or `..` would put the archive somewhere else, or replace something
+        if !paths::is_plain_name(dialog.name.trim()) {
+            self.notifications.error(format!("{} is not a valid archive name", dialog.name.trim()));
+            return;
+        }
+        let mut name = match dialog.name.trim() {
+            typed if typed == paths::display(&dialog.suggested) => dialog.suggested.clone(),
+            typed => OsString::from(typed),
+        };
+        name.push(format!(".{}", dialog.format.extension()));
+        let target = archive_ops::unique_path(&self.files.browser.current_dir.join(name));
+        let title = format!("Compressing {}", paths::display_file_name(&target));
+        self.jobs.spawn(ctx, title, move |progress| {
+            match archive_ops::compress(&dialog.sources, &target, dialog.format, dialog.level, progress) {
+                Ok(()) => Notification::new(Severity::Success, format!("Created {}", paths::display_path(&target))).with_path(&target),
+                Err(_) if progress.is_cancelled() => Notification::new(Severity::Warning, format!("Cancelled creating {}", paths::display_file_name(&target))),
+                Err(err) => Notification::new(Severity::Error, format!("Failed to create {}: {}", paths::display_file_name(&target), err)).with_path(&target),
+            }
+        });
+    }
+
+    /// Copies a remote file or folder into the downloads folder.
+    fn download(&mut self, ctx: &egui::Context, path: PathBuf) {
+        let (Some(downloads), Some(name)) = (paths::downloads_dir(), path.file_name()) else {
+            self.notifications.error("No downloads folder; set $XDG_DOWNLOAD_DIR or $HOME");
+            return;
+        };
+        let target = archive_ops::unique_path(&downloads.join(name));
+        let vfs = self.files.browser.vfs.clone();
+        let title = format!("Downloading {}", paths::display_file_name(&path));
+        self.jobs.spawn(ctx, title, move |progress| {
+            let result = std::fs::create_dir_all(&downloads).and_then(|()| vfs::copy_tree(vfs.as_ref(), &path, &LocalFs, &target, progress));
+            match result {
+                Ok(files) => Notification::new(Severity::Success, format!("Downloaded {} file{} to {}", files, if files == 1 { "" } else { "s" }, paths::display_path(&target))).with_path(&target),
+                Err(_) if progress.is_cancelled() => Notification::new(Severity::Warning, format!("Cancelled downloading {}; the partial copy was kept", paths::display_file_name(&path))).with_path(&target),
+                Err(err) => Notification::new(Severity::Error, format!("Failed to download {}: {}", paths::display_file_name(&path), err)).with_path(&target),
+            }
+        });
+    }
+
+    fn rename_window(&mut self, ctx: &egui::Context) {
+        let Some((path, name)) = &mut self.rename_dialog else { return };
+        let mut open = true;
+        let mut confirmed = false;
+        egui::Window::new("Rename")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new(paths::display_file_name(path)).monospace());
+                let response = ui.text_edit_singleline(name);
+                confirmed = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                let valid = paths::is_plain_name(name.trim());
+                if ui.add_enabled(valid, egui::Button::new("Rename")).clicked() {
+                    confirmed = true;
+                }
+            });
+        if !open || confirmed {
+            let Some((path, name)) = self.rename_dialog.take() else { return };
+            let name = name.trim();
+            if confirmed && paths::is_plain_name(name) && Some(OsStr::new(name)) != path.file_name() {
+                let target = path.with_file_name(name);
+                self.start_rename(ctx, path, target, None);
+            }
+        }
+    }
+
+    /// Renames `path` to `to` in the current filesystem as a job, since on a
+    /// server this is a round trip or two. An existing `to` is never replaced.
+    fn start_rename(&mut self, ctx: &egui::Context, path: PathBuf, to: PathBuf, reply: Option<Reply>) {
+        let vfs = self.files.browser.vfs.clone();
+        // Moving out of the shown folder refreshes it through the old path
+        let shown = if self.is_shown(&to) || !self.is_shown(&path) { to.clone() } else { path.clone() };
+        self.jobs.spawn(ctx, format!("Renaming {}", paths::display_file_name(&path)), move |_| {
+            let name = paths::display_file_name(&to);
+            // Servers would replace an existing entry without asking
+            let result = match vfs.stat(&to) {
+                Ok(_) => Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, format!("{} already exists", name))),
+                Err(_) => vfs.rename(&path, &to),
             };
-            
-            let start_char = (scroll_offset / char_width) as usize;
-            let visible_chars = max_chars;
-            
-            if start_char + visible_chars <= chars.len() {
-                let result: String = chars[start_char..start_char + visible_chars].iter().collect();
-                (result, 0.0)
-            } else {
-                // Wrap around
-                let end_part: String = chars[start_char..].iter().collect();
-                let remaining = visible_chars - (chars.len() - start_char);
-                let start_part: String = chars[..remaining].iter().collect();
-                (format!("{}{}", end_part, start_part), 0.0)
+            let notification = match result {
+                Ok(()) => Notification::new(Severity::Success, format!("Renamed {} to {}", paths::display_file_name(&path), name)).with_path(&shown),
+                Err(err) => Notification::new(Severity::Error, format!("Failed to rename {}: {}", paths::display_file_name(&path), err)).with_path(&path),
+            };
+            answer_with(reply, &notification);
+            notification
+        });
+    }
+
+    /// Creates the folder `path` in the current filesystem as a job.
+    fn start_mkdir(&mut self, ctx: &egui::Context, path: PathBuf, reply: Option<Reply>) {
+        let vfs = self.files.browser.vfs.clone();
+        self.jobs.spawn(ctx, format!("Creating {}", paths::display_file_name(&path)), move |_| {
+            let notification = match vfs.mkdir(&path) {
+                Ok(()) => Notification::new(Severity::Success, format!("Created {}", paths::display_file_name(&path))).with_path(&path),
+                Err(err) => Notification::new(Severity::Error, format!("Failed to create {}: {}", paths::display_path(&path), err)).with_path(&path),
+            };
+            answer_with(reply, &notification);
+            notification
+        });
+    }
+
+    fn delete_window(&mut self, ctx: &egui::Context) {
+        let Some(path) = &self.delete_confirm else { return };
+        let mut open = true;
+        let mut confirmed = false;
+        egui::Window::new("Delete")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new(paths::display_file_name(path)).monospace());
+                ui.colored_label(egui::Color32::from_rgb(255, 200, 0), "⚠ Deleted from the server for good, including everything inside");
+                ui.separator();
+                if ui.button("Delete").clicked() {
+                    confirmed = true;
+                }
+            });
+        if !open || confirmed {
+            let Some(path) = self.delete_confirm.take() else { return };
+            if confirmed {
+                self.start_delete(ctx, path, None);
             }
-        } else {
-            // Show truncated text with ellipsis
-            (self.truncate_text(text, max_width, font_size), 0.0)
         }
     }
 
-    fn save_settings(&self) {
-        let settings = format!(
-            "ui_scale={}\nmax_items_per_row={}\nshow_scanlines={}\nshow_hidden={}\nhorizontal_spacing={}\nvertical_spacing={}\n",
-            self.ui_scale, self.max_items_per_row, self.show_scanlines, self.show_hidden, self.horizontal_spacing, self.vertical_spacing
-        );
-        let _ = std::fs::write("datara_settings.txt", settings);
+    /// Deletes `path` with everything inside as a job; `reply` is answered
+    /// when it ends.
+    fn start_delete(&mut self, ctx: &egui::Context, path: PathBuf, reply: Option<Reply>) {
+        let vfs = self.files.browser.vfs.clone();
+        let title = format!("Deleting {}", paths::display_file_name(&path));
+        self.jobs.spawn(ctx, title, move |progress| {
+            let name = paths::display_file_name(&path);
+            let notification = match vfs::remove_tree(vfs.as_ref(), &path, progress) {
+                Ok(_) => Notification::new(Severity::Success, format!("Deleted {}", name)).with_path(&path),
+                Err(_) if progress.is_cancelled() => Notification::new(Severity::Warning, format!("Stopped deleting {}; some of it is gone", name)).with_path(&path),
+                Err(err) => Notification::new(Severity::Error, format!("Failed to delete {}: {}", name, err)).with_path(&path),
+            };
+            answer_with(reply, &notification);
+            notification
+        });
+    }
+
+    /// Copies `path` to `to` in the current filesystem as a job.
+    fn start_copy(&mut self, ctx: &egui::Context, path: PathBuf, to: PathBuf, reply: Option<Reply>) {
+        let vfs = self.files.browser.vfs.clone();
+        let title = format!("Copying {}", paths::display_file_name(&path));
+        self.jobs.spawn(ctx, title, move |progress| {
+            let target = paths::display_path(&to);
+            let notification = match vfs::copy_tree(vfs.as_ref(), &path, vfs.as_ref(), &to, progress) {
+                Ok(files) => Notification::new(Severity::Success, format!("Copied {} file{} to {}", files, if files == 1 { "" } else { "s" }, target)).with_path(&to),
+                Err(_) if progress.is_cancelled() => Notification::new(Severity::Warning, format!("Cancelled copying to {}; the partial copy was kept", target)).with_path(&to),
+                Err(err) => Notification::new(Severity::Error, format!("Failed to copy {}: {}", paths::display_path(&path), err)).with_path(&path),
+            };
+            answer_with(reply, &notification);
+            notification
+        });
+    }
+
+    /// Files dropped onto the window are uploaded into the current folder
+    /// while browsing a server.
+    fn upload_dropped(&mut self, ctx: &egui::Context) {
+        let Some(remote) = &self.remote else { return };
+        let dropped: Vec<PathBuf> = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|file| file.path.clone()).collect());
+        for source in dropped {
+            let Some(name) = source.file_name() else { continue };
+            let target = self.files.browser.current_dir.join(name);
+            let location = remote.with_path(&target).to_string();
+            let vfs = self.files.browser.vfs.clone();
+            let title = format!("Uploading {}", paths::display_file_name(&source));
+            self.jobs.spawn(ctx, title, move |progress| {
+                match vfs::copy_tree(&LocalFs, &source, vfs.as_ref(), &target, progress) {
+                    Ok(files) => Notification::new(Severity::Success, format!("Uploaded {} file{} to {}", files, if files == 1 { "" } else { "s" }, location)).with_path(&target),
+                    Err(_) if progress.is_cancelled() => Notification::new(Severity::Warning, format!("Cancelled uploading to {}; the partial copy was kept", location)).with_path(&target),
+                    Err(err) => Notification::new(Severity::Error, format!("Failed to upload to {}: {}", location, err)).with_path(&source),
+                }
+            });
+        }
+    }
+
+    fn extract_window(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.extract_dialog else { return };
+        let mut open = true;
+        let mut confirmed = false;
+        egui::Window::new("Extract Here")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new(paths::display_file_name(&dialog.archive)).monospace());
+                ui.separator();
+                ui.label("When a file already exists:");
+                ui.radio_value(&mut dialog.conflict, Conflict::KeepBoth, "Keep both");
+                ui.radio_value(&mut dialog.conflict, Conflict::Replace, "Replace");
+                ui.radio_value(&mut dialog.conflict, Conflict::Skip, "Skip");
+                ui.separator();
+                if ui.button("Extract").clicked() {
+                    confirmed = true;
+                }
+            });
+        if !open || confirmed {
+            let dialog = self.extract_dialog.take();
+            if confirmed && let Some(dialog) = dialog {
+                let dest = dialog.archive.parent().map(PathBuf::from).unwrap_or_default();
+                self.start_extract(ctx, dialog.archive, dest, dialog.conflict);
+            }
+        }
+    }
+
+    fn start_extract(&mut self, ctx: &egui::Context, archive: PathBuf, dest: PathBuf, conflict: Conflict) {
+        let title = format!("Extracting {}", paths::display_file_name(&archive));
+        self.jobs.spawn(ctx, title, move |progress| {
+            let name = paths::display_file_name(&archive);
+            match archive_ops::extract(&archive, &dest, conflict, progress) {
+                Ok(report) if !report.refused.is_empty() => Notification::new(
+                    Severity::Warning,
+                    format!("Extracted {} from {}, refused {} unsafe member{}", report.files, name, report.refused.len(), if report.refused.len() == 1 { "" } else { "s" }),
+                )
+                .with_path(&dest)
+                .with_detail(report.refused.join("\n")),
+                Ok(report) => {
+                    let skipped = if report.skipped > 0 { format!(" ({} skipped)", report.skipped) } else { String::new() };
+                    Notification::new(Severity::Success, format!("Extracted {} file{} to {}{}", report.files, if report.files == 1 { "" } else { "s" }, paths::display_path(&dest), skipped))
+                        .with_path(&dest)
+                }
+                Err(_) if progress.is_cancelled() => Notification::new(Severity::Warning, format!("Cancelled extracting {}", name)).with_path(&dest),
+                Err(err) => Notification::new(Severity::Error, format!("Failed to extract {}: {}", name, err)).with_path(&archive),
+            }
+        });
+    }
+
+    fn to_config(&self) -> Config {
+        let mut config = Config::default();
+        config.display.ui_scale = self.ui_scale;
+        config.display.max_items_per_row = self.files.max_items_per_row;
+        config.display.horizontal_spacing = self.files.horizontal_spacing;
+        config.display.vertical_spacing = self.files.vertical_spacing;
+        config.display.show_scanlines = self.show_scanlines;
+        config.display.show_preview = self.show_preview;
+        config.files.show_hidden = self.files.browser.show_hidden;
+        config.files.show_thumbnails = self.files.show_thumbnails;
+        config.files.bookmarks = self.bookmarks.clone();
+        config.openers.terminal_command = self.terminal_command.clone();
+        config.openers.rules = self.opener_rules.clone();
+        // Command-line overrides are not saved while they are in effect
+        if self.overrides.show_hidden == Some(self.files.browser.show_hidden) {
+            config.files.show_hidden = self.file_config.files.show_hidden;
+        }
+        if self.overrides.ui_scale == Some(self.ui_scale) {
+            config.display.ui_scale = self.file_config.display.ui_scale;
+        }
+        config
+    }
+
+    fn apply_config(&mut self, config: Config) {
+        self.file_config = config.clone();
+        self.ui_scale = config.display.ui_scale;
+        self.files.max_items_per_row = config.display.max_items_per_row;
+        self.files.horizontal_spacing = config.display.horizontal_spacing;
+        self.files.vertical_spacing = config.display.vertical_spacing;
+        self.show_scanlines = config.display.show_scanlines;
+        self.show_preview = config.display.show_preview;
+        self.files.browser.show_hidden = config.files.show_hidden;
+        self.files.show_thumbnails = config.files.show_thumbnails;
+        self.bookmarks = config.files.bookmarks;
+        self.terminal_command = config.openers.terminal_command;
+        self.opener_rules = config.openers.rules;
+    }
+
+    /// Saves changed settings after a short delay and applies external edits.
+    fn sync_settings(&mut self, ctx: &egui::Context) {
+        let current = self.to_config();
+        match self.config_store.update(ctx, &current) {
+            Some(ConfigEvent::SaveFailed(err)) => self.report_config_error(err),
+            Some(ConfigEvent::ReloadFailed(errors)) => {
+                for err in errors {
+                    self.report_config_error(err);
+                }
+            }
+            Some(ConfigEvent::Reloaded(loaded)) => {
+                let show_hidden = self.files.browser.show_hidden;
+                self.apply_loaded_config(loaded);
+                if let Some(path) = self.config_store.path() {
+                    self.notifications.success(format!("Reloaded {}", paths::display_path(path)));
+                }
+                if self.files.browser.show_hidden != show_hidden {
+                    self.read_dir();
+                }
+            }
+            None => {}
+        }
     }
 
     fn load_settings(&mut self) {
-        if let Ok(contents) = std::fs::read_to_string("datara_settings.txt") {
-            for line in contents.lines() {
-                if let Some((key, value)) = line.split_once('=') {
-                    match key {
-                        "ui_scale" => if let Ok(val) = value.parse::<f32>() { self.ui_scale = val; },
-                        "max_items_per_row" => if let Ok(val) = value.parse::<i32>() { self.max_items_per_row = val.clamp(2, 5); },
-                        "show_scanlines" => if let Ok(val) = value.parse::<bool>() { self.show_scanlines = val; },
-                        "show_hidden" => if let Ok(val) = value.parse::<bool>() { self.show_hidden = val; },
-                        "horizontal_spacing" => if let Ok(val) = value.parse::<f32>() { self.horizontal_spacing = val; },
-                        "vertical_spacing" => if let Ok(val) = value.parse::<f32>() { self.vertical_spacing = val; },
-                        _ => {}
-                    }
+        match self.config_store.load() {
+            Some(loaded) => self.apply_loaded_config(loaded),
+            None => self.notifications.error("No config directory; set $XDG_CONFIG_HOME or $HOME to keep settings"),
+        }
+    }
+
+    fn apply_loaded_config(&mut self, loaded: config::LoadedConfig) {
+        for err in loaded.errors {
+            self.report_config_error(err);
+        }
+        if let Some(legacy) = loaded.migrated_from
+            && let Some(path) = self.config_store.path() {
+            self.notifications.success(format!("Migrated {} to {}", paths::display_path(&legacy), paths::display_path(path)));
+        }
+        self.apply_config(loaded.config);
+    }
+
+    fn report_config_error(&mut self, err: String) {
+        // Parse errors carry a source snippet after the first line
+        let (message, detail) = err.split_once('\n').unwrap_or((&err, ""));
+        let mut notification = Notification::new(Severity::Error, message).with_detail(detail);
+        if let Some(path) = self.config_store.path() {
+            notification = notification.with_path(path);
+        }
+        self.notifications.push(notification);
+    }
+
+    fn opener_rules_editor(&mut self, ui: &mut egui::Ui) {
+        ui.label(egui::RichText::new("First matching rule wins. %f file, %d directory, datara:image|hex|markdown for built-in viewers, datara:default for the system default application.").small());
+        ui.horizontal(|ui| {
+            ui.label("Terminal:");
+            ui.text_edit_singleline(&mut self.terminal_command);
+        });
+        ui.add_space(4.0);
+
+        let mut move_up: Option<usize> = None;
+        let mut remove: Option<usize> = None;
+        egui::ScrollArea::vertical().max_height(260.0).show(ui, |ui| {
+            egui::Grid::new("opener_rules").num_columns(5).striped(true).show(ui, |ui| {
+                for (i, rule) in self.opener_rules.iter_mut().enumerate() {
+                    egui::ComboBox::from_id_source(("opener_kind", i))
+                        .selected_text(rule.kind.label())
+                        .width(90.0)
+                        .show_ui(ui, |ui| {
+                            for kind in MatchKind::ALL {
+                                ui.selectable_value(&mut rule.kind, kind, kind.label());
+                            }
+                        });
+                    ui.add(egui::TextEdit::singleline(&mut rule.pattern).desired_width(140.0));
+                    ui.add(egui::TextEdit::singleline(&mut rule.command).desired_width(160.0));
+                    ui.checkbox(&mut rule.terminal, "Term");
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(i > 0, egui::Button::new("↑")).clicked() {
+                            move_up = Some(i);
+                        }
+                        if ui.button("✖").clicked() {
+                            remove = Some(i);
+                        }
+                        if let Err(err) = rule.validate() {
+                            ui.colored_label(egui::Color32::RED, "⚠").on_hover_text(err);
+                        }
+                    });
+                    ui.end_row();
                 }
+            });
+        });
+        if let Some(i) = move_up {
+            self.opener_rules.swap(i - 1, i);
+        }
+        if let Some(i) = remove {
+            self.opener_rules.remove(i);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Add Rule").clicked() {
+                self.opener_rules.push(OpenerRule {
+                    kind: MatchKind::Extension,
+                    pattern: String::new(),
+                    command: "xdg-open %f".to_string(),
+                    terminal: false,
+                });
+            }
+            if ui.button("Restore Default Rules").clicked() {
+                self.opener_rules = openers::default_rules();
+            }
+        });
+    }
+
+    fn preview_panel(&mut self, ctx: &egui::Context, bg: egui::Color32) {
+        // Follow the hovered entry, fall back to the clicked one, otherwise keep the last preview
+        let hovered = self.files.hovered().map(|entry| entry.path.clone());
+        let loader = self.preview_loader.get_or_insert_with(|| PreviewLoader::new(ctx));
+        match hovered.or_else(|| self.files.browser.selected_path.clone()) {
+            Some(target) if self.preview.as_ref().map(|p| &p.path) != Some(&target) => loader.want(&self.files.browser.vfs, &target),
+            _ => loader.cancel(),
+        }
+        if let Some(preview) = loader.poll() {
+            self.preview = Some(preview);
+        }
+        let loading = loader.loading().is_some();
+
+        let mut clicked_link = None;
+        egui::SidePanel::right("preview_panel")
+            .frame(egui::Frame::default().fill(bg).inner_margin(12.0 * self.ui_scale))
+            .resizable(true)
+            .default_width(320.0 * self.ui_scale)
+            .show(ctx, |ui| {
+                let Some(preview) = &mut self.preview else {
+                    if loading { ui.spinner(); } else { ui.label("No selection"); }
+                    return;
+                };
+                let name = paths::display_file_name(&preview.path);
+                let name_text = if preview.is_dir { egui::RichText::new(name).strong().monospace() } else { egui::RichText::new(name).monospace() };
+                ui.label(name_text);
+                if preview.path.file_name().is_some_and(paths::needs_escaping) {
+                    ui.label(egui::RichText::new("Name is not valid UTF-8; invalid bytes are shown as \\xNN").small());
+                }
+                let date_str = preview.modified.map(file_browser::format_date).unwrap_or_default();
+                let size_str = preview.size.map(file_browser::format_size).unwrap_or_default();
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(format!("{}  {}", date_str, size_str)).small().monospace());
+                    if loading { ui.spinner(); }
+                });
+                ui.separator();
+
+                egui::ScrollArea::both()
+                    .auto_shrink([false; 2])
+                    .show(ui, |ui| {
+                    match &mut preview.content {
+                        PreviewContent::Text { lines, truncated } | PreviewContent::Hex { lines, truncated } => {
+                            for line in lines {
+                                ui.label(egui::RichText::new(line.as_str()).monospace().size(12.0 * self.ui_scale));
+                            }
+                            if *truncated {
+                                ui.label(egui::RichText::new("…").monospace());
+                            }
+                        }
+                        PreviewContent::Markdown { document, source, show_source } => {
+                            ui.horizontal(|ui| {
+                                ui.selectable_value(show_source, false, "Rendered");
+                                ui.selectable_value(show_source, true, "Source");
+                            });
+                            if *show_source {
+                                ui.label(egui::RichText::new(source.as_str()).monospace().size(12.0 * self.ui_scale));
+                            } else {
+                                clicked_link = document.ui(ui, 13.0 * self.ui_scale);
+                            }
+                        }
+                        PreviewContent::Image { texture, width, height } => {
+                            let available = ui.available_width();
+                            let tex_size = texture.size_vec2();
+                            let scale = (available / tex_size.x).min(1.0);
+                            ui.image((texture.id(), tex_size * scale));
+                            ui.label(egui::RichText::new(format!("{} × {}", width, height)).monospace());
+                        }
+                        PreviewContent::Directory { files, dirs, total_size, truncated } => {
+                            let more = if *truncated { "+" } else { "" };
+                            ui.label(format!("Folders: {}{}", dirs, more));
+                            ui.label(format!("Files:   {}{}", files, more));
+                            ui.label(format!("Size:    {}{}", file_browser::format_size(*total_size), more));
+                        }
+                        PreviewContent::Error(err) => {
+                            ui.colored_label(egui::Color32::RED, err);
+                        }
+                    }
+                });
+            });
+        if let Some(path) = clicked_link {
+            if path.is_dir() {
+                self.navigate_to(path, true);
+            } else if path.exists() {
+                self.open_file(ctx, &path);
             }
         }
     }
@@ -341,8 +1632,25 @@ impl DataraApp {
 
 impl eframe::App for DataraApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Load icons if not already loaded
-        self.load_icons(ctx);
+        for failure in self.processes.poll(ctx) {
+            self.report_launch_failure(failure);
+        }
+        self.upload_dropped(ctx);
+        for request in self.instance.as_ref().map(Inbox::poll).unwrap_or_default() {
+            self.open_forwarded(ctx, request);
+        }
+        for call in self.control.as_ref().map(Control::poll).unwrap_or_default() {
+            self.run_command(ctx, call);
+        }
+        for notification in self.jobs.poll() {
+            // Show what the job created or filled
+            if notification.path.as_deref().is_some_and(|path| self.is_shown(path)) {
+                self.read_dir();
+            }
+            self.notifications.push(notification);
+        }
+        self.open_fetched(ctx);
+        self.poll_connecting();
         
         let bg = egui::Color32::from_rgba_unmultiplied(0, 12, 0, 210);
 
@@ -350,8 +1658,8 @@ impl eframe::App for DataraApp {
             .frame(egui::Frame::default().fill(bg))
             .show(ctx, |ui| {
             ui.horizontal(|ui| {
-                let back_enabled = !self.history.is_empty();
-                let fwd_enabled = !self.future.is_empty();
+                let back_enabled = self.files.browser.can_go_back();
+                let fwd_enabled = self.files.browser.can_go_forward();
                 if ui.add_enabled(back_enabled, egui::Button::new("←")).clicked() {
                     self.navigate_back();
                 }
@@ -362,19 +1670,54 @@ impl eframe::App for DataraApp {
                     self.navigate_up();
                 }
                 ui.separator();
-                ui.label(egui::RichText::new(self.current_dir.to_string_lossy()).monospace());
+                self.bookmarks_menu(ui);
+                self.address_bar(ui);
+                if let Some(connecting) = &self.connecting {
+                    ui.spinner();
+                    ui.label(egui::RichText::new(format!("Connecting to {}…", connecting.url.host)).monospace());
+                }
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     // Settings button
                     if ui.button("⚙️").clicked() {
                         self.show_settings = !self.show_settings;
                     }
                     ui.separator();
-                    let label = if self.grid_view { "Grid" } else { "List" };
+                    let errors = self.notifications.error_count();
+                    let log_label = if errors > 0 { format!("Log ({})", errors) } else { "Log".to_string() };
+                    if ui.selectable_label(self.notifications.show_log, log_label).clicked() {
+                        self.notifications.show_log = !self.notifications.show_log;
+                    }
+                    let preview_label = if self.show_preview { "On" } else { "Off" };
+                    if ui.button(format!("Preview: {}", preview_label)).clicked() {
+                        self.show_preview = !self.show_preview;
+                    }
+                    let label = if self.files.grid_view { "Grid" } else { "List" };
                     if ui.button(format!("View: {}", label)).clicked() {
-                        self.grid_view = !self.grid_view;
+                        self.files.grid_view = !self.files.grid_view;
                     }
                 });
             });
+            if self.tabs.len() > 1 {
+                let mut switch_to: Option<usize> = None;
+                let mut close: Option<usize> = None;
+                ui.horizontal(|ui| {
+                    for (i, tab) in self.tabs.iter().enumerate() {
+                        let title = if i == self.active_tab { tab_title(self.remote.as_ref(), &self.files.browser.current_dir) } else { tab.title() };
+                        if ui.selectable_label(i == self.active_tab, title).clicked() {
+                            switch_to = Some(i);
+                        }
+                        if ui.small_button("✖").clicked() {
+                            close = Some(i);
+                        }
+                        ui.separator();
+                    }
+                });
+                if let Some(i) = close {
+                    self.close_tab(i);
+                } else if let Some(i) = switch_to {
+                    self.switch_tab(i);
+                }
+            }
         });
 
         // Settings window
@@ -388,19 +1731,19 @@ impl eframe::App for DataraApp {
                         ui.heading("Display Settings");
                         ui.separator();
                         
-                        ui.add(egui::Slider::new(&mut self.max_items_per_row, 2..=5).text("Max Items Per Row"));
+                        ui.add(egui::Slider::new(&mut self.files.max_items_per_row, 2..=5).text("Max Items Per Row"));
                         // Calculate max horizontal spacing based on available space
-                        let max_h_spacing = if self.grid_view {
+                        let max_h_spacing = if self.files.grid_view {
                             let available_width = ctx.screen_rect().width() - (32.0 * self.ui_scale); // Account for margins
-                            let columns = self.max_items_per_row as f32;
+                            let columns = self.files.max_items_per_row as f32;
                             let min_item_width = 200.0 * self.ui_scale; // Minimum reasonable item width
                             let max_spacing = (available_width - (columns * min_item_width)) / (columns - 1.0);
-                            max_spacing.max(0.0).min(100.0)
+                            max_spacing.clamp(0.0, 100.0)
                         } else {
                             50.0 // For list view, keep reasonable max
                         };
-                        ui.add(egui::Slider::new(&mut self.horizontal_spacing, 0.0..=max_h_spacing).text("Horizontal Spacing"));
-                        ui.add(egui::Slider::new(&mut self.vertical_spacing, 0.0..=50.0).text("Vertical Spacing"));
+                        ui.add(egui::Slider::new(&mut self.files.horizontal_spacing, 0.0..=max_h_spacing).text("Horizontal Spacing"));
+                        ui.add(egui::Slider::new(&mut self.files.vertical_spacing, 0.0..=50.0).text("Vertical Spacing"));
                         
                         ui.separator();
                         ui.heading("Visual Effects");
@@ -412,25 +1755,44 @@ impl eframe::App for DataraApp {
                         ui.heading("File Options");
                         ui.separator();
                         
-                        let hidden_label = if self.show_hidden { "Show Hidden Files" } else { "Hide Hidden Files" };
-                        if ui.checkbox(&mut self.show_hidden, hidden_label).changed() {
+                        let hidden_label = if self.files.browser.show_hidden { "Show Hidden Files" } else { "Hide Hidden Files" };
+                        if ui.checkbox(&mut self.files.browser.show_hidden, hidden_label).changed() {
                             self.read_dir();
                         }
+                        ui.checkbox(&mut self.files.show_thumbnails, "Image Thumbnails");
+
+                        ui.separator();
+                        egui::CollapsingHeader::new("Opener Rules").show(ui, |ui| {
+                            self.opener_rules_editor(ui);
+                        });
                         
                         ui.separator();
                         if ui.button("Reset to Defaults").clicked() {
-                            self.ui_scale = 1.0;
-                            self.max_items_per_row = 3;
-                            self.horizontal_spacing = 16.0;
-                            self.vertical_spacing = 12.0;
-                            self.show_scanlines = false;
-                            self.show_hidden = false;
+                            let show_preview = self.show_preview;
+                            self.overrides = Overrides::default();
+                            self.apply_config(Config::default());
+                            self.show_preview = show_preview;
                             self.read_dir();
                         }
+                        if self.config_store.is_dirty() {
+                            ui.label(egui::RichText::new("Saving…").small());
+                        }
                     });
                 });
         }
 
+        self.pick_bar(ctx, bg);
+
+        if self.notifications.show_log
+            && let Some(action) = self.notifications.log_panel(ctx, bg) {
+            self.apply_notification_action(action);
+        }
+
+        if self.show_preview {
+            self.preview_panel(ctx, bg);
+        }
+        self.jobs.show(ctx, bg);
+
         egui::CentralPanel::default()
             .frame(egui::Frame::default().fill(bg))
             .show(ctx, |ui| {
@@ -439,247 +1801,32 @@ impl eframe::App for DataraApp {
             style.override_font_id = Some(egui::FontId::monospace(16.0 * self.ui_scale));
             ui.ctx().set_style(style);
 
-            if let Some(err) = &self.error {
-                ui.colored_label(egui::Color32::RED, err);
+            let mut entry_action: Option<EntryAction> = None;
+            let place = Place::of(self.remote.is_some(), self.archive_root.as_deref());
+            let registry = &mut self.app_registry;
+            self.files.ui_scale = self.ui_scale;
+            let response = self.files.ui_with_menu(ui, |ui, entry, file_type| {
+                Self::entry_context_menu(ui, registry, place, entry, file_type, &mut entry_action);
+            });
+            if response.events.iter().any(|event| !matches!(event, BrowserEvent::Hovered(_))) {
+                Self::play_click_sound(&mut self.processes);
             }
-
-            let mut navigate_to_path: Option<PathBuf> = None;
-            let base_green = egui::Color32::from_rgb(0, 255, 0);
-            let hover_green = egui::Color32::from_rgb(120, 255, 120);
-            let hover_stroke = egui::Stroke { width: 1.0 * self.ui_scale, color: hover_green };
-
-            // Add margin around the entire content area
-            let margin = 16.0 * self.ui_scale;
-            ui.add_space(margin);
-
-            if self.grid_view {
-                // Grid view with neon bordered cards and metadata
-                let card_height = 80.0 * self.ui_scale;
-                let horizontal_spacing = self.horizontal_spacing * self.ui_scale;
-                let vertical_spacing = self.vertical_spacing * self.ui_scale;
-                let available_width = ui.available_width() - (margin * 2.0);
-                let columns = self.max_items_per_row as usize;
-                // Calculate item width based on available space and max items per row
-                let desired_width = (available_width - (horizontal_spacing * (columns - 1) as f32)) / columns as f32;
-                
-                egui::ScrollArea::vertical()
-                    .auto_shrink([false; 2])
-                    .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysVisible)
-                    .show(ui, |ui| {
-                        egui::Grid::new("files_grid").num_columns(columns).spacing(egui::vec2(horizontal_spacing, vertical_spacing)).show(ui, |ui| {
-                    for (i, entry) in self.entries.iter().enumerate() {
-                        let name_plain = Self::entry_name(entry);
-                        let (is_dir, size_opt, date_opt) = Self::entry_info(entry);
-
-                        let (alloc_id, alloc_rect) = ui.allocate_space(egui::vec2(desired_width, card_height - margin));
-                        // Adjust rect to add left margin and ensure right margin
-                        let adjusted_rect = alloc_rect.translate(egui::vec2(margin, 0.0));
-                        let response = ui
-                            .interact(adjusted_rect, alloc_id, egui::Sense::click())
-                            .on_hover_cursor(egui::CursorIcon::PointingHand);
-
-                        // Sharp neon border and hover inner border
-                        let round = 0.0;
-                        ui.painter().rect_stroke(adjusted_rect, round, hover_stroke);
-                        if response.hovered() {
-                            ui.painter().rect_stroke(adjusted_rect.shrink(2.0), round, hover_stroke);
-                            // Play hover sound only once per item
-                            if self.last_hovered_item != Some(i) {
-                                self.play_hover_sound();
-                                self.last_hovered_item = Some(i);
-                            }
-                        } else if self.last_hovered_item == Some(i) {
-                            self.last_hovered_item = None;
-                        }
-
-                        // Content positions (vertically centered name + vector icon)
-                        let left = adjusted_rect.left() + 12.0 * self.ui_scale;
-                        let center_y = adjusted_rect.center().y;
-
-                        // Icon (custom PNG)
-                        let icon_size = 28.0 * self.ui_scale;
-                        if let Some(icon_texture) = if is_dir { &self.folder_icon } else { &self.file_icon } {
-                            let icon_rect = egui::Rect::from_center_size(
-                                egui::pos2(left + icon_size * 0.5, center_y),
-                                egui::vec2(icon_size, icon_size)
-                            );
-                            ui.painter().image(icon_texture.id(), icon_rect, egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)), base_green);
-                        } else {
-                            // Fallback to emoji if custom icon not loaded
-                            let icon = if is_dir { "📁" } else { "📄" };
-                            ui.painter().text(
-                                egui::pos2(left.floor(), center_y.floor()),
-                                egui::Align2::LEFT_CENTER,
-                                icon,
-                                egui::FontId::monospace(icon_size),
-                                base_green,
-                            );
-                        }
-
-                        // Name (no icon in text) with scrolling
-                        let name_x = left + icon_size + 12.0 * self.ui_scale;
-                        let name_width = adjusted_rect.right() - name_x - 8.0 * self.ui_scale;
-                        let font_size = 15.0 * self.ui_scale;
-                        let time = ui.ctx().input(|i| i.time) as f32;
-                        let (display_name, _) = self.get_scrolling_text(&name_plain, name_width, font_size, i, response.hovered(), time);
-                        let name_text = if is_dir { egui::RichText::new(display_name).strong().monospace() } else { egui::RichText::new(display_name).monospace() };
-                        ui.painter().text(
-                            egui::pos2(name_x.floor(), center_y.floor()),
-                            egui::Align2::LEFT_CENTER,
-                            name_text.text(),
-                            egui::FontId::monospace(font_size),
-                            base_green,
-                        );
-
-                        // Metadata line (date · size) with scrolling
-                        let meta_y = (adjusted_rect.bottom() - 10.0 * self.ui_scale).floor();
-                        let date_str = date_opt.map(Self::format_date).unwrap_or_default();
-                        let size_str = size_opt.map(Self::format_size).unwrap_or_default();
-                        let meta = if is_dir {
-                            date_str
-                        } else if !date_str.is_empty() && !size_str.is_empty() {
-                            format!("{}  ·  {}", date_str, size_str)
-                        } else {
-                            format!("{}{}", date_str, size_str)
-                        };
-                        let meta_width = adjusted_rect.right() - name_x - 8.0 * self.ui_scale;
-                        let meta_font_size = 11.0 * self.ui_scale;
-                        let (display_meta, _) = self.get_scrolling_text(&meta, meta_width, meta_font_size, i, response.hovered(), time);
-                        ui.painter().text(
-                            egui::pos2(name_x.floor(), meta_y),
-                            egui::Align2::LEFT_BOTTOM,
-                            display_meta,
-                            egui::FontId::monospace(meta_font_size),
-                            base_green,
-                        );
-
-                        if response.clicked() {
-                            self.play_click_sound();
-                            if is_dir {
-                                navigate_to_path = Some(entry.path());
-                            } else {
-                                self.open_file(&entry.path());
-                            }
-                        }
-
-                        let last_col = (i + 1) % columns == 0;
-                        if last_col { ui.end_row(); }
-                    }
-                    });
-                });
-                // Add bottom margin after grid scroll area
-                ui.add_space(margin);
-            } else {
-                // List view with sharp bordered rows and vector icons
-                egui::ScrollArea::vertical()
-                    .auto_shrink([false; 2])
-                    .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysVisible)
-                    .show(ui, |ui| {
-                    // Add top margin for list view
-                    ui.add_space(margin);
-                    
-                    let row_h = 56.0 * self.ui_scale;
-                    let vertical_spacing = self.vertical_spacing * self.ui_scale;
-                    let available_width = ui.available_width() - (margin * 2.0);
-                    for (i, entry) in self.entries.iter().enumerate() {
-                        let (is_dir, _size_opt, _date_opt) = Self::entry_info(entry);
-                        let name_plain = Self::entry_name(entry);
-
-                        // Add vertical margin between rows
-                        if i > 0 { ui.add_space(vertical_spacing); }
-
-                        let (row_id, row_rect) = ui.allocate_space(egui::vec2(available_width, row_h - margin));
-                        // Adjust rect to add left margin
-                        let adjusted_rect = row_rect.translate(egui::vec2(margin, 0.0));
-                        let response = ui
-                            .interact(adjusted_rect, row_id, egui::Sense::click())
-                            .on_hover_cursor(egui::CursorIcon::PointingHand);
-
-                        // Sharp border
-                        ui.painter().rect_stroke(adjusted_rect, 0.0, hover_stroke);
-                        if response.hovered() {
-                            ui.painter().rect_stroke(adjusted_rect.shrink(2.0), 0.0, hover_stroke);
-                            // Play hover sound only once per item
-                            if self.last_hovered_item != Some(i) {
-                                self.play_hover_sound();
-                                self.last_hovered_item = Some(i);
-                            }
-                        } else if self.last_hovered_item == Some(i) {
-                            self.last_hovered_item = None;
-                        }
-
-                        // Icon and name centered vertically
-                        let left = adjusted_rect.left() + 12.0 * self.ui_scale;
-                        let cy = adjusted_rect.center().y;
-                        let icon_size = 26.0 * self.ui_scale;
-                        if let Some(icon_texture) = if is_dir { &self.folder_icon } else { &self.file_icon } {
-                            let icon_rect = egui::Rect::from_center_size(
-                                egui::pos2(left + icon_size * 0.5, cy),
-                                egui::vec2(icon_size, icon_size)
-                            );
-                            ui.painter().image(icon_texture.id(), icon_rect, egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)), base_green);
-                        } else {
-                            // Fallback to emoji if custom icon not loaded
-                            let icon = if is_dir { "📁" } else { "📄" };
-                            ui.painter().text(
-                                egui::pos2(left.floor(), cy.floor()),
-                                egui::Align2::LEFT_CENTER,
-                                icon,
-                                egui::FontId::monospace(icon_size),
-                                base_green,
-                            );
-                        }
-
-                        let name_x = left + icon_size + 12.0 * self.ui_scale;
-                        let name_width = adjusted_rect.right() - name_x - 8.0 * self.ui_scale;
-                        let font_size = 15.0 * self.ui_scale;
-                        let time = ui.ctx().input(|i| i.time) as f32;
-                        let (display_name, _) = self.get_scrolling_text(&name_plain, name_width, font_size, i, response.hovered(), time);
-                        let name_text = if is_dir { egui::RichText::new(display_name).strong().monospace() } else { egui::RichText::new(display_name).monospace() };
-                        ui.painter().text(
-                            egui::pos2(name_x.floor(), cy.floor()),
-                            egui::Align2::LEFT_CENTER,
-                            name_text.text(),
-                            egui::FontId::monospace(font_size),
-                            base_green,
-                        );
-
-                        // Add metadata (date · size) to list view with scrolling
-                        let (is_dir, size_opt, date_opt) = Self::entry_info(entry);
-                        let date_str = date_opt.map(Self::format_date).unwrap_or_default();
-                        let size_str = size_opt.map(Self::format_size).unwrap_or_default();
-                        let meta = if is_dir {
-                            date_str
-                        } else if !date_str.is_empty() && !size_str.is_empty() {
-                            format!("{}  ·  {}", date_str, size_str)
-                        } else {
-                            format!("{}{}", date_str, size_str)
-                        };
-                        let meta_width = adjusted_rect.right() - name_x - 8.0 * self.ui_scale;
-                        let meta_font_size = 11.0 * self.ui_scale;
-                        let meta_y = cy + 12.0 * self.ui_scale;
-                        let (display_meta, _) = self.get_scrolling_text(&meta, meta_width, meta_font_size, i, response.hovered(), time);
-                        ui.painter().text(
-                            egui::pos2(name_x.floor(), meta_y),
-                            egui::Align2::LEFT_TOP,
-                            display_meta,
-                            egui::FontId::monospace(meta_font_size),
-                            base_green,
-                        );
-
-                        if response.clicked() {
-                            self.play_click_sound();
-                            if is_dir {
-                                navigate_to_path = Some(entry.path());
-                            } else {
-                                self.open_file(&entry.path());
-                            }
+            for event in response.events {
+                match event {
+                    BrowserEvent::Hovered(_) => Self::play_hover_sound(&mut self.processes),
+                    BrowserEvent::Selected(paths) => {
+                        if let Some(pick) = &mut self.pick
+                            && let Some(path) = paths.last() {
+                            pick.picked(path);
                         }
                     }
-                });
+                    BrowserEvent::Opened(_) if self.pick.is_some() => self.pick_confirm(ui.ctx()),
+                    BrowserEvent::Opened(path) => self.open_file(ui.ctx(), &path),
+                    BrowserEvent::Navigated(result) => self.listed(result),
+                }
             }
-
-            if let Some(path) = navigate_to_path { self.navigate_to(path, true); }
+            if let Some(action) = entry_action { self.apply_entry_action(ui.ctx(), action); }
+            let margin = 16.0 * self.ui_scale;
 
             // Optional CRT scanlines overlay (more transparent, thicker, animated downward, faux glow)
             if self.show_scanlines {
@@ -751,8 +1898,62 @@ impl eframe::App for DataraApp {
             }
         });
         
-        // Auto-save settings when they change
-        self.save_settings();
+        if let Some(viewer) = &mut self.image_viewer {
+            viewer.show(ctx);
+            if !viewer.open {
+                self.image_viewer = None;
+            }
+        }
+        if let Some(markdown_view) = &mut self.markdown_view {
+            let link = markdown_view.show(ctx);
+            if !markdown_view.open {
+                self.markdown_view = None;
+            }
+            if let Some(path) = link {
+                if path.is_dir() {
+                    self.navigate_to(path, true);
+                } else if path.exists() {
+                    self.open_file(ctx, &path);
+                }
+            }
+        }
+        if let Some(hex_view) = &mut self.hex_view {
+            if hex_view.is_dirty() && ctx.input(|i| i.viewport().close_requested()) {
+                ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+                hex_view.request_close();
+                self.quit_after_hex_view = true;
+            }
+            hex_view.show(ctx);
+            if !hex_view.open {
+                self.hex_view = None;
+                if std::mem::take(&mut self.quit_after_hex_view) {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            } else if !hex_view.is_asking() {
+                // Cancelled; stay open
+                self.quit_after_hex_view = false;
+            }
+        }
+
+        if let Some(action) = self.notifications.show_toasts(ctx, bg) {
+            self.apply_notification_action(action);
+        }
+        self.choose_app_window(ctx);
+        self.compress_window(ctx);
+        self.extract_window(ctx);
+        self.rename_window(ctx);
+        self.delete_window(ctx);
+
+        self.sync_settings(ctx);
+        self.answer_navigate();
+        self.report_events();
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        let current = self.to_config();
+        if let Some(ConfigEvent::SaveFailed(err)) = self.config_store.flush(&current) {
+            eprintln!("{}", err);
+        }
     }
 }
 
@@ -771,15 +1972,111 @@ fn apply_retro_style(ctx: &egui::Context) {
     ctx.set_style(style);
 }
 
+/// Answers a control call with how its job ended.
+fn answer_with(reply: Option<Reply>, notification: &Notification) {
+    if let Some(reply) = reply {
+        reply.send(match notification.severity {
+            Severity::Success => Ok(Value::Null),
+            _ => Err(RpcError::failed(notification.message.clone())),
+        });
+    }
+}
+
+/// Serves the FileChooser portal until the process is killed.
+fn run_portal() -> ! {
+    let served = portal::FileChooser::current_exe()
+        .map_err(zbus::Error::from)
+        .and_then(|chooser| portal::serve(zbus::blocking::connection::Builder::session()?, chooser));
+    let _connection = match served {
+        Ok(connection) => connection,
+        Err(err) => {
+            eprintln!("datara: cannot serve {} on the session bus: {}", portal::BUS_NAME, err);
+            std::process::exit(1);
+        }
+    };
+    loop {
+        std::thread::park();
+    }
+}
+
+/// Hands the launch to a running Datara and exits, or returns the socket
+/// this instance answers on. Problems with the socket only cost the
+/// handoff; the window still opens.
+fn claim_instance(options: &LaunchOptions) -> Option<instance::Server> {
+    let socket = instance::socket_path()?;
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+    let request = OpenRequest {
+        paths: options.paths.iter().map(|path| cwd.join(path)).collect(),
+        select: options.select.as_ref().map(|file| cwd.join(file)),
+        cwd,
+    };
+    match instance::claim(&socket, &request) {
+        Ok(Claim::Primary(server)) => Some(server),
+        Ok(Claim::Forwarded) => std::process::exit(0),
+        Err(err) => {
+            eprintln!("datara: cannot reach the running instance at {}: {}", paths::display_path(&socket), err);
+            None
+        }
+    }
+}
+
 fn main() -> eframe::Result<()> {
-    let start_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let options = match cli::parse(std::env::args_os().skip(1)) {
+        Ok(cli::Command::Run(options)) => options,
+        Ok(cli::Command::Help) => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Ok(cli::Command::Version) => {
+            println!("datara {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        Ok(cli::Command::Portal) => run_portal(),
+        Err(err) => {
+            eprintln!("datara: {}\nTry 'datara --help' for more information.", err);
+            std::process::exit(2);
+        }
+    };
+    let picking = options.pick.clone();
+    let control_socket = options.control.clone();
+    let server = if picking.is_some() || options.new_instance { None } else { claim_instance(&options) };
+    let title = match &picking {
+        Some(pick) => format!("Datara — {}", pick.describe()),
+        None => "Datara".to_string(),
+    };
+    let pick_outcome = PickOutcome::default();
+    let app_outcome = pick_outcome.clone();
     let native_options = eframe::NativeOptions::default();
     eframe::run_native(
-        "Datara",
+        &title,
         native_options,
         Box::new(|cc| {
             apply_retro_style(&cc.egui_ctx);
-            Ok(Box::new(DataraApp::new(std::env::current_dir().unwrap_or(start_dir))))
+            let mut app = DataraApp::new(options, app_outcome);
+            let ctx = cc.egui_ctx.clone();
+            let wake = ctx.clone();
+            app.instance = server.map(|server| server.serve(move || wake.request_repaint()));
+            if let Some(socket) = control_socket {
+                match Control::listen(&socket, move || ctx.request_repaint()) {
+                    Ok(control) => app.control = Some(control),
+                    Err(err) => app.notifications.error(format!("Cannot serve the remote control on {}: {}", paths::display_path(&socket), err)),
+                }
+            }
+            Ok(Box::new(app))
         }),
-    )
+    )?;
+
+    if let Some(pick) = picking {
+        let picked = pick_outcome.lock().ok().and_then(|mut outcome| outcome.take());
+        match picked {
+            Some(paths) => {
+                if let Err(err) = pick::write_selection(&paths, pick.separator) {
+                    eprintln!("datara: {}", err);
+                    std::process::exit(2);
+                }
+            }
+            None => std::process::exit(1),
+        }
+    }
+    Ok(())
 }
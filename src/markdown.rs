@@ -0,0 +1,612 @@
+use eframe::egui;
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::paths;
+
+const MAX_IMAGE_SIDE: u32 = 1024;
+/// Inline images larger than this on disk, or wider or taller than
+/// `MAX_DECODED_SIDE`, are shown as their alt text instead.
+const MAX_IMAGE_FILE_SIZE: u64 = 32 * 1024 * 1024;
+const MAX_DECODED_SIDE: u32 = 16384;
+
+#[derive(Debug, Clone, Default)]
+struct Span {
+    text: String,
+    strong: bool,
+    emphasis: bool,
+    strike: bool,
+    code: bool,
+    link: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+enum Inline {
+    Span(Span),
+    Image { url: String, alt: String },
+    Break,
+}
+
+#[derive(Debug, Clone)]
+struct ListItem {
+    blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone)]
+enum Block {
+    Heading(u8, Vec<Inline>),
+    Paragraph(Vec<Inline>),
+    Code { lang: String, text: String },
+    List { start: Option<u64>, items: Vec<ListItem> },
+    Quote(Vec<Block>),
+    Table { header: Vec<Vec<Inline>>, rows: Vec<Vec<Vec<Inline>>> },
+    Rule,
+}
+
+/// Decodes a document's inline images on a worker thread, started with the
+/// first image; it exits when the document is dropped.
+struct ImageLoader {
+    jobs: Sender<(String, PathBuf)>,
+    done: Receiver<(String, Option<egui::ColorImage>)>,
+}
+
+impl ImageLoader {
+    fn new(ctx: &egui::Context) -> Self {
+        let (jobs, job_rx) = mpsc::channel::<(String, PathBuf)>();
+        let (done_tx, done) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            for (url, path) in job_rx {
+                if done_tx.send((url, decode_image(&path))).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+        });
+        Self { jobs, done }
+    }
+}
+
+/// Parsed Markdown file, rendered with egui widgets in the retro theme.
+pub struct MarkdownDocument {
+    blocks: Vec<Block>,
+    base_dir: PathBuf,
+    /// Inline images by URL; `None` while loading or when it failed to load.
+    images: HashMap<String, Option<egui::TextureHandle>>,
+    loader: Option<ImageLoader>,
+}
+
+impl MarkdownDocument {
+    /// `base_dir` is used to resolve relative links and inline images.
+    pub fn parse(source: &str, base_dir: &Path) -> Self {
+        let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS | Options::ENABLE_FOOTNOTES;
+        let mut events = Parser::new_ext(source, options).peekable();
+        let blocks = parse_blocks(&mut events);
+        Self { blocks, base_dir: base_dir.to_path_buf(), images: HashMap::new(), loader: None }
+    }
+
+    /// Draws the document; returns the target of a clicked relative link.
+    pub fn ui(&mut self, ui: &mut egui::Ui, base_size: f32) -> Option<PathBuf> {
+        self.poll_images(ui.ctx());
+        let mut clicked = None;
+        let blocks = std::mem::take(&mut self.blocks);
+        for (i, block) in blocks.iter().enumerate() {
+            ui.push_id(i, |ui| self.block_ui(ui, block, base_size, &mut clicked));
+        }
+        self.blocks = blocks;
+        clicked
+    }
+
+    fn block_ui(&mut self, ui: &mut egui::Ui, block: &Block, base_size: f32, clicked: &mut Option<PathBuf>) {
+        let green = egui::Color32::from_rgb(0, 255, 0);
+        let dim_green = egui::Color32::from_rgb(0, 120, 0);
+        match block {
+            Block::Heading(level, inlines) => {
+                let size = match level {
+                    1 => base_size * 1.6,
+                    2 => base_size * 1.35,
+                    3 => base_size * 1.15,
+                    _ => base_size,
+                };
+                ui.add_space(base_size * 0.4);
+                self.inlines_ui(ui, inlines, size, true, clicked);
+                if *level <= 2 {
+                    ui.separator();
+                }
+            }
+            Block::Paragraph(inlines) => {
+                self.inlines_ui(ui, inlines, base_size, false, clicked);
+                ui.add_space(base_size * 0.4);
+            }
+            Block::Code { lang, text } => {
+                egui::Frame::default()
+                    .fill(egui::Color32::from_rgb(0, 24, 0))
+                    .stroke(egui::Stroke::new(1.0, dim_green))
+                    .inner_margin(8.0)
+                    .show(ui, |ui| {
+                        if !lang.is_empty() {
+                            ui.label(egui::RichText::new(lang).monospace().size(base_size * 0.75).color(dim_green));
+                        }
+                        egui::ScrollArea::horizontal().show(ui, |ui| {
+                            ui.label(egui::RichText::new(text.trim_end_matches('\n')).monospace().size(base_size * 0.9));
+                        });
+                    });
+                ui.add_space(base_size * 0.4);
+            }
+            Block::List { start, items } => {
+                for (n, item) in items.iter().enumerate() {
+                    let marker = match start {
+                        Some(start) => format!("{}.", start + n as u64),
+                        None => "•".to_string(),
+                    };
+                    ui.horizontal_top(|ui| {
+                        ui.label(egui::RichText::new(marker).monospace().size(base_size).color(green));
+                        ui.vertical(|ui| {
+                            for (i, block) in item.blocks.iter().enumerate() {
+                                ui.push_id((n, i), |ui| self.block_ui(ui, block, base_size, clicked));
+                            }
+                        });
+                    });
+                }
+                ui.add_space(base_size * 0.4);
+            }
+            Block::Quote(blocks) => {
+                egui::Frame::default()
+                    .stroke(egui::Stroke::new(1.0, dim_green))
+                    .inner_margin(egui::Margin { left: 12.0, right: 8.0, top: 6.0, bottom: 6.0 })
+                    .show(ui, |ui| {
+                        for (i, block) in blocks.iter().enumerate() {
+                            ui.push_id(i, |ui| self.block_ui(ui, block, base_size, clicked));
+                        }
+                    });
+                ui.add_space(base_size * 0.4);
+            }
+            Block::Table { header, rows } => {
+                egui::Grid::new("md_table").striped(true).spacing(egui::vec2(16.0, 4.0)).show(ui, |ui| {
+                    for cell in header {
+                        self.inlines_ui(ui, cell, base_size, true, clicked);
+                    }
+                    ui.end_row();
+                    for row in rows {
+                        for cell in row {
+                            self.inlines_ui(ui, cell, base_size, false, clicked);
+                        }
+                        ui.end_row();
+                    }
+                });
+                ui.add_space(base_size * 0.4);
+            }
+            Block::Rule => {
+                ui.separator();
+            }
+        }
+    }
+
+    fn inlines_ui(&mut self, ui: &mut egui::Ui, inlines: &[Inline], size: f32, strong: bool, clicked: &mut Option<PathBuf>) {
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+            for inline in inlines {
+                match inline {
+                    Inline::Span(span) => {
+                        let mut text = egui::RichText::new(&span.text).size(size);
+                        if span.strong || strong { text = text.strong(); }
+                        if span.emphasis { text = text.italics(); }
+                        if span.strike { text = text.strikethrough(); }
+                        if span.code { text = text.monospace().background_color(egui::Color32::from_rgb(0, 32, 0)); }
+                        match &span.link {
+                            Some(url) if is_external(url) => {
+                                ui.hyperlink_to(text, url);
+                            }
+                            Some(url) => {
+                                if ui.link(text).on_hover_text(url).clicked() {
+                                    *clicked = Some(self.resolve(url));
+                                }
+                            }
+                            None => {
+                                ui.label(text);
+                            }
+                        }
+                    }
+                    Inline::Image { url, alt } => {
+                        match self.image(ui.ctx(), url) {
+                            Some(texture) => {
+                                let size = texture.size_vec2();
+                                let scale = (ui.available_width() / size.x).min(1.0);
+                                ui.image((texture.id(), size * scale)).on_hover_text(alt);
+                            }
+                            None => {
+                                ui.label(egui::RichText::new(format!("[{}]", alt)).size(size).italics());
+                            }
+                        }
+                    }
+                    Inline::Break => {
+                        ui.end_row();
+                    }
+                }
+            }
+        });
+    }
+
+    fn resolve(&self, url: &str) -> PathBuf {
+        let url = url.split('#').next().unwrap_or(url);
+        self.base_dir.join(url)
+    }
+
+    /// The texture of an inline image, asking the loader for it the first
+    /// time; external images are not fetched.
+    fn image(&mut self, ctx: &egui::Context, url: &str) -> Option<egui::TextureHandle> {
+        if !self.images.contains_key(url) {
+            self.images.insert(url.to_string(), None);
+            if !is_external(url) {
+                let path = self.resolve(url);
+                let loader = self.loader.get_or_insert_with(|| ImageLoader::new(ctx));
+                let _ = loader.jobs.send((url.to_string(), path));
+            }
+        }
+        self.images.get(url).cloned().flatten()
+    }
+
+    fn poll_images(&mut self, ctx: &egui::Context) {
+        let Some(loader) = &self.loader else { return };
+        for (url, color_image) in loader.done.try_iter() {
+            let texture = color_image.map(|color_image| ctx.load_texture(format!("md_image:{}", url), color_image, egui::TextureOptions::LINEAR));
+            self.images.insert(url, texture);
+        }
+    }
+}
+
+/// Reads and downscales an inline image, refusing files and dimensions too
+/// large to decode in reasonable time and memory.
+fn decode_image(path: &Path) -> Option<egui::ColorImage> {
+    if std::fs::metadata(path).ok()?.len() > MAX_IMAGE_FILE_SIZE {
+        return None;
+    }
+    let mut reader = image::io::Reader::open(path).ok()?.with_guessed_format().ok()?;
+    let mut limits = image::io::Limits::default();
+    limits.max_image_width = Some(MAX_DECODED_SIDE);
+    limits.max_image_height = Some(MAX_DECODED_SIDE);
+    reader.limits(limits);
+    let image = reader.decode().ok()?;
+    let image = if image.width() > MAX_IMAGE_SIDE || image.height() > MAX_IMAGE_SIDE {
+        image.thumbnail(MAX_IMAGE_SIDE, MAX_IMAGE_SIDE)
+    } else {
+        image
+    };
+    let rgba_image = image.to_rgba8();
+    let size = [rgba_image.width() as usize, rgba_image.height() as usize];
+    Some(egui::ColorImage::from_rgba_unmultiplied(size, rgba_image.as_raw()))
+}
+
+fn is_external(url: &str) -> bool {
+    url.contains("://") || url.starts_with("mailto:")
+}
+
+type Events<'a> = Peekable<Parser<'a>>;
+
+fn is_inline(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Text(_)
+            | Event::Code(_)
+            | Event::InlineMath(_)
+            | Event::InlineHtml(_)
+            | Event::FootnoteReference(_)
+            | Event::SoftBreak
+            | Event::HardBreak
+            | Event::TaskListMarker(_)
+            | Event::Start(Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Superscript | Tag::Subscript | Tag::Link { .. } | Tag::Image { .. })
+    )
+}
+
+/// Collects blocks until the end tag of the enclosing container (which is consumed).
+fn parse_blocks(events: &mut Events) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    while let Some(event) = events.peek() {
+        if is_inline(event) {
+            // Tight list items carry their text without a paragraph
+            let mut inlines = Vec::new();
+            parse_inlines(events, &Span::default(), &mut inlines);
+            blocks.push(Block::Paragraph(inlines));
+            continue;
+        }
+        let Some(event) = events.next() else { break };
+        match event {
+            Event::Start(Tag::Paragraph) => {
+                let mut inlines = Vec::new();
+                parse_inlines(events, &Span::default(), &mut inlines);
+                events.next();
+                blocks.push(Block::Paragraph(inlines));
+            }
+            Event::Start(Tag::Heading { level, .. }) => {
+                let mut inlines = Vec::new();
+                parse_inlines(events, &Span::default(), &mut inlines);
+                events.next();
+                blocks.push(Block::Heading(level as u8, inlines));
+            }
+            Event::Start(Tag::BlockQuote(_)) => blocks.push(Block::Quote(parse_blocks(events))),
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(lang) => lang.to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                blocks.push(Block::Code { lang, text: collect_text(events) });
+            }
+            Event::Start(Tag::HtmlBlock) => blocks.push(Block::Code { lang: "html".to_string(), text: collect_text(events) }),
+            Event::Start(Tag::List(start)) => {
+                let mut items = Vec::new();
+                while let Some(event) = events.next() {
+                    match event {
+                        Event::Start(Tag::Item) => items.push(ListItem { blocks: parse_blocks(events) }),
+                        Event::End(TagEnd::List(_)) => break,
+                        _ => {}
+                    }
+                }
+                blocks.push(Block::List { start, items });
+            }
+            Event::Start(Tag::Table(_)) => blocks.push(parse_table(events)),
+            Event::Rule => blocks.push(Block::Rule),
+            Event::DisplayMath(math) => blocks.push(Block::Code { lang: "math".to_string(), text: math.to_string() }),
+            // Footnote definitions, definition lists, metadata: render their content inline
+            Event::Start(_) => blocks.extend(parse_blocks(events)),
+            Event::End(_) => break,
+            _ => {}
+        }
+    }
+    blocks
+}
+
+/// Collects inline content, stopping before the first block-level event.
+fn parse_inlines(events: &mut Events, style: &Span, out: &mut Vec<Inline>) {
+    while let Some(event) = events.peek() {
+        if !is_inline(event) {
+            // Inline containers end here; their end tag belongs to us
+            if let Event::End(TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Superscript | TagEnd::Subscript | TagEnd::Link) = event {
+                events.next();
+            }
+            return;
+        }
+        let Some(event) = events.next() else { return };
+        let span = |text: &str| Span { text: text.to_string(), ..style.clone() };
+        match event {
+            Event::Text(text) => out.push(Inline::Span(span(&text))),
+            Event::Code(text) | Event::InlineMath(text) | Event::InlineHtml(text) => {
+                out.push(Inline::Span(Span { code: true, ..span(&text) }));
+            }
+            Event::FootnoteReference(name) => out.push(Inline::Span(span(&format!("[^{}]", name)))),
+            Event::SoftBreak => out.push(Inline::Span(span(" "))),
+            Event::HardBreak => out.push(Inline::Break),
+            Event::TaskListMarker(checked) => out.push(Inline::Span(span(if checked { "[x] " } else { "[ ] " }))),
+            Event::Start(Tag::Emphasis) => parse_inlines(events, &Span { emphasis: true, ..style.clone() }, out),
+            Event::Start(Tag::Strong) => parse_inlines(events, &Span { strong: true, ..style.clone() }, out),
+            Event::Start(Tag::Strikethrough) => parse_inlines(events, &Span { strike: true, ..style.clone() }, out),
+            Event::Start(Tag::Superscript | Tag::Subscript) => parse_inlines(events, style, out),
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                parse_inlines(events, &Span { link: Some(dest_url.to_string()), ..style.clone() }, out);
+            }
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                let alt = collect_text(events);
+                out.push(Inline::Image { url: dest_url.to_string(), alt });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Concatenates text up to the matching end tag (which is consumed).
+fn collect_text(events: &mut Events) -> String {
+    let mut text = String::new();
+    let mut depth = 0;
+    for event in events.by_ref() {
+        match event {
+            Event::Text(t) | Event::Code(t) | Event::Html(t) | Event::InlineHtml(t) => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            Event::Start(_) => depth += 1,
+            Event::End(_) if depth == 0 => break,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+    }
+    text
+}
+
+fn parse_table(events: &mut Events) -> Block {
+    let mut header = Vec::new();
+    let mut rows = Vec::new();
+    let mut row: Vec<Vec<Inline>> = Vec::new();
+    while let Some(event) = events.next() {
+        match event {
+            Event::Start(Tag::TableCell) => {
+                let mut inlines = Vec::new();
+                parse_inlines(events, &Span::default(), &mut inlines);
+                events.next();
+                row.push(inlines);
+            }
+            Event::End(TagEnd::TableHead) => header = std::mem::take(&mut row),
+            Event::End(TagEnd::TableRow) => rows.push(std::mem::take(&mut row)),
+            Event::End(TagEnd::Table) => break,
+            _ => {}
+        }
+    }
+    Block::Table { header, rows }
+}
+
+/// Window showing a Markdown file rendered, with a toggle to its source.
+pub struct MarkdownView {
+    pub open: bool,
+    path: PathBuf,
+    source: String,
+    document: MarkdownDocument,
+    show_source: bool,
+    error: Option<String>,
+}
+
+impl MarkdownView {
+    pub fn new(path: &Path) -> Self {
+        let (source, error) = match std::fs::read_to_string(path) {
+            Ok(source) => (source, None),
+            Err(err) => (String::new(), Some(format!("Failed to read file: {}", err))),
+        };
+        let base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let document = MarkdownDocument::parse(&source, &base_dir);
+        Self { open: true, path: path.to_path_buf(), source, document, show_source: false, error }
+    }
+
+    /// Returns the target of a clicked relative link.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<PathBuf> {
+        let title = paths::display_file_name(&self.path);
+        let mut clicked = None;
+        let mut open = self.open;
+        egui::Window::new(format!("Markdown — {}", title))
+            .id(egui::Id::new("markdown_view"))
+            .open(&mut open)
+            .default_size(egui::vec2(760.0, 640.0))
+            .resizable(true)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                if let Some(err) = &self.error {
+                    ui.colored_label(egui::Color32::RED, err);
+                    return;
+                }
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.show_source, false, "Rendered");
+                    ui.selectable_value(&mut self.show_source, true, "Source");
+                });
+                ui.separator();
+                egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+                    if self.show_source {
+                        ui.add(egui::TextEdit::multiline(&mut self.source.as_str()).code_editor().desired_width(f32::INFINITY));
+                    } else {
+                        clicked = self.document.ui(ui, 15.0);
+                    }
+                });
+            });
+        self.open = open;
+        clicked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Vec<Block> {
+        MarkdownDocument::parse(source, Path::new("/docs")).blocks
+    }
+
+    fn text(inlines: &[Inline]) -> String {
+        inlines
+            .iter()
+            .map(|inline| match inline {
+                Inline::Span(span) => span.text.clone(),
+                Inline::Image { alt, .. } => format!("![{}]", alt),
+                Inline::Break => "\n".to_string(),
+            })
+            .collect()
+    }
+
+    fn paragraph(block: &Block) -> String {
+        match block {
+            Block::Paragraph(inlines) => text(inlines),
+            other => panic!("expected a paragraph, got {:?}", other),
+        }
+    }
+
+    fn list_items(block: &Block) -> &[ListItem] {
+        match block {
+            Block::List { items, .. } => items,
+            other => panic!("expected a list, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn tight_list_items_hold_their_text_and_sublists() {
+        let blocks = parse("- one\n- two\n  - nested\n");
+        assert_eq!(blocks.len(), 1);
+        let items = list_items(&blocks[0]);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].blocks.len(), 1);
+        assert_eq!(paragraph(&items[0].blocks[0]), "one");
+        assert_eq!(paragraph(&items[1].blocks[0]), "two");
+        assert_eq!(paragraph(&list_items(&items[1].blocks[1])[0].blocks[0]), "nested");
+    }
+
+    #[test]
+    fn loose_list_items_keep_every_paragraph() {
+        let blocks = parse("3. first\n\n   more\n\n4. second\n\nafter\n");
+        assert!(matches!(blocks[0], Block::List { start: Some(3), .. }));
+        let items = list_items(&blocks[0]);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].blocks.iter().map(paragraph).collect::<Vec<_>>(), ["first", "more"]);
+        assert_eq!(paragraph(&items[1].blocks[0]), "second");
+        assert_eq!(paragraph(&blocks[1]), "after");
+    }
+
+    #[test]
+    fn quotes_nest() {
+        let blocks = parse("> outer\n>\n> > inner\n\nafter\n");
+        assert_eq!(blocks.len(), 2);
+        let Block::Quote(outer) = &blocks[0] else { panic!("expected a quote, got {:?}", blocks[0]) };
+        assert_eq!(paragraph(&outer[0]), "outer");
+        let Block::Quote(inner) = &outer[1] else { panic!("expected a nested quote, got {:?}", outer[1]) };
+        assert_eq!(paragraph(&inner[0]), "inner");
+        assert_eq!(paragraph(&blocks[1]), "after");
+    }
+
+    #[test]
+    fn tables_split_header_and_rows() {
+        let blocks = parse("| Name | Size |\n|------|-----:|\n| `a` | **1** |\n| b | 2 |\n\nafter\n");
+        let Block::Table { header, rows } = &blocks[0] else { panic!("expected a table, got {:?}", blocks[0]) };
+        assert_eq!(header.iter().map(|cell| text(cell)).collect::<Vec<_>>(), ["Name", "Size"]);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].iter().map(|cell| text(cell)).collect::<Vec<_>>(), ["a", "1"]);
+        assert!(matches!(&rows[0][0][0], Inline::Span(Span { code: true, .. })));
+        assert!(matches!(&rows[0][1][0], Inline::Span(Span { strong: true, .. })));
+        assert_eq!(paragraph(&blocks[1]), "after");
+    }
+
+    #[test]
+    fn inline_styles_end_with_their_container() {
+        let blocks = parse("# Title *em*\n\nplain **bold _both_** [link](other.md#part) ![logo](img/logo.png) end\n");
+        let Block::Heading(1, heading) = &blocks[0] else { panic!("expected a heading, got {:?}", blocks[0]) };
+        assert_eq!(text(heading), "Title em");
+        let Block::Paragraph(inlines) = &blocks[1] else { panic!("expected a paragraph, got {:?}", blocks[1]) };
+        let spans: Vec<&Span> = inlines.iter().filter_map(|inline| match inline {
+            Inline::Span(span) => Some(span),
+            _ => None,
+        }).collect();
+        let both = spans.iter().find(|span| span.text == "both").unwrap();
+        assert!(both.strong && both.emphasis);
+        let link = spans.iter().find(|span| span.text == "link").unwrap();
+        assert_eq!(link.link.as_deref(), Some("other.md#part"));
+        assert!(spans.last().is_some_and(|span| span.text == " end" && !span.strong && span.link.is_none()));
+        assert!(inlines.iter().any(|inline| matches!(inline, Inline::Image { url, alt } if url == "img/logo.png" && alt == "logo")));
+    }
+
+    #[test]
+    fn relative_links_resolve_against_the_base_dir() {
+        let document = MarkdownDocument::parse("", Path::new("/docs"));
+        assert_eq!(document.resolve("guide/setup.md#install"), Path::new("/docs/guide/setup.md"));
+        assert_eq!(document.resolve("../README.md"), Path::new("/docs/../README.md"));
+        assert!(is_external("https://example.com/a.md"));
+        assert!(is_external("mailto:someone@example.com"));
+        assert!(!is_external("notes.md"));
+    }
+
+    #[test]
+    fn inline_images_are_downscaled_and_capped() {
+        let dir = std::env::temp_dir().join(format!("datara-markdown-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let wide = dir.join("wide.png");
+        image::RgbaImage::new(2048, 16).save(&wide).unwrap();
+        let decoded = decode_image(&wide).unwrap();
+        assert_eq!(decoded.size, [1024, 8]);
+        let huge = dir.join("huge.png");
+        image::GrayImage::new(MAX_DECODED_SIDE + 1, 1).save(&huge).unwrap();
+        assert!(decode_image(&huge).is_none());
+        assert!(decode_image(&dir.join("missing.png")).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,291 @@
+use eframe::egui;
+use std::path::PathBuf;
+
+use crate::paths;
+
+/// Upper bound on the log; the oldest entries are dropped first.
+const MAX_LOG_ENTRIES: usize = 500;
+const MAX_TOASTS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Success,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn label(self) -> &'static str {
+        match self {
+            Severity::Success => "OK",
+            Severity::Warning => "WARN",
+            Severity::Error => "ERROR",
+        }
+    }
+
+    fn color(self) -> egui::Color32 {
+        match self {
+            Severity::Success => egui::Color32::from_rgb(0, 255, 0),
+            Severity::Warning => egui::Color32::from_rgb(255, 200, 0),
+            Severity::Error => egui::Color32::RED,
+        }
+    }
+
+    /// Seconds a toast stays on screen.
+    fn toast_duration(self) -> f64 {
+        match self {
+            Severity::Success => 3.0,
+            Severity::Warning => 6.0,
+            Severity::Error => 10.0,
+        }
+    }
+}
+
+/// Follow-up offered on a notification.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotificationAction {
+    /// Pick a different application for a file that failed to open.
+    ChooseApplication(PathBuf),
+}
+
+impl NotificationAction {
+    fn label(&self) -> &'static str {
+        match self {
+            NotificationAction::ChooseApplication(_) => "Choose another application…",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    id: u64,
+    pub severity: Severity,
+    pub message: String,
+    pub path: Option<PathBuf>,
+    /// Extra text such as the stderr of a failed process.
+    pub detail: Option<String>,
+    pub action: Option<NotificationAction>,
+    pub time: chrono::DateTime<chrono::Local>,
+}
+
+impl Notification {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            id: 0,
+            severity,
+            message: message.into(),
+            path: None,
+            detail: None,
+            action: None,
+            time: chrono::Local::now(),
+        }
+    }
+
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        let detail = detail.into();
+        if !detail.is_empty() {
+            self.detail = Some(detail);
+        }
+        self
+    }
+
+    pub fn with_action(mut self, action: NotificationAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    /// Plain text form used for the clipboard.
+    fn to_text(&self) -> String {
+        let mut text = format!("[{}] {} {}", self.time.format("%Y-%m-%d %H:%M:%S"), self.severity.label(), self.message);
+        if let Some(path) = &self.path {
+            text.push_str(&format!("\n  {}", paths::display_path(path)));
+        }
+        if let Some(detail) = &self.detail {
+            for line in detail.lines() {
+                text.push_str(&format!("\n  | {}", line));
+            }
+        }
+        text
+    }
+}
+
+struct Toast {
+    id: u64,
+    /// Set when the toast is first drawn.
+    shown_at: Option<f64>,
+}
+
+/// Transient toasts plus a persistent log of every notification.
+pub struct Notifications {
+    log: Vec<Notification>,
+    toasts: Vec<Toast>,
+    next_id: u64,
+    pub show_log: bool,
+    errors_only: bool,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self { log: Vec::new(), toasts: Vec::new(), next_id: 1, show_log: false, errors_only: true }
+    }
+}
+
+impl Notifications {
+    pub fn push(&mut self, mut notification: Notification) {
+        notification.id = self.next_id;
+        self.next_id += 1;
+        self.toasts.push(Toast { id: notification.id, shown_at: None });
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+        self.log.push(notification);
+        if self.log.len() > MAX_LOG_ENTRIES {
+            self.log.remove(0);
+        }
+    }
+
+    pub fn success(&mut self, message: impl Into<String>) {
+        self.push(Notification::new(Severity::Success, message));
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(Notification::new(Severity::Error, message));
+    }
+
+    /// Marks the log's current end, for `errors_since`.
+    pub fn mark(&self) -> u64 {
+        self.next_id
+    }
+
+    /// Errors pushed after `mark` was taken, oldest first.
+    pub fn errors_since(&self, mark: u64) -> impl Iterator<Item = &Notification> {
+        self.log.iter().filter(move |n| n.id >= mark && n.severity == Severity::Error)
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.log.iter().filter(|n| n.severity == Severity::Error).count()
+    }
+
+    /// Draws the toasts in the bottom right corner; returns a clicked action.
+    pub fn show_toasts(&mut self, ctx: &egui::Context, bg: egui::Color32) -> Option<NotificationAction> {
+        let now = ctx.input(|i| i.time);
+        let mut action = None;
+        let mut dismissed: Vec<u64> = Vec::new();
+        let mut next_expiry = f64::INFINITY;
+
+        let mut offset = -10.0;
+        for toast in self.toasts.iter_mut().rev() {
+            let Some(notification) = self.log.iter().find(|n| n.id == toast.id) else {
+                dismissed.push(toast.id);
+                continue;
+            };
+            let shown_at = *toast.shown_at.get_or_insert(now);
+            let remaining = notification.severity.toast_duration() - (now - shown_at);
+            if remaining <= 0.0 {
+                dismissed.push(toast.id);
+                continue;
+            }
+            next_expiry = next_expiry.min(remaining);
+
+            let response = egui::Area::new(egui::Id::new(("toast", toast.id)))
+                .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, offset))
+                .order(egui::Order::Foreground)
+                .show(ctx, |ui| {
+                    egui::Frame::none()
+                        .fill(bg)
+                        .stroke(egui::Stroke::new(1.0, notification.severity.color()))
+                        .inner_margin(8.0)
+                        .show(ui, |ui| {
+                            ui.set_max_width(360.0);
+                            ui.horizontal(|ui| {
+                                ui.colored_label(notification.severity.color(), notification.severity.label());
+                                ui.label(egui::RichText::new(notification.message.as_str()).monospace());
+                                if ui.small_button("✖").clicked() {
+                                    dismissed.push(toast.id);
+                                }
+                            });
+                            if let Some(a) = &notification.action
+                                && ui.button(a.label()).clicked() {
+                                action = Some(a.clone());
+                                dismissed.push(toast.id);
+                            }
+                        });
+                });
+            offset -= response.response.rect.height() + 6.0;
+        }
+        self.toasts.retain(|toast| !dismissed.contains(&toast.id));
+        if next_expiry.is_finite() {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(next_expiry));
+        }
+        action
+    }
+
+    /// Bottom panel listing the log; returns a clicked action.
+    pub fn log_panel(&mut self, ctx: &egui::Context, bg: egui::Color32) -> Option<NotificationAction> {
+        let mut action = None;
+        egui::TopBottomPanel::bottom("notification_log")
+            .frame(egui::Frame::default().fill(bg).inner_margin(8.0))
+            .resizable(true)
+            .default_height(180.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong("Log");
+                    ui.checkbox(&mut self.errors_only, "Errors only");
+                    if ui.button("Copy All").clicked() {
+                        let text: Vec<String> = self.visible().map(Notification::to_text).collect();
+                        ui.ctx().copy_text(text.join("\n"));
+                    }
+                    if ui.button("Clear").clicked() {
+                        self.log.clear();
+                        self.toasts.clear();
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("✖").clicked() {
+                            self.show_log = false;
+                        }
+                    });
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false; 2])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                    if self.visible().next().is_none() {
+                        ui.label("Nothing logged");
+                    }
+                    for notification in self.visible() {
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new(notification.time.format("%H:%M:%S").to_string()).monospace().small());
+                            ui.colored_label(notification.severity.color(), notification.severity.label());
+                            ui.label(egui::RichText::new(notification.message.as_str()).monospace());
+                            if ui.small_button("Copy").clicked() {
+                                ui.ctx().copy_text(notification.to_text());
+                            }
+                            if let Some(a) = &notification.action
+                                && ui.small_button(a.label()).clicked() {
+                                action = Some(a.clone());
+                            }
+                        });
+                        if let Some(path) = &notification.path {
+                            ui.label(egui::RichText::new(paths::display_path(path)).monospace().small());
+                        }
+                        if let Some(detail) = &notification.detail {
+                            egui::CollapsingHeader::new("details").id_source(("log_detail", notification.id)).show(ui, |ui| {
+                                ui.label(egui::RichText::new(detail.as_str()).monospace().small());
+                            });
+                        }
+                    }
+                });
+            });
+        action
+    }
+
+    fn visible(&self) -> impl Iterator<Item = &Notification> {
+        self.log.iter().filter(|n| !self.errors_only || n.severity == Severity::Error)
+    }
+}
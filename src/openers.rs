@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::path::Path;
+
+use crate::file_type;
+
+/// Command prefix for Datara's own viewers, e.g. `datara:image`.
+const BUILTIN_PREFIX: &str = "datara:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchKind {
+    #[serde(rename = "ext")]
+    Extension,
+    #[serde(rename = "glob")]
+    Glob,
+    #[serde(rename = "mime")]
+    Mime,
+}
+
+impl MatchKind {
+    pub const ALL: [MatchKind; 3] = [MatchKind::Extension, MatchKind::Glob, MatchKind::Mime];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MatchKind::Extension => "Extension",
+            MatchKind::Glob => "Glob",
+            MatchKind::Mime => "MIME",
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            MatchKind::Extension => "ext",
+            MatchKind::Glob => "glob",
+            MatchKind::Mime => "mime",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        MatchKind::ALL.into_iter().find(|kind| kind.key() == key)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Builtin {
+    ImageViewer,
+    HexView,
+    Markdown,
+    /// The default application from `mimeapps.list`, else `xdg-open`.
+    SystemDefault,
+}
+
+impl Builtin {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "image" => Some(Builtin::ImageViewer),
+            "hex" => Some(Builtin::HexView),
+            "markdown" => Some(Builtin::Markdown),
+            "default" => Some(Builtin::SystemDefault),
+            _ => None,
+        }
+    }
+}
+
+pub enum OpenAction {
+    Builtin(Builtin),
+    Spawn { program: OsString, args: Vec<OsString> },
+}
+
+/// One row of the opener table: files matching `pattern` are opened with `command`.
+///
+/// `command` is a template where `%f` is the file, `%F` the list of files,
+/// `%d` the containing directory and `%%` a literal percent sign.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpenerRule {
+    pub kind: MatchKind,
+    /// Comma separated extensions, a glob on the file name, or comma separated
+    /// MIME types (`image/*` allowed) detected from the file content.
+    pub pattern: String,
+    pub command: String,
+    #[serde(default)]
+    pub terminal: bool,
+}
+
+impl OpenerRule {
+    fn new(kind: MatchKind, pattern: &str, command: &str, terminal: bool) -> Self {
+        Self { kind, pattern: pattern.to_string(), command: command.to_string(), terminal }
+    }
+
+    /// Whether the rule applies to `path`, whose content was detected as
+    /// `mime`.
+    pub fn matches(&self, path: &Path, mime: &str) -> bool {
+        match self.kind {
+            MatchKind::Extension => {
+                let Some(extension) = path.extension().and_then(|ext| ext.to_str()) else {
+                    return false;
+                };
+                self.pattern.split(',').any(|ext| ext.trim().trim_start_matches('.').eq_ignore_ascii_case(extension))
+            }
+            MatchKind::Glob => {
+                let Some(name) = path.file_name() else { return false };
+                glob::Pattern::new(self.pattern.trim())
+                    .map(|pattern| pattern.matches(&name.to_string_lossy()))
+                    .unwrap_or(false)
+            }
+            MatchKind::Mime => {
+                self.pattern.split(',').any(|pattern| mime_matches(pattern.trim(), mime))
+            }
+        }
+    }
+
+    /// Why the rule can't be used, if anything.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.pattern.trim().is_empty() {
+            return Err("Pattern is empty".to_string());
+        }
+        if self.kind == MatchKind::Glob
+            && let Err(err) = glob::Pattern::new(self.pattern.trim()) {
+            return Err(format!("Invalid glob: {}", err));
+        }
+        if self.kind == MatchKind::Mime && self.pattern.split(',').any(|mime| !mime.contains('/')) {
+            return Err("MIME type must look like type/subtype".to_string());
+        }
+        let command = self.command.trim();
+        if let Some(name) = command.strip_prefix(BUILTIN_PREFIX) {
+            if Builtin::from_name(name).is_none() {
+                return Err(format!("Unknown built-in viewer '{}'", name));
+            }
+        } else if split_command(command).is_empty() {
+            return Err("Command is empty".to_string());
+        }
+        Ok(())
+    }
+
+    /// Parses the `kind|pattern|terminal|command` lines of the legacy settings file.
+    pub fn from_config(line: &str) -> Result<Self, String> {
+        let mut parts = line.splitn(4, '|');
+        let (Some(kind), Some(pattern), Some(terminal), Some(command)) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return Err(format!("Malformed opener rule '{}'", line));
+        };
+        let kind = MatchKind::from_key(kind).ok_or_else(|| format!("Unknown match kind '{}'", kind))?;
+        let rule = Self::new(kind, pattern, command, terminal == "terminal");
+        rule.validate()?;
+        Ok(rule)
+    }
+
+    /// Builds what to run for `path`, wrapping the command in `terminal_command` if requested.
+    pub fn action(&self, path: &Path, terminal_command: &str) -> Option<OpenAction> {
+        let command = self.command.trim();
+        if let Some(name) = command.strip_prefix(BUILTIN_PREFIX) {
+            return Builtin::from_name(name).map(OpenAction::Builtin);
+        }
+        let mut argv: Vec<OsString> = Vec::new();
+        if self.terminal {
+            argv.extend(split_command(terminal_command).into_iter().map(OsString::from));
+        }
+        argv.extend(split_command(command).iter().map(|word| expand_placeholders(word, path)));
+        let mut argv = argv.into_iter();
+        let program = argv.next()?;
+        Some(OpenAction::Spawn { program, args: argv.collect() })
+    }
+}
+
+/// Content comes first, so a misnamed file opens by what it holds; the
+/// extension rules catch the rest, like files that can't be read.
+pub fn default_rules() -> Vec<OpenerRule> {
+    vec![
+        OpenerRule::new(MatchKind::Mime, "text/markdown", "datara:markdown", false),
+        OpenerRule::new(MatchKind::Mime, "text/*,application/x-shellscript,application/x-perl,application/x-ruby,application/x-php,application/json,application/xml", "vim %f", true),
+        OpenerRule::new(MatchKind::Mime, "image/png,image/jpeg,image/gif,image/bmp,image/webp,image/tiff,image/vnd.microsoft.icon", "datara:image", false),
+        OpenerRule::new(MatchKind::Mime, "video/*,audio/*", "mpv %f", false),
+        OpenerRule::new(MatchKind::Mime, "application/pdf,image/svg+xml", "firefox %f", false),
+        OpenerRule::new(MatchKind::Mime, "application/octet-stream,application/x-executable,application/x-sharedlib", "datara:hex", false),
+        // Extension fallbacks
+        OpenerRule::new(MatchKind::Extension, "md,markdown", "datara:markdown", false),
+        OpenerRule::new(MatchKind::Extension, "txt,rs,py,js,html,css,json,xml,yml,yaml,toml,ini,cfg,conf,log,c,cpp,h,hpp,java,go,php,rb,sh,bash,zsh,fish", "vim %f", true),
+        OpenerRule::new(MatchKind::Extension, "mp4,avi,mkv,mov,wmv,flv,webm,m4v,3gp,ogv,mpeg,mpg", "mpv %f", false),
+        OpenerRule::new(MatchKind::Extension, "jpg,jpeg,png,gif,bmp,webp,tiff,ico", "datara:image", false),
+        OpenerRule::new(MatchKind::Extension, "pdf,svg", "firefox %f", false),
+        OpenerRule::new(MatchKind::Extension, "mp3,wav,flac,ogg,aac,m4a,wma", "mpv %f", false),
+        OpenerRule::new(MatchKind::Extension, "bin,img,rom,fw,dat,elf,raw,dump", "datara:hex", false),
+        OpenerRule::new(MatchKind::Glob, "*", "datara:default", false),
+    ]
+}
+
+/// The first usable rule for `path`; the file is sniffed once for all the
+/// MIME rules.
+pub fn find_rule<'a>(rules: &'a [OpenerRule], path: &Path) -> Option<&'a OpenerRule> {
+    let mime = file_type::detect(path).mime;
+    rules.iter().find(|rule| rule.validate().is_ok() && rule.matches(path, mime))
+}
+
+fn mime_matches(pattern: &str, mime: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(major) => mime.split('/').next() == Some(major),
+        None => pattern.eq_ignore_ascii_case(mime),
+    }
+}
+
+fn expand_placeholders(word: &str, path: &Path) -> OsString {
+    let mut out = OsString::new();
+    let mut chars = word.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c.encode_utf8(&mut [0; 4]));
+            continue;
+        }
+        match chars.next() {
+            Some('f') | Some('F') => out.push(path.as_os_str()),
+            Some('d') => out.push(path.parent().unwrap_or(Path::new("/")).as_os_str()),
+            Some('%') => out.push("%"),
+            Some(other) => {
+                out.push("%");
+                out.push(other.encode_utf8(&mut [0; 4]));
+            }
+            None => out.push("%"),
+        }
+    }
+    out
+}
+
+/// Splits a command line on whitespace, honouring single and double quotes.
+pub fn split_command(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, '\\') => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                    in_word = true;
+                }
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builtin(path: &Path) -> Option<Builtin> {
+        let rules = default_rules();
+        match find_rule(&rules, path)?.action(path, "xterm -e")? {
+            OpenAction::Builtin(builtin) => Some(builtin),
+            OpenAction::Spawn { .. } => None,
+        }
+    }
+
+    #[test]
+    fn content_wins_over_the_extension() {
+        let dir = std::env::temp_dir().join(format!("datara-openers-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("photo.txt"), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+        std::fs::write(dir.join("build.jpg"), b"#!/bin/sh\nmake\n").unwrap();
+        std::fs::write(dir.join("README"), b"plain words\n").unwrap();
+        std::fs::write(dir.join("logo.svg"), b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>").unwrap();
+
+        assert_eq!(builtin(&dir.join("photo.txt")), Some(Builtin::ImageViewer));
+        // A script goes to the editor, not the image viewer
+        assert_eq!(builtin(&dir.join("build.jpg")), None);
+        // Extensionless text goes to the editor rather than the hex viewer
+        assert!(find_rule(&default_rules(), &dir.join("README")).is_some_and(|rule| rule.terminal && rule.command == "vim %f"));
+        // The image viewer can't decode vector images
+        assert!(matches!(find_rule(&default_rules(), &dir.join("logo.svg")).unwrap().action(&dir.join("logo.svg"), ""), Some(OpenAction::Spawn { .. })));
+        // Unreadable files fall back to their extension
+        assert_eq!(builtin(&dir.join("missing.md")), Some(Builtin::Markdown));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Lossless-looking display form of an OS string: bytes that aren't valid
+/// UTF-8 are shown as `\xNN` escapes instead of being replaced by U+FFFD,
+/// so distinct names stay distinct on screen.
+pub fn display(name: &OsStr) -> String {
+    let bytes = name.as_encoded_bytes();
+    let mut out = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        out.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            out.push_str(&format!("\\x{:02X}", byte));
+        }
+    }
+    out
+}
+
+pub fn display_path(path: &Path) -> String {
+    display(path.as_os_str())
+}
+
+/// Display form of the last path component, or of the whole path for `/`.
+pub fn display_file_name(path: &Path) -> String {
+    display(path.file_name().unwrap_or(path.as_os_str()))
+}
+
+/// Whether `display` had to escape anything.
+pub fn needs_escaping(name: &OsStr) -> bool {
+    name.to_str().is_none()
+}
+
+/// Whether a typed name names one new entry in the current folder: not
+/// empty, not `.` or `..`, and without a `/` that would lead elsewhere.
+pub fn is_plain_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains('/')
+}
+
+/// Dotfiles, regardless of whether the rest of the name is valid UTF-8.
+pub fn is_hidden(name: &OsStr) -> bool {
+    name.as_encoded_bytes().first() == Some(&b'.')
+}
+
+/// `$XDG_DOWNLOAD_DIR`, falling back to `~/Downloads`.
+pub fn downloads_dir() -> Option<PathBuf> {
+    match std::env::var_os("XDG_DOWNLOAD_DIR") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => Some(PathBuf::from(std::env::var_os("HOME")?).join("Downloads")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openers::{self, OpenAction};
+    use std::ffi::OsString;
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+    use std::path::PathBuf;
+
+    /// A scratch directory holding files with invalid UTF-8 names.
+    struct Fixture {
+        dir: PathBuf,
+    }
+
+    impl Fixture {
+        const NAMES: [&'static [u8]; 3] = [b"bad\xffname.txt", b"caf\xe9.md", b".hidden\x80"];
+
+        fn new(test: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("datara-{}-{}", test, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            for name in Self::NAMES {
+                std::fs::write(dir.join(OsStr::from_bytes(name)), b"hello\n").unwrap();
+            }
+            Self { dir }
+        }
+
+        fn path(&self, name: &[u8]) -> PathBuf {
+            self.dir.join(OsStr::from_bytes(name))
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn display_escapes_invalid_bytes() {
+        assert_eq!(display(OsStr::from_bytes(b"bad\xffname.txt")), "bad\\xFFname.txt");
+        assert_eq!(display(OsStr::from_bytes(b"caf\xe9.md")), "caf\\xE9.md");
+        assert_eq!(display(OsStr::new("caf\u{e9}.md")), "caf\u{e9}.md");
+        assert!(needs_escaping(OsStr::from_bytes(b"\xff")));
+        assert!(!needs_escaping(OsStr::new("plain")));
+    }
+
+    #[test]
+    fn plain_names_stay_in_the_folder() {
+        assert!(is_plain_name("report.pdf") && is_plain_name("...") && is_plain_name(".hidden"));
+        for name in ["", ".", "..", "a/b", "/etc/passwd", "../up"] {
+            assert!(!is_plain_name(name), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn hidden_detection_ignores_encoding() {
+        assert!(is_hidden(OsStr::from_bytes(b".hidden\x80")));
+        assert!(!is_hidden(OsStr::from_bytes(b"bad\xffname.txt")));
+    }
+
+    #[test]
+    fn fixture_names_survive_listing() {
+        let fixture = Fixture::new("listing");
+        let mut names: Vec<OsString> = std::fs::read_dir(&fixture.dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        names.sort();
+        let mut expected: Vec<OsString> = Fixture::NAMES.iter().map(|n| OsString::from_vec(n.to_vec())).collect();
+        expected.sort();
+        assert_eq!(names, expected);
+        assert_eq!(display_file_name(&fixture.path(b"caf\xe9.md")), "caf\\xE9.md");
+    }
+
+    #[test]
+    fn openers_receive_the_exact_path() {
+        let fixture = Fixture::new("openers");
+        let path = fixture.path(b"bad\xffname.txt");
+        let rules = openers::default_rules();
+        let rule = openers::find_rule(&rules, &path).expect("text rule");
+        let Some(OpenAction::Spawn { args, .. }) = rule.action(&path, "xterm -e") else {
+            panic!("expected a command for a text file");
+        };
+        assert!(args.iter().any(|arg| arg.as_os_str() == path.as_os_str()));
+    }
+
+    #[test]
+    fn content_detection_reads_invalid_names() {
+        let fixture = Fixture::new("detect");
+        let detected = crate::file_type::detect(&fixture.path(b".hidden\x80"));
+        assert_eq!(detected.mime, "text/plain");
+    }
+}
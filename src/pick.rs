@@ -0,0 +1,198 @@
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickTarget {
+    Files,
+    Directories,
+    Both,
+}
+
+impl PickTarget {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "files" => Some(PickTarget::Files),
+            "dirs" | "directories" => Some(PickTarget::Directories),
+            "both" => Some(PickTarget::Both),
+            _ => None,
+        }
+    }
+
+    fn noun(self) -> &'static str {
+        match self {
+            PickTarget::Files => "files",
+            PickTarget::Directories => "folders",
+            PickTarget::Both => "files or folders",
+        }
+    }
+}
+
+/// What `--pick` asks for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PickOptions {
+    pub target: PickTarget,
+    pub multiple: bool,
+    /// Lowercase extensions without the dot; empty allows every file.
+    pub extensions: Vec<String>,
+    /// Written after each path: `\n`, or NUL with `--null`.
+    pub separator: u8,
+    /// Save mode: the user picks a folder and edits this file name.
+    pub save_name: Option<String>,
+}
+
+impl PickOptions {
+    pub fn new(target: PickTarget) -> Self {
+        Self { target, multiple: false, extensions: Vec::new(), separator: b'\n', save_name: None }
+    }
+
+    /// Whether the entry can be chosen.
+    pub fn accepts(&self, path: &Path, is_dir: bool) -> bool {
+        if self.save_name.is_some() {
+            // Clicking an existing file takes over its name, which has to be
+            // editable text like the `--save` name
+            return !is_dir && path.file_name().is_some_and(|name| name.to_str().is_some()) && self.matches_filter(path);
+        }
+        if is_dir {
+            return self.target != PickTarget::Files;
+        }
+        self.target != PickTarget::Directories && self.matches_filter(path)
+    }
+
+    /// Whether the entry is listed at all; directories always are, to navigate.
+    pub fn shows(&self, path: &Path, is_dir: bool) -> bool {
+        is_dir || self.accepts(path, is_dir)
+    }
+
+    fn matches_filter(&self, path: &Path) -> bool {
+        if self.extensions.is_empty() {
+            return true;
+        }
+        let Some(extension) = path.extension().and_then(|e| e.to_str()) else { return false };
+        self.extensions.iter().any(|ext| ext.eq_ignore_ascii_case(extension))
+    }
+
+    pub fn describe(&self) -> String {
+        if self.save_name.is_some() {
+            return "Save as".to_string();
+        }
+        let mut text = format!("Pick {}{}", if self.multiple { "one or more " } else { "" }, self.target.noun());
+        if !self.extensions.is_empty() && self.target != PickTarget::Directories {
+            let patterns: Vec<String> = self.extensions.iter().map(|ext| format!("*.{}", ext)).collect();
+            text.push_str(&format!(" ({})", patterns.join(", ")));
+        }
+        text
+    }
+}
+
+/// Shared with `main`, which reads it after the window closes: `Some` holds
+/// the confirmed paths, `None` means the pick was cancelled.
+pub type PickOutcome = Arc<Mutex<Option<Vec<PathBuf>>>>;
+
+/// Picker state of a `--pick` window; the picked entries are the file
+/// browser's selection.
+pub struct PickState {
+    pub options: PickOptions,
+    /// File name being edited in save mode.
+    pub name: String,
+    pub outcome: PickOutcome,
+}
+
+impl PickState {
+    pub fn new(options: PickOptions, outcome: PickOutcome) -> Self {
+        let name = options.save_name.clone().unwrap_or_default();
+        Self { options, name, outcome }
+    }
+
+    /// In save mode, picking an existing file takes over its name. Names that
+    /// aren't valid UTF-8 are left alone rather than saved to a mangled copy;
+    /// `accepts` doesn't offer them.
+    pub fn picked(&mut self, path: &Path) {
+        if self.options.save_name.is_some()
+            && let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+            self.name = name.to_string();
+        }
+    }
+
+    pub fn confirm(&mut self, paths: Vec<PathBuf>) {
+        if let Ok(mut outcome) = self.outcome.lock() {
+            *outcome = Some(paths);
+        }
+    }
+}
+
+/// Prints the paths byte for byte, so non-UTF-8 names reach the caller intact.
+pub fn write_selection(paths: &[PathBuf], separator: u8) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    for path in paths {
+        stdout.write_all(path.as_os_str().as_bytes())?;
+        stdout.write_all(&[separator])?;
+    }
+    stdout.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    fn options(target: PickTarget, extensions: &[&str]) -> PickOptions {
+        PickOptions { extensions: extensions.iter().map(|ext| ext.to_string()).collect(), ..PickOptions::new(target) }
+    }
+
+    #[test]
+    fn targets_and_filters_decide_what_is_shown_and_accepted() {
+        let (photo, notes, folder) = (Path::new("/p/a.PNG"), Path::new("/p/notes.txt"), Path::new("/p/shots.png"));
+        // (target, filter, [photo, notes, folder] accepted, [photo, notes, folder] shown)
+        let matrix = [
+            (PickTarget::Files, &[][..], [true, true, false], [true, true, true]),
+            (PickTarget::Files, &["png"][..], [true, false, false], [true, false, true]),
+            (PickTarget::Directories, &[][..], [false, false, true], [false, false, true]),
+            (PickTarget::Directories, &["png"][..], [false, false, true], [false, false, true]),
+            (PickTarget::Both, &[][..], [true, true, true], [true, true, true]),
+            (PickTarget::Both, &["png"][..], [true, false, true], [true, false, true]),
+        ];
+        for (target, filter, accepted, shown) in matrix {
+            let options = options(target, filter);
+            let entries = [(photo, false), (notes, false), (folder, true)];
+            assert_eq!(entries.map(|(path, is_dir)| options.accepts(path, is_dir)), accepted, "{:?} {:?}", target, filter);
+            assert_eq!(entries.map(|(path, is_dir)| options.shows(path, is_dir)), shown, "{:?} {:?}", target, filter);
+        }
+    }
+
+    #[test]
+    fn save_mode_offers_files_to_take_the_name_of() {
+        let options = PickOptions { save_name: Some("new.png".to_string()), ..options(PickTarget::Files, &["png"]) };
+        assert!(options.accepts(Path::new("/p/old.png"), false));
+        assert!(!options.accepts(Path::new("/p/old.jpg"), false));
+        assert!(!options.accepts(Path::new("/p/shots.png"), true));
+        assert!(options.shows(Path::new("/p/shots.png"), true));
+        assert!(!options.accepts(&Path::new("/p").join(OsStr::from_bytes(b"caf\xe9.png")), false));
+    }
+
+    #[test]
+    fn descriptions_name_the_target_and_filter() {
+        assert_eq!(options(PickTarget::Files, &[]).describe(), "Pick files");
+        assert_eq!(PickOptions { multiple: true, ..options(PickTarget::Both, &["png", "jpg"]) }.describe(), "Pick one or more files or folders (*.png, *.jpg)");
+        assert_eq!(options(PickTarget::Directories, &["png"]).describe(), "Pick folders");
+        assert_eq!(PickOptions { save_name: Some("a".to_string()), ..options(PickTarget::Files, &[]) }.describe(), "Save as");
+    }
+
+    #[test]
+    fn picking_in_save_mode_takes_over_utf8_names_only() {
+        let outcome = PickOutcome::default();
+        let mut state = PickState::new(PickOptions { save_name: Some("new.txt".to_string()), ..PickOptions::new(PickTarget::Files) }, outcome.clone());
+        assert_eq!(state.name, "new.txt");
+        state.picked(&Path::new("/p").join(OsStr::from_bytes(b"caf\xe9.txt")));
+        assert_eq!(state.name, "new.txt");
+        state.picked(Path::new("/p/old.txt"));
+        assert_eq!(state.name, "old.txt");
+
+        let mut plain = PickState::new(PickOptions::new(PickTarget::Files), outcome.clone());
+        plain.picked(Path::new("/p/old.txt"));
+        assert_eq!(plain.name, "");
+        plain.confirm(vec![PathBuf::from("/p/old.txt")]);
+        assert_eq!(*outcome.lock().unwrap(), Some(vec![PathBuf::from("/p/old.txt")]));
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStringExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+
+use crate::thumbnails;
+
+/// Well-known name of this backend; `data/datara.portal` points
+/// xdg-desktop-portal at it.
+pub const BUS_NAME: &str = "org.freedesktop.impl.portal.desktop.datara";
+pub const OBJECT_PATH: &str = "/org/freedesktop/portal/desktop";
+
+/// Portal response codes.
+const RESPONSE_SUCCESS: u32 = 0;
+const RESPONSE_CANCELLED: u32 = 1;
+const RESPONSE_OTHER: u32 = 2;
+
+type Results = HashMap<String, OwnedValue>;
+
+/// `org.freedesktop.impl.portal.FileChooser`. Every request runs a picker
+/// window as a child process, so dialogs never share state and a crash only
+/// fails that one request.
+pub struct FileChooser {
+    /// Command that starts a picker; the `--pick`/`--save` options are
+    /// appended. Normally this executable.
+    picker: Vec<OsString>,
+}
+
+impl FileChooser {
+    pub fn new(picker: Vec<OsString>) -> Self {
+        Self { picker }
+    }
+
+    /// The picker command for this executable.
+    pub fn current_exe() -> std::io::Result<Self> {
+        Ok(Self::new(vec![std::env::current_exe()?.into_os_string()]))
+    }
+
+    async fn run(&self, args: Vec<OsString>) -> (u32, Results) {
+        let mut command = self.picker.clone();
+        command.extend(args);
+        let output = blocking::unblock(move || {
+            let (program, args) = command.split_first().expect("picker command is not empty");
+            Command::new(program).args(args).stdin(Stdio::null()).stderr(Stdio::inherit()).output()
+        })
+        .await;
+        let output = match output {
+            Ok(output) => output,
+            Err(err) => {
+                eprintln!("datara: failed to start the picker: {}", err);
+                return (RESPONSE_OTHER, Results::new());
+            }
+        };
+        match output.status.code() {
+            Some(0) => {
+                let uris: Vec<String> = output
+                    .stdout
+                    .split(|&b| b == b'\0')
+                    .filter(|path| !path.is_empty())
+                    .map(|path| thumbnails::file_uri(&PathBuf::from(OsString::from_vec(path.to_vec()))))
+                    .collect();
+                if uris.is_empty() {
+                    return (RESPONSE_CANCELLED, Results::new());
+                }
+                let mut results = Results::new();
+                results.insert("uris".to_string(), Value::from(uris).try_into().expect("string array has no fds"));
+                (RESPONSE_SUCCESS, results)
+            }
+            Some(1) => (RESPONSE_CANCELLED, Results::new()),
+            _ => (RESPONSE_OTHER, Results::new()),
+        }
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.impl.portal.FileChooser")]
+impl FileChooser {
+    async fn open_file(&self, _handle: ObjectPath<'_>, _app_id: &str, _parent_window: &str, _title: &str, options: HashMap<&str, Value<'_>>) -> (u32, Results) {
+        let directory = option::<bool>(&options, "directory").unwrap_or(false);
+        let mut args = vec![OsString::from(if directory { "--pick=dirs" } else { "--pick=files" }), OsString::from("--null")];
+        if option::<bool>(&options, "multiple").unwrap_or(false) {
+            args.push("--multiple".into());
+        }
+        push_filter(&mut args, &options);
+        if let Some(folder) = option_path(&options, "current_folder") {
+            args.push("--".into());
+            args.push(folder.into_os_string());
+        }
+        self.run(args).await
+    }
+
+    async fn save_file(&self, _handle: ObjectPath<'_>, _app_id: &str, _parent_window: &str, _title: &str, options: HashMap<&str, Value<'_>>) -> (u32, Results) {
+        // current_file names an existing file to overwrite and wins over the other hints
+        let current_file = option_path(&options, "current_file");
+        let name = current_file
+            .as_ref()
+            .and_then(|file| file.file_name())
+            .map(OsString::from)
+            .or_else(|| option::<String>(&options, "current_name").map(OsString::from))
+            .unwrap_or_default();
+        let folder = current_file.as_ref().and_then(|file| file.parent()).map(PathBuf::from).or_else(|| option_path(&options, "current_folder"));
+        let mut args = vec![OsString::from("--save"), name, OsString::from("--null")];
+        push_filter(&mut args, &options);
+        if let Some(folder) = folder {
+            args.push("--".into());
+            args.push(folder.into_os_string());
+        }
+        self.run(args).await
+    }
+}
+
+fn option<T: TryFrom<Value<'static>>>(options: &HashMap<&str, Value<'_>>, key: &str) -> Option<T> {
+    T::try_from(options.get(key)?.try_to_owned().ok()?.into()).ok()
+}
+
+/// Paths are passed as NUL-terminated byte arrays.
+fn option_path(options: &HashMap<&str, Value<'_>>, key: &str) -> Option<PathBuf> {
+    let mut bytes = option::<Vec<u8>>(options, key)?;
+    if bytes.last() == Some(&0) {
+        bytes.pop();
+    }
+    (!bytes.is_empty()).then(|| PathBuf::from(OsString::from_vec(bytes)))
+}
+
+/// A filter is `(name, [(kind, pattern)])` with kind 0 for globs and 1 for MIME
+/// types. Only `*.ext` globs map onto `--filter`; anything else offers every file.
+fn push_filter(args: &mut Vec<OsString>, options: &HashMap<&str, Value<'_>>) {
+    type Filter = (String, Vec<(u32, String)>);
+    let filter = option::<Filter>(options, "current_filter").or_else(|| option::<Vec<Filter>>(options, "filters")?.into_iter().next());
+    let Some((_, patterns)) = filter else { return };
+    let mut extensions = Vec::new();
+    for (kind, pattern) in patterns {
+        match pattern.strip_prefix("*.") {
+            Some(ext) if kind == 0 && !ext.is_empty() && !ext.as_bytes().iter().any(|b| b"*?[/,".contains(b)) => extensions.push(ext.to_string()),
+            _ => return,
+        }
+    }
+    if !extensions.is_empty() {
+        args.push("--filter".into());
+        args.push(extensions.join(",").into());
+    }
+}
+
+/// Exports the backend on `builder`'s bus and claims `BUS_NAME`.
+pub fn serve(builder: zbus::blocking::connection::Builder<'_>, chooser: FileChooser) -> zbus::Result<zbus::blocking::Connection> {
+    builder.name(BUS_NAME)?.serve_at(OBJECT_PATH, chooser)?.build()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::Child;
+
+    /// A private session bus, so the test never touches the user's.
+    struct Bus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl Bus {
+        fn start() -> Option<Self> {
+            let mut daemon = Command::new("dbus-daemon").args(["--session", "--nofork", "--print-address"]).stdout(Stdio::piped()).spawn().ok()?;
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take()?).read_line(&mut address).ok()?;
+            Some(Self { daemon, address: address.trim().to_string() })
+        }
+
+        fn builder(&self) -> zbus::blocking::connection::Builder<'_> {
+            zbus::blocking::connection::Builder::address(self.address.as_str()).unwrap()
+        }
+    }
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    /// A picker that runs `script` instead of opening a window.
+    fn fake_picker(script: &str) -> FileChooser {
+        FileChooser::new(["sh", "-c", script, "picker"].map(OsString::from).to_vec())
+    }
+
+    fn call(bus: &Bus, method: &str, options: HashMap<&str, Value<'_>>) -> (u32, HashMap<String, OwnedValue>) {
+        let client = bus.builder().build().unwrap();
+        let handle = ObjectPath::try_from("/org/freedesktop/portal/desktop/request/1_1/t").unwrap();
+        let reply = client.call_method(Some(BUS_NAME), OBJECT_PATH, Some("org.freedesktop.impl.portal.FileChooser"), method, &(handle, "org.example.App", "", method, options)).unwrap();
+        reply.body().deserialize().unwrap()
+    }
+
+    fn open_file(bus: &Bus, options: HashMap<&str, Value<'_>>) -> (u32, HashMap<String, OwnedValue>) {
+        call(bus, "OpenFile", options)
+    }
+
+    /// The picker's arguments, as `file://` URIs since it echoes them back.
+    fn picker_args(results: &HashMap<String, OwnedValue>) -> Vec<String> {
+        Vec::<String>::try_from(results["uris"].try_clone().unwrap()).unwrap()
+    }
+
+    fn uris(args: &[&[u8]]) -> Vec<String> {
+        args.iter().map(|arg| thumbnails::file_uri(&PathBuf::from(OsString::from_vec(arg.to_vec())))).collect()
+    }
+
+    #[test]
+    fn open_file_returns_the_picked_uris() {
+        let Some(bus) = Bus::start() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        // Echo the arguments back as the "picked" paths
+        let _server = serve(bus.builder(), fake_picker(r#"printf '/tmp/a b\0'; printf '%s\0' "$@""#)).unwrap();
+        let mut options = HashMap::new();
+        options.insert("multiple", Value::from(true));
+        options.insert("current_folder", Value::from(b"/srv/data\0".to_vec()));
+        options.insert("filters", Value::from(vec![("Images".to_string(), vec![(0u32, "*.png".to_string()), (0u32, "*.JPG".to_string())])]));
+
+        let (response, results) = open_file(&bus, options);
+        assert_eq!(response, RESPONSE_SUCCESS);
+        let uris = Vec::<String>::try_from(results["uris"].try_clone().unwrap()).unwrap();
+        let expected: Vec<String> = ["/tmp/a b", "--pick=files", "--null", "--multiple", "--filter", "png,JPG", "--", "/srv/data"].iter().map(|path| thumbnails::file_uri(std::path::Path::new(path))).collect();
+        assert_eq!(uris, expected);
+    }
+
+    #[test]
+    fn save_file_prefers_current_file_over_name_and_folder() {
+        let Some(bus) = Bus::start() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        let _server = serve(bus.builder(), fake_picker(r#"printf '%s\0' "$@""#)).unwrap();
+        let mut options = HashMap::new();
+        options.insert("current_name", Value::from("Untitled.txt"));
+        options.insert("current_folder", Value::from(b"/srv/data\0".to_vec()));
+        let (response, results) = call(&bus, "SaveFile", options.clone());
+        assert_eq!(response, RESPONSE_SUCCESS);
+        assert_eq!(picker_args(&results), uris(&[b"--save", b"Untitled.txt", b"--null", b"--", b"/srv/data"]));
+
+        // The existing file's name is passed on byte for byte
+        options.insert("current_file", Value::from(b"/home/me/caf\xe9 notes.txt\0".to_vec()));
+        options.insert("current_filter", Value::from(("Text".to_string(), vec![(0u32, "*.txt".to_string())])));
+        let (response, results) = call(&bus, "SaveFile", options);
+        assert_eq!(response, RESPONSE_SUCCESS);
+        assert_eq!(picker_args(&results), uris(&[b"--save", b"caf\xe9 notes.txt", b"--null", b"--filter", b"txt", b"--", b"/home/me"]));
+
+        // No hints: an empty name, which the echo drops like any empty path
+        let (_, results) = call(&bus, "SaveFile", HashMap::new());
+        assert_eq!(picker_args(&results), uris(&[b"--save", b"--null"]));
+    }
+
+    #[test]
+    fn cancelled_picker_reports_cancelled() {
+        let Some(bus) = Bus::start() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        let _server = serve(bus.builder(), fake_picker("exit 1")).unwrap();
+        let (response, results) = open_file(&bus, HashMap::new());
+        assert_eq!(response, RESPONSE_CANCELLED);
+        assert!(results.is_empty());
+    }
+}
@@ -0,0 +1,292 @@
+use eframe::egui;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::file_kind::FileKind;
+use crate::file_type;
+use crate::markdown::MarkdownDocument;
+use crate::vfs::Vfs;
+
+const TEXT_PREVIEW_BYTES: usize = 64 * 1024;
+const TEXT_PREVIEW_LINES: usize = 60;
+const HEX_PREVIEW_BYTES: usize = 512;
+const IMAGE_PREVIEW_MAX_SIDE: u32 = 512;
+const DIR_SCAN_LIMIT: usize = 10_000;
+/// How long the pointer has to rest on an entry before it is previewed, so
+/// that sweeping across the grid doesn't load everything on the way.
+const HOVER_DELAY: Duration = Duration::from_millis(150);
+
+pub enum PreviewContent {
+    Text { lines: Vec<String>, truncated: bool },
+    Markdown { document: MarkdownDocument, source: String, show_source: bool },
+    Image { texture: egui::TextureHandle, width: u32, height: u32 },
+    Directory { files: usize, dirs: usize, total_size: u64, truncated: bool },
+    Hex { lines: Vec<String>, truncated: bool },
+    Error(String),
+}
+
+/// Cached preview of a single entry, rebuilt only when the previewed path changes.
+pub struct Preview {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: Option<u64>,
+    pub modified: Option<SystemTime>,
+    pub content: PreviewContent,
+}
+
+impl Preview {
+    pub fn load(ctx: &egui::Context, vfs: &dyn Vfs, path: &Path) -> Self {
+        let meta = vfs.stat(path).ok();
+        let is_dir = meta.is_some_and(|m| m.is_dir);
+        let size = meta.filter(|m| !m.is_dir).map(|m| m.len);
+        let modified = meta.and_then(|m| m.modified);
+
+        let content = if is_dir {
+            Self::load_directory(vfs, path)
+        } else {
+            match file_type::detect_in(vfs, path).kind {
+                FileKind::Image => Self::load_image(ctx, vfs, path).unwrap_or_else(|| Self::load_sniffed(vfs, path)),
+                FileKind::Text => Self::load_text(vfs, path),
+                FileKind::Markdown => Self::load_markdown(vfs, path),
+                _ => Self::load_sniffed(vfs, path),
+            }
+        };
+
+        Self { path: path.to_path_buf(), is_dir, size, modified, content }
+    }
+
+    fn read_head(vfs: &dyn Vfs, path: &Path, limit: usize) -> std::io::Result<(Vec<u8>, bool)> {
+        let mut buf = vfs.read_head(path, limit as u64 + 1)?;
+        let truncated = buf.len() > limit;
+        buf.truncate(limit);
+        Ok((buf, truncated))
+    }
+
+    fn load_text(vfs: &dyn Vfs, path: &Path) -> PreviewContent {
+        match Self::read_head(vfs, path, TEXT_PREVIEW_BYTES) {
+            Ok((bytes, truncated)) => Self::text_from_bytes(&bytes, truncated),
+            Err(err) => PreviewContent::Error(format!("Failed to read file: {}", err)),
+        }
+    }
+
+    fn load_markdown(vfs: &dyn Vfs, path: &Path) -> PreviewContent {
+        match Self::read_head(vfs, path, TEXT_PREVIEW_BYTES) {
+            Ok((bytes, _)) => {
+                let source = String::from_utf8_lossy(&bytes).to_string();
+                let base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+                let document = MarkdownDocument::parse(&source, &base_dir);
+                PreviewContent::Markdown { document, source, show_source: false }
+            }
+            Err(err) => PreviewContent::Error(format!("Failed to read file: {}", err)),
+        }
+    }
+
+    fn text_from_bytes(bytes: &[u8], truncated: bool) -> PreviewContent {
+        let text = String::from_utf8_lossy(bytes);
+        let mut lines: Vec<String> = text.lines().take(TEXT_PREVIEW_LINES + 1).map(|l| l.replace('\t', "    ")).collect();
+        let more_lines = lines.len() > TEXT_PREVIEW_LINES;
+        lines.truncate(TEXT_PREVIEW_LINES);
+        PreviewContent::Text { lines, truncated: truncated || more_lines }
+    }
+
+    /// Files without a dedicated previewer: show them as text when they look
+    /// like text, otherwise fall back to a hex dump of the first bytes.
+    fn load_sniffed(vfs: &dyn Vfs, path: &Path) -> PreviewContent {
+        match Self::read_head(vfs, path, TEXT_PREVIEW_BYTES) {
+            Ok((bytes, truncated)) => {
+                let probe = &bytes[..bytes.len().min(HEX_PREVIEW_BYTES * 2)];
+                if !probe.contains(&0) && std::str::from_utf8(probe).is_ok() {
+                    Self::text_from_bytes(&bytes, truncated)
+                } else {
+                    let shown = &bytes[..bytes.len().min(HEX_PREVIEW_BYTES)];
+                    PreviewContent::Hex { lines: hex_dump(shown), truncated: truncated || bytes.len() > HEX_PREVIEW_BYTES }
+                }
+            }
+            Err(err) => PreviewContent::Error(format!("Failed to read file: {}", err)),
+        }
+    }
+
+    fn load_image(ctx: &egui::Context, vfs: &dyn Vfs, path: &Path) -> Option<PreviewContent> {
+        let mut data = Vec::new();
+        vfs.open_read(path).ok()?.read_to_end(&mut data).ok()?;
+        let image = image::load_from_memory(&data).ok()?;
+        let (width, height) = (image.width(), image.height());
+        let image = if width > IMAGE_PREVIEW_MAX_SIDE || height > IMAGE_PREVIEW_MAX_SIDE {
+            image.thumbnail(IMAGE_PREVIEW_MAX_SIDE, IMAGE_PREVIEW_MAX_SIDE)
+        } else {
+            image
+        };
+        let rgba_image = image.to_rgba8();
+        let size = [rgba_image.width() as usize, rgba_image.height() as usize];
+        let color_image = egui::ColorImage::from_rgba_unmultiplied(size, rgba_image.as_raw());
+        let texture = ctx.load_texture("preview_image", color_image, egui::TextureOptions::LINEAR);
+        Some(PreviewContent::Image { texture, width, height })
+    }
+
+    fn load_directory(vfs: &dyn Vfs, path: &Path) -> PreviewContent {
+        let entries = match vfs.list(path) {
+            Ok(entries) => entries,
+            Err(err) => return PreviewContent::Error(format!("Failed to read dir: {}", err)),
+        };
+        let (mut files, mut dirs, mut total_size) = (0, 0, 0);
+        let truncated = entries.len() > DIR_SCAN_LIMIT;
+        for entry in entries.iter().take(DIR_SCAN_LIMIT) {
+            match entry.metadata {
+                Some(meta) if meta.is_dir => dirs += 1,
+                Some(meta) => {
+                    files += 1;
+                    total_size += meta.len;
+                }
+                None => files += 1,
+            }
+        }
+        PreviewContent::Directory { files, dirs, total_size, truncated }
+    }
+}
+
+struct Request {
+    generation: u64,
+    vfs: Arc<dyn Vfs>,
+    path: PathBuf,
+}
+
+/// Loads previews on a worker thread, since decoding images and listing
+/// folders, or reading over the network, would stall the frame. Only the
+/// latest request counts: asking for another path cancels the one before.
+pub struct PreviewLoader {
+    /// Bumped by every request and by `cancel`; the worker skips requests,
+    /// and results, of older generations.
+    generation: Arc<AtomicU64>,
+    requests: Sender<Request>,
+    done: Receiver<(u64, Preview)>,
+    ctx: egui::Context,
+    /// The path waiting out `HOVER_DELAY`, and since when.
+    wanted: Option<(PathBuf, Instant)>,
+    loading: Option<PathBuf>,
+}
+
+impl PreviewLoader {
+    pub fn new(ctx: &egui::Context) -> Self {
+        let (request_tx, request_rx) = mpsc::channel::<Request>();
+        let (done_tx, done_rx) = mpsc::channel();
+        let generation = Arc::new(AtomicU64::new(0));
+        let current = Arc::clone(&generation);
+        let worker_ctx = ctx.clone();
+        std::thread::spawn(move || {
+            while let Ok(request) = request_rx.recv() {
+                let request = request_rx.try_iter().last().unwrap_or(request);
+                if request.generation != current.load(Ordering::Relaxed) {
+                    continue;
+                }
+                let preview = Preview::load(&worker_ctx, request.vfs.as_ref(), &request.path);
+                if done_tx.send((request.generation, preview)).is_err() {
+                    return;
+                }
+                worker_ctx.request_repaint();
+            }
+        });
+        Self { generation, requests: request_tx, done: done_rx, ctx: ctx.clone(), wanted: None, loading: None }
+    }
+
+    /// Asks for a preview of `path` once it has been wanted for
+    /// `HOVER_DELAY`; call every frame with the entry to preview.
+    pub fn want(&mut self, vfs: &Arc<dyn Vfs>, path: &Path) {
+        if self.loading.as_deref() == Some(path) {
+            return;
+        }
+        let since = match &self.wanted {
+            Some((wanted, since)) if wanted == path => *since,
+            _ => {
+                self.wanted = Some((path.to_path_buf(), Instant::now()));
+                self.ctx.request_repaint_after(HOVER_DELAY);
+                return;
+            }
+        };
+        let waited = since.elapsed();
+        if waited < HOVER_DELAY {
+            self.ctx.request_repaint_after(HOVER_DELAY - waited);
+            return;
+        }
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+        let _ = self.requests.send(Request { generation, vfs: Arc::clone(vfs), path: path.to_path_buf() });
+        self.wanted = None;
+        self.loading = Some(path.to_path_buf());
+    }
+
+    /// Forgets the pending request, e.g. because the folder changed.
+    pub fn cancel(&mut self) {
+        if self.wanted.is_some() || self.loading.is_some() {
+            self.generation.fetch_add(1, Ordering::Relaxed);
+            self.wanted = None;
+            self.loading = None;
+        }
+    }
+
+    /// The path being loaded, if any.
+    pub fn loading(&self) -> Option<&Path> {
+        self.loading.as_deref()
+    }
+
+    /// The preview of the latest request, once it is ready.
+    pub fn poll(&mut self) -> Option<Preview> {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let preview = self.done.try_iter().filter(|(done, _)| *done == generation).map(|(_, preview)| preview).last()?;
+        self.loading = None;
+        Some(preview)
+    }
+}
+
+/// Classic `offset  hex bytes  |ascii|` dump, 16 bytes per line.
+pub fn hex_dump(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let mut hex = String::with_capacity(16 * 3 + 1);
+            for (i, b) in chunk.iter().enumerate() {
+                if i == 8 { hex.push(' '); }
+                hex.push_str(&format!("{:02x} ", b));
+            }
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                .collect();
+            format!("{:08x}  {:<49} |{}|", row * 16, hex, ascii)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+
+    #[test]
+    fn only_the_latest_hover_is_loaded() {
+        let fs = MemoryFs::default();
+        fs.add_file("/a.txt", "first").add_file("/b.txt", "second");
+        let vfs: Arc<dyn Vfs> = Arc::new(fs);
+        let mut loader = PreviewLoader::new(&egui::Context::default());
+
+        // Passing over an entry doesn't load it
+        loader.want(&vfs, Path::new("/a.txt"));
+        loader.want(&vfs, Path::new("/b.txt"));
+        assert!(loader.loading().is_none());
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut preview = None;
+        while preview.is_none() && Instant::now() < deadline {
+            loader.want(&vfs, Path::new("/b.txt"));
+            preview = loader.poll();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let preview = preview.expect("the preview should load");
+        assert_eq!(preview.path, Path::new("/b.txt"));
+        assert!(matches!(preview.content, PreviewContent::Text { ref lines, .. } if lines == &["second"]));
+        assert!(loader.loading().is_none());
+    }
+}
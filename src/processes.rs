@@ -0,0 +1,190 @@
+use eframe::egui;
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::os::unix::fs::{FileExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// How much of a process's stderr is read for the failure report.
+const STDERR_TAIL_BYTES: u64 = 4096;
+const STDERR_TAIL_LINES: usize = 8;
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaunchKind {
+    /// An application opening a file.
+    Opener,
+    /// A `beep` sound effect.
+    Sound,
+}
+
+/// A launched process that could not be started or exited unsuccessfully.
+#[derive(Debug, Clone)]
+pub struct LaunchFailure {
+    pub kind: LaunchKind,
+    /// The file the process was launched for.
+    pub path: Option<PathBuf>,
+    pub message: String,
+    /// Last lines the process wrote to stderr.
+    pub stderr_tail: String,
+}
+
+struct Running {
+    child: Child,
+    kind: LaunchKind,
+    program: String,
+    path: Option<PathBuf>,
+    /// Where the process's stderr goes, see `stderr_file`.
+    stderr: Option<File>,
+}
+
+impl Running {
+    fn stderr_tail(&self) -> String {
+        let Some(file) = &self.stderr else { return String::new() };
+        let len = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+        let start = len.saturating_sub(STDERR_TAIL_BYTES);
+        let mut bytes = vec![0; (len - start) as usize];
+        let read = file.read_at(&mut bytes, start).unwrap_or(0);
+        bytes.truncate(read);
+        let text = String::from_utf8_lossy(&bytes);
+        let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+        lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..].join("\n")
+    }
+}
+
+/// Launches external programs, reaps them and collects their failures.
+///
+/// Sound effects are best effort: the first failure is reported once and
+/// disables them for the rest of the session.
+#[derive(Default)]
+pub struct ProcessSupervisor {
+    running: Vec<Running>,
+    failures: Vec<LaunchFailure>,
+    sound_disabled: bool,
+}
+
+impl ProcessSupervisor {
+    pub fn spawn(&mut self, kind: LaunchKind, program: &OsStr, args: &[OsString], path: Option<&Path>) {
+        if kind == LaunchKind::Sound && self.sound_disabled {
+            return;
+        }
+        let program_name = program.to_string_lossy().to_string();
+        let stderr = stderr_file().ok();
+        let spawned = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(stderr.as_ref().and_then(|file| file.try_clone().ok()).map_or_else(Stdio::null, Stdio::from))
+            .spawn();
+        let child = match spawned {
+            Ok(child) => child,
+            Err(err) => {
+                let message = if err.kind() == std::io::ErrorKind::NotFound {
+                    format!("{}: command not found", program_name)
+                } else {
+                    format!("Failed to launch {}: {}", program_name, err)
+                };
+                self.fail(LaunchFailure { kind, path: path.map(Path::to_path_buf), message, stderr_tail: String::new() });
+                return;
+            }
+        };
+
+        self.running.push(Running { child, kind, program: program_name, path: path.map(Path::to_path_buf), stderr });
+    }
+
+    fn fail(&mut self, failure: LaunchFailure) {
+        if failure.kind == LaunchKind::Sound {
+            if self.sound_disabled {
+                return;
+            }
+            self.sound_disabled = true;
+        }
+        self.failures.push(failure);
+    }
+
+    /// Reaps finished processes and returns the failures since the last call.
+    pub fn poll(&mut self, ctx: &egui::Context) -> Vec<LaunchFailure> {
+        let mut finished = Vec::new();
+        self.running.retain_mut(|running| {
+            let status = match running.child.try_wait() {
+                Ok(Some(status)) => status,
+                Ok(None) => return true,
+                // The child can no longer be waited on; nothing to report
+                Err(_) => return false,
+            };
+            if !status.success() {
+                finished.push(LaunchFailure {
+                    kind: running.kind,
+                    path: running.path.clone(),
+                    message: format!("{} failed ({})", running.program, status),
+                    stderr_tail: running.stderr_tail(),
+                });
+            }
+            false
+        });
+        for failure in finished {
+            self.fail(failure);
+        }
+        if !self.running.is_empty() {
+            ctx.request_repaint_after(POLL_INTERVAL);
+        }
+        std::mem::take(&mut self.failures)
+    }
+}
+
+/// An unlinked temporary file for a child's stderr. A pipe would need a
+/// reader for as long as the child runs, and once Datara exits the child's
+/// next write would fail with EPIPE or kill it with SIGPIPE; a file has no
+/// reader to outlive.
+fn stderr_file() -> std::io::Result<File> {
+    static FILES: AtomicU64 = AtomicU64::new(0);
+    let path = std::env::temp_dir().join(format!(".datara-stderr-{}-{}", std::process::id(), FILES.fetch_add(1, Ordering::Relaxed)));
+    let file = std::fs::OpenOptions::new().read(true).append(true).create_new(true).mode(0o600).open(&path)?;
+    std::fs::remove_file(&path)?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn sh(supervisor: &mut ProcessSupervisor, script: &str) {
+        let args = [OsString::from("-c"), OsString::from(script)];
+        supervisor.spawn(LaunchKind::Opener, OsStr::new("sh"), &args, None);
+    }
+
+    #[test]
+    fn failures_carry_the_stderr_tail() {
+        let mut supervisor = ProcessSupervisor::default();
+        sh(&mut supervisor, "echo starting >&2; echo 'no such file' >&2; exit 3");
+        let ctx = egui::Context::default();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut failures = Vec::new();
+        while failures.is_empty() && Instant::now() < deadline {
+            failures = supervisor.poll(&ctx);
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].stderr_tail, "starting\nno such file");
+    }
+
+    #[test]
+    fn children_outlive_the_supervisor() {
+        let marker = std::env::temp_dir().join(format!("datara-detached-{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        let mut supervisor = ProcessSupervisor::default();
+        // Writes to stderr only after the supervisor is gone
+        sh(&mut supervisor, &format!("sleep 0.2; echo still here >&2; touch '{}'", marker.display()));
+        drop(supervisor);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !marker.exists() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(marker.exists());
+        std::fs::remove_file(&marker).unwrap();
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::dav::DavFs;
+use crate::paths;
+use crate::sftp::SftpFs;
+use crate::vfs::Vfs;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scheme {
+    Sftp,
+    /// WebDAV over plain HTTP.
+    Dav,
+    /// WebDAV over HTTPS.
+    Davs,
+}
+
+impl Scheme {
+    fn name(self) -> &'static str {
+        match self {
+            Scheme::Sftp => "sftp",
+            Scheme::Dav => "dav",
+            Scheme::Davs => "davs",
+        }
+    }
+
+    fn default_port(self) -> u16 {
+        match self {
+            Scheme::Sftp => 22,
+            Scheme::Dav => 80,
+            Scheme::Davs => 443,
+        }
+    }
+}
+
+/// A `scheme://user@host:port/path` location. The path is taken literally,
+/// without percent-decoding.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RemoteUrl {
+    pub scheme: Scheme,
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+    /// Absolute path on the server.
+    pub path: PathBuf,
+}
+
+impl RemoteUrl {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (scheme, rest) = text.split_once("://").ok_or_else(|| format!("{} is not a URL", text))?;
+        let scheme = match scheme.to_ascii_lowercase().as_str() {
+            "sftp" => Scheme::Sftp,
+            "dav" => Scheme::Dav,
+            "davs" => Scheme::Davs,
+            other => return Err(format!("Unsupported location type {}://", other)),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None => (rest, "/"),
+        };
+        let (user, host_port) = match authority.rsplit_once('@') {
+            Some((user, host_port)) => (Some(user.to_string()), host_port),
+            None => (None, authority),
+        };
+        let (host, port) = match host_port.rsplit_once(':') {
+            // A bracketed IPv6 address carries its own colons
+            Some((host, port)) if !port.contains(']') => (host, Some(port.parse().map_err(|_| format!("Invalid port in {}", text))?)),
+            _ => (host_port, None),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(format!("No host in {}", text));
+        }
+        if user.as_deref() == Some("") {
+            return Err(format!("Empty user name in {}", text));
+        }
+        Ok(Self { scheme, user, host: host.to_string(), port, path: PathBuf::from(path) })
+    }
+
+    pub fn port_or_default(&self) -> u16 {
+        self.port.unwrap_or(self.scheme.default_port())
+    }
+
+    /// The same server at another path.
+    pub fn with_path(&self, path: &Path) -> Self {
+        Self { path: path.to_path_buf(), ..self.clone() }
+    }
+
+    /// `scheme://user@host:port`, without a path.
+    pub fn root(&self) -> String {
+        let mut root = format!("{}://", self.scheme.name());
+        if let Some(user) = &self.user {
+            root.push_str(user);
+            root.push('@');
+        }
+        if self.host.contains(':') {
+            root.push_str(&format!("[{}]", self.host));
+        } else {
+            root.push_str(&self.host);
+        }
+        if let Some(port) = self.port {
+            root.push_str(&format!(":{}", port));
+        }
+        root
+    }
+}
+
+impl fmt::Display for RemoteUrl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.root(), paths::display_path(&self.path))
+    }
+}
+
+/// Where the address bar or a bookmark points.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Location {
+    Local(PathBuf),
+    Remote(RemoteUrl),
+}
+
+impl Location {
+    /// Accepts URLs, absolute paths and `~/...`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        if text.contains("://") {
+            return RemoteUrl::parse(text).map(Location::Remote);
+        }
+        let path = match text.strip_prefix('~') {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+                let home = std::env::var_os("HOME").ok_or("$HOME is not set")?;
+                PathBuf::from(home).join(rest.trim_start_matches('/'))
+            }
+            _ => PathBuf::from(text),
+        };
+        if !path.is_absolute() {
+            return Err(format!("{} is not an absolute path", text));
+        }
+        Ok(Location::Local(path))
+    }
+}
+
+/// Open connections, one per server, shared by all tabs browsing it.
+#[derive(Default)]
+pub struct Remotes {
+    connections: Mutex<HashMap<String, Arc<dyn Vfs>>>,
+}
+
+impl Remotes {
+    /// The connection to `url`'s server, opened on first use.
+    /// Connecting doesn't hold the table locked, so a slow server doesn't
+    /// stall `disconnect` or connections to other servers.
+    pub fn connect(&self, url: &RemoteUrl) -> io::Result<Arc<dyn Vfs>> {
+        let root = url.root();
+        if let Some(vfs) = self.lock()?.get(&root) {
+            return Ok(vfs.clone());
+        }
+        let vfs: Arc<dyn Vfs> = match url.scheme {
+            Scheme::Sftp => Arc::new(SftpFs::connect(url)?),
+            Scheme::Dav | Scheme::Davs => Arc::new(DavFs::connect(url)?),
+        };
+        // Another thread may have connected meanwhile; share its connection
+        Ok(self.lock()?.entry(root).or_insert(vfs).clone())
+    }
+
+    fn lock(&self) -> io::Result<MutexGuard<'_, HashMap<String, Arc<dyn Vfs>>>> {
+        self.connections.lock().map_err(|_| io::Error::other("connection table poisoned"))
+    }
+
+    /// Forgets the connection so the next `connect` opens a new one, e.g.
+    /// after the server went away.
+    pub fn disconnect(&self, url: &RemoteUrl) {
+        if let Ok(mut connections) = self.connections.lock() {
+            connections.remove(&url.root());
+        }
+    }
+}
+
+/// Standard base64 with padding, for host key fingerprints and HTTP Basic
+/// credentials.
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            out.push(if i <= chunk.len() { ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char } else { '=' });
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_user_host_port_and_path() {
+        let url = RemoteUrl::parse("sftp://ada@lab1.example:2222/srv/assets").unwrap();
+        assert_eq!(url.scheme, Scheme::Sftp);
+        assert_eq!(url.user.as_deref(), Some("ada"));
+        assert_eq!(url.host, "lab1.example");
+        assert_eq!(url.port, Some(2222));
+        assert_eq!(url.path, Path::new("/srv/assets"));
+        assert_eq!(url.to_string(), "sftp://ada@lab1.example:2222/srv/assets");
+
+        let url = RemoteUrl::parse("SFTP://lab1").unwrap();
+        assert_eq!((url.user.as_deref(), url.port_or_default(), url.path.as_path()), (None, 22, Path::new("/")));
+
+        let url = RemoteUrl::parse("sftp://[::1]:2022/tmp").unwrap();
+        assert_eq!((url.host.as_str(), url.port), ("::1", Some(2022)));
+        assert_eq!(url.root(), "sftp://[::1]:2022");
+    }
+
+    #[test]
+    fn rejects_bad_urls() {
+        assert!(RemoteUrl::parse("ftp://host/").is_err());
+        assert!(RemoteUrl::parse("sftp:///path").is_err());
+        assert!(RemoteUrl::parse("sftp://@host/").is_err());
+        assert!(RemoteUrl::parse("sftp://host:port/").is_err());
+    }
+
+    #[test]
+    fn dav_urls_default_to_the_http_ports() {
+        assert_eq!(RemoteUrl::parse("dav://nas/share").unwrap().port_or_default(), 80);
+        assert_eq!(RemoteUrl::parse("davs://nas").unwrap().port_or_default(), 443);
+    }
+
+    #[test]
+    fn base64_pads_to_four_characters() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"user:pa55"), "dXNlcjpwYTU1");
+    }
+
+    #[test]
+    fn locations_are_urls_or_absolute_paths() {
+        assert_eq!(Location::parse(" /tmp/x ").unwrap(), Location::Local(PathBuf::from("/tmp/x")));
+        assert!(matches!(Location::parse("sftp://host/a").unwrap(), Location::Remote(url) if url.path == Path::new("/a")));
+        assert!(Location::parse("relative/dir").is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use std::ffi::OsString;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use ssh2::{CheckResult, ErrorCode, FileStat, HashType, KnownHostFileKind, Session, Sftp};
+
+use crate::remote::{self, RemoteUrl};
+use crate::vfs::{Metadata, Vfs, VfsEntry};
+
+/// Applies to connecting and to every request afterwards.
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A server reached over SSH. Only keys from the agent or `~/.ssh` are
+/// offered, and the server's host key must already be in `known_hosts`.
+pub struct SftpFs {
+    // Keeps the connection open for `sftp`
+    _session: Session,
+    sftp: Sftp,
+}
+
+impl SftpFs {
+    pub fn connect(url: &RemoteUrl) -> io::Result<Self> {
+        let port = url.port_or_default();
+        let addr = (url.host.as_str(), port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} has no address", url.host)))?;
+        let stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+        let mut session = Session::new()?;
+        session.set_timeout(TIMEOUT.as_millis() as u32);
+        session.set_tcp_stream(stream);
+        session.handshake()?;
+        verify_host_key(&session, &url.host, port)?;
+
+        let user = match &url.user {
+            Some(user) => user.clone(),
+            None => std::env::var("USER").or_else(|_| std::env::var("LOGNAME")).map_err(|_| io::Error::other("no user name in the URL and $USER is not set"))?,
+        };
+        authenticate(&session, &user)?;
+        let sftp = session.sftp()?;
+        Ok(Self { _session: session, sftp })
+    }
+}
+
+fn ssh_dir() -> Option<PathBuf> {
+    Some(PathBuf::from(std::env::var_os("HOME")?).join(".ssh"))
+}
+
+/// Checks the server against the user's and the system's `known_hosts`, as
+/// `ssh` would with `StrictHostKeyChecking=yes`.
+fn verify_host_key(session: &Session, host: &str, port: u16) -> io::Result<()> {
+    let (key, _) = session.host_key().ok_or_else(|| io::Error::other("the server sent no host key"))?;
+    let mut known_hosts = session.known_hosts()?;
+    let files = ssh_dir().map(|dir| dir.join("known_hosts")).into_iter().chain([PathBuf::from("/etc/ssh/ssh_known_hosts")]);
+    for file in files {
+        // Missing files just know no hosts
+        let _ = known_hosts.read_file(&file, KnownHostFileKind::OpenSSH);
+    }
+    let fingerprint = session.host_key_hash(HashType::Sha256).map(|hash| format!("SHA256:{}", remote::base64(hash).trim_end_matches('='))).unwrap_or_default();
+    match known_hosts.check_port(host, port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("Host key for {} does not match known_hosts (got {}); refusing to connect", host, fingerprint),
+        )),
+        CheckResult::NotFound => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not in known_hosts (key {}); connect once with ssh to verify and add it", host, fingerprint),
+        )),
+        CheckResult::Failure => Err(io::Error::other(format!("Could not check the host key of {}", host))),
+    }
+}
+
+/// Tries the SSH agent, then the default unencrypted key files.
+fn authenticate(session: &Session, user: &str) -> io::Result<()> {
+    if session.userauth_agent(user).is_ok() && session.authenticated() {
+        return Ok(());
+    }
+    if let Some(dir) = ssh_dir() {
+        for name in ["id_ed25519", "id_ecdsa", "id_rsa"] {
+            let key = dir.join(name);
+            if key.is_file() && session.userauth_pubkey_file(user, None, &key, None).is_ok() && session.authenticated() {
+                return Ok(());
+            }
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("No SSH agent identity or key in ~/.ssh was accepted for {}", user)))
+}
+
+/// Maps SFTP status codes to error kinds. The `ssh2` conversion only knows
+/// "not found", and a refusal mistaken for a broken connection would make
+/// the browser reconnect.
+fn io_error(err: ssh2::Error) -> io::Error {
+    let kind = match err.code() {
+        // SSH_FX_* codes from the SFTP draft
+        ErrorCode::SFTP(2 | 10) => io::ErrorKind::NotFound,
+        ErrorCode::SFTP(3 | 12) => io::ErrorKind::PermissionDenied,
+        ErrorCode::SFTP(11) => io::ErrorKind::AlreadyExists,
+        ErrorCode::SFTP(18) => io::ErrorKind::DirectoryNotEmpty,
+        ErrorCode::SFTP(19) => io::ErrorKind::NotADirectory,
+        // LIBSSH2_ERROR_TIMEOUT
+        ErrorCode::Session(-9) => io::ErrorKind::TimedOut,
+        _ => io::ErrorKind::Other,
+    };
+    io::Error::new(kind, err.message().to_string())
+}
+
+fn metadata(stat: &FileStat) -> Metadata {
+    Metadata {
+        is_dir: stat.is_dir(),
+        len: if stat.is_dir() { 0 } else { stat.size.unwrap_or(0) },
+        modified: stat.mtime.map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+    }
+}
+
+impl Vfs for SftpFs {
+    fn list(&self, dir: &Path) -> io::Result<Vec<VfsEntry>> {
+        let mut entries = Vec::new();
+        for (path, stat) in self.sftp.readdir(dir).map_err(io_error)? {
+            let Some(name) = path.file_name().map(OsString::from) else { continue };
+            // Listings describe links themselves; show what they point to
+            let stat = if stat.file_type().is_symlink() { self.sftp.stat(&path).ok() } else { Some(stat) };
+            entries.push(VfsEntry { name, metadata: stat.as_ref().map(metadata) });
+        }
+        Ok(entries)
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<Metadata> {
+        self.sftp.stat(path).map(|stat| metadata(&stat)).map_err(io_error)
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(self.sftp.open(path).map_err(io_error)?))
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        Ok(Box::new(self.sftp.create(path).map_err(io_error)?))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.sftp.rename(from, to, None).map_err(io_error)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        if self.sftp.lstat(path).map_err(io_error)?.is_dir() {
+            self.sftp.rmdir(path).map_err(io_error)
+        } else {
+            self.sftp.unlink(path).map_err(io_error)
+        }
+    }
+
+    fn mkdir(&self, path: &Path) -> io::Result<()> {
+        self.sftp.mkdir(path, 0o755).map_err(io_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs;
+    use std::net::TcpListener;
+
+    #[test]
+    fn sftp_statuses_map_to_error_kinds() {
+        let kind = |code| io_error(ssh2::Error::new(code, "refused")).kind();
+        assert_eq!(kind(ErrorCode::SFTP(2)), io::ErrorKind::NotFound);
+        assert_eq!(kind(ErrorCode::SFTP(3)), io::ErrorKind::PermissionDenied);
+        assert_eq!(kind(ErrorCode::SFTP(11)), io::ErrorKind::AlreadyExists);
+        assert_eq!(kind(ErrorCode::SFTP(18)), io::ErrorKind::DirectoryNotEmpty);
+        assert_eq!(kind(ErrorCode::Session(-9)), io::ErrorKind::TimedOut);
+        assert_eq!(kind(ErrorCode::SFTP(4)), io::ErrorKind::Other);
+        assert_eq!(io_error(ssh2::Error::new(ErrorCode::SFTP(3), "refused")).to_string(), "refused");
+    }
+
+    #[test]
+    fn folders_have_no_size_and_times_are_seconds() {
+        let stat = FileStat { size: Some(4096), uid: None, gid: None, perm: Some(0o040755), atime: None, mtime: Some(60) };
+        let meta = metadata(&stat);
+        assert!(meta.is_dir);
+        assert_eq!(meta.len, 0);
+        assert_eq!(meta.modified, Some(SystemTime::UNIX_EPOCH + Duration::from_secs(60)));
+        assert_eq!(metadata(&FileStat { perm: Some(0o100644), ..stat }).len, 4096);
+    }
+
+    #[test]
+    fn connecting_to_something_that_is_not_ssh_fails() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // Hang up after a greeting that isn't an SSH banner
+            let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n");
+        });
+        let url = RemoteUrl::parse(&format!("sftp://me@127.0.0.1:{}/", port)).unwrap();
+        assert!(SftpFs::connect(&url).is_err());
+        server.join().unwrap();
+    }
+
+    /// Runs against a real server, e.g. an `sshd` on loopback whose host key
+    /// is in `known_hosts`: `DATARA_SFTP_TEST_URL=sftp://me@127.0.0.1:2222/tmp`.
+    #[test]
+    #[ignore = "needs an SSH server; set DATARA_SFTP_TEST_URL"]
+    fn round_trip_against_a_server() {
+        let url = RemoteUrl::parse(&std::env::var("DATARA_SFTP_TEST_URL").expect("DATARA_SFTP_TEST_URL")).unwrap();
+        let fs = SftpFs::connect(&url).unwrap();
+        let dir = url.path.join(format!("datara-test-{}", std::process::id()));
+        fs.mkdir(&dir).unwrap();
+        fs.open_write(&dir.join("a.txt")).unwrap().write_all(b"hello").unwrap();
+        fs.rename(&dir.join("a.txt"), &dir.join("b.txt")).unwrap();
+
+        let entries = fs.list(&dir).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "b.txt");
+        assert_eq!(entries[0].metadata.map(|meta| meta.len), Some(5));
+        assert_eq!(fs.read_head(&dir.join("b.txt"), 4).unwrap(), b"hell");
+        let local = vfs::local_copy(&fs, &dir.join("b.txt"), &()).unwrap();
+        assert_eq!(std::fs::read(local).unwrap(), b"hello");
+
+        fs.remove(&dir.join("b.txt")).unwrap();
+        fs.remove(&dir).unwrap();
+        assert_eq!(fs.stat(&dir).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,300 @@
+use eframe::egui;
+use std::collections::HashMap;
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+const WORKER_COUNT: usize = 3;
+
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Freedesktop thumbnail flavours, see the Thumbnail Managing Standard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThumbnailSize {
+    Normal,
+    Large,
+}
+
+impl ThumbnailSize {
+    pub fn for_pixels(px: f32) -> Self {
+        if px <= 128.0 { ThumbnailSize::Normal } else { ThumbnailSize::Large }
+    }
+
+    fn pixels(self) -> u32 {
+        match self {
+            ThumbnailSize::Normal => 128,
+            ThumbnailSize::Large => 256,
+        }
+    }
+
+    fn dir_name(self) -> &'static str {
+        match self {
+            ThumbnailSize::Normal => "normal",
+            ThumbnailSize::Large => "large",
+        }
+    }
+}
+
+pub fn is_thumbnailable(mime: &str) -> bool {
+    matches!(mime, "image/png" | "image/jpeg" | "image/gif" | "image/bmp" | "image/webp" | "image/tiff")
+}
+
+enum ThumbnailState {
+    Pending,
+    Ready(egui::TextureHandle),
+    Failed,
+}
+
+struct Job {
+    generation: u64,
+    path: PathBuf,
+    size: ThumbnailSize,
+}
+
+struct Done {
+    generation: u64,
+    path: PathBuf,
+    image: Option<egui::ColorImage>,
+}
+
+/// Generates thumbnails on a small worker pool and keeps the decoded textures
+/// for the current directory. Results are persisted in `~/.cache/thumbnails`.
+pub struct ThumbnailCache {
+    states: HashMap<PathBuf, ThumbnailState>,
+    /// Bumped by `clear`; workers skip jobs from older generations, so
+    /// leaving a big folder doesn't keep them busy with its images.
+    generation: Arc<AtomicU64>,
+    jobs: Sender<Job>,
+    done: Receiver<Done>,
+}
+
+impl ThumbnailCache {
+    pub fn new(ctx: &egui::Context) -> Self {
+        let (job_tx, job_rx) = std::sync::mpsc::channel::<Job>();
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<Done>();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let generation = Arc::new(AtomicU64::new(0));
+        for _ in 0..WORKER_COUNT {
+            let job_rx = Arc::clone(&job_rx);
+            let current = Arc::clone(&generation);
+            let done_tx = done_tx.clone();
+            let ctx = ctx.clone();
+            std::thread::spawn(move || {
+                loop {
+                    let job = match job_rx.lock() {
+                        Ok(rx) => match rx.recv() {
+                            Ok(job) => job,
+                            Err(_) => return,
+                        },
+                        Err(_) => return,
+                    };
+                    if job.generation != current.load(Ordering::Relaxed) {
+                        continue;
+                    }
+                    let image = load_or_generate(&job.path, job.size);
+                    if done_tx.send(Done { generation: job.generation, path: job.path, image }).is_err() {
+                        return;
+                    }
+                    ctx.request_repaint();
+                }
+            });
+        }
+        Self { states: HashMap::new(), generation, jobs: job_tx, done: done_rx }
+    }
+
+    /// Drops every texture and the queued jobs, e.g. after switching directories.
+    pub fn clear(&mut self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        self.states.clear();
+    }
+
+    /// Uploads finished thumbnails; call once per frame before drawing.
+    pub fn poll(&mut self, ctx: &egui::Context) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        while let Ok(done) = self.done.try_recv() {
+            // Ignore results for entries that were cleared in the meantime
+            if done.generation != generation || !matches!(self.states.get(&done.path), Some(ThumbnailState::Pending)) {
+                continue;
+            }
+            let state = match done.image {
+                Some(image) => {
+                    let name = format!("thumb:{}", done.path.to_string_lossy());
+                    ThumbnailState::Ready(ctx.load_texture(name, image, egui::TextureOptions::LINEAR))
+                }
+                None => ThumbnailState::Failed,
+            };
+            self.states.insert(done.path, state);
+        }
+    }
+
+    /// Returns the thumbnail if it is ready, queueing it for generation otherwise.
+    pub fn get(&mut self, path: &Path, size: ThumbnailSize) -> Option<&egui::TextureHandle> {
+        if !self.states.contains_key(path) {
+            self.states.insert(path.to_path_buf(), ThumbnailState::Pending);
+            let generation = self.generation.load(Ordering::Relaxed);
+            let _ = self.jobs.send(Job { generation, path: path.to_path_buf(), size });
+        }
+        match self.states.get(path) {
+            Some(ThumbnailState::Ready(texture)) => Some(texture),
+            _ => None,
+        }
+    }
+}
+
+fn cache_root() -> Option<PathBuf> {
+    match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("thumbnails")),
+        _ => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache").join("thumbnails")),
+    }
+}
+
+/// `file://` URI of an absolute path, percent-encoding everything but unreserved characters and `/`.
+pub fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for &b in path.as_os_str().as_bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'/' | b'-' | b'_' | b'.' | b'~') {
+            uri.push(b as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", b));
+        }
+    }
+    uri
+}
+
+fn thumbnail_path(uri: &str, size: ThumbnailSize) -> Option<PathBuf> {
+    let digest = md5::compute(uri.as_bytes());
+    Some(cache_root()?.join(size.dir_name()).join(format!("{:x}.png", digest)))
+}
+
+fn load_or_generate(path: &Path, size: ThumbnailSize) -> Option<egui::ColorImage> {
+    let path = std::fs::canonicalize(path).ok()?;
+    let mtime = std::fs::metadata(&path)
+        .ok()?
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    let uri = file_uri(&path);
+    let thumb_path = thumbnail_path(&uri, size);
+
+    if let Some(thumb_path) = &thumb_path
+        && let Some(image) = load_cached(thumb_path, &uri, mtime) {
+        return Some(image);
+    }
+
+    let image = image::open(&path).ok()?;
+    let thumb = image.thumbnail(size.pixels(), size.pixels()).to_rgba8();
+    if let Some(thumb_path) = &thumb_path {
+        let _ = save_cached(thumb_path, &thumb, &uri, mtime);
+    }
+    let size = [thumb.width() as usize, thumb.height() as usize];
+    Some(egui::ColorImage::from_rgba_unmultiplied(size, thumb.as_raw()))
+}
+
+/// Reads a cached thumbnail, rejecting it when `Thumb::URI` or `Thumb::MTime` don't match.
+fn load_cached(thumb_path: &Path, uri: &str, mtime: u64) -> Option<egui::ColorImage> {
+    let bytes = std::fs::read(thumb_path).ok()?;
+    let reader = png::Decoder::new(bytes.as_slice()).read_info().ok()?;
+    let info = reader.info();
+    let text = |key: &str| {
+        info.uncompressed_latin1_text
+            .iter()
+            .find(|chunk| chunk.keyword == key)
+            .map(|chunk| chunk.text.clone())
+            .or_else(|| info.utf8_text.iter().find(|chunk| chunk.keyword == key).and_then(|chunk| chunk.get_text().ok()))
+    };
+    if text("Thumb::URI").as_deref() != Some(uri) {
+        return None;
+    }
+    if text("Thumb::MTime").and_then(|t| t.parse::<u64>().ok()) != Some(mtime) {
+        return None;
+    }
+    let image = image::load_from_memory(&bytes).ok()?.to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    Some(egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw()))
+}
+
+fn save_cached(thumb_path: &Path, thumb: &image::RgbaImage, uri: &str, mtime: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = thumb_path.parent().ok_or("thumbnail path has no parent")?;
+    std::fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+
+    let mut encoded = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut encoded, thumb.width(), thumb.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.add_text_chunk("Thumb::URI".to_string(), uri.to_string())?;
+        encoder.add_text_chunk("Thumb::MTime".to_string(), mtime.to_string())?;
+        encoder.add_text_chunk("Software".to_string(), "Datara".to_string())?;
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(thumb.as_raw())?;
+    }
+
+    // Write to a unique temporary file and rename so readers never see partial thumbnails
+    let tmp_path = dir.join(format!(".datara-{}-{}.png", std::process::id(), TMP_COUNTER.fetch_add(1, Ordering::Relaxed)));
+    let mut file = std::fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(&tmp_path)?;
+    let written = file.write_all(&encoded).and_then(|()| {
+        drop(file);
+        std::fs::rename(&tmp_path, thumb_path)
+    });
+    if written.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    Ok(written?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("datara-thumbnails-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn file_uris_escape_reserved_and_non_utf8_bytes() {
+        assert_eq!(file_uri(Path::new("/home/me/a-b_c.~1.png")), "file:///home/me/a-b_c.~1.png");
+        assert_eq!(file_uri(Path::new("/tmp/my photo#1%.png")), "file:///tmp/my%20photo%231%25.png");
+        assert_eq!(file_uri(Path::new("/tmp/é?.png")), "file:///tmp/%C3%A9%3F.png");
+        let raw = std::ffi::OsStr::from_bytes(b"/tmp/\xff.png");
+        assert_eq!(file_uri(Path::new(raw)), "file:///tmp/%FF.png");
+    }
+
+    #[test]
+    fn cached_thumbnails_must_match_uri_and_mtime() {
+        let dir = scratch("cache");
+        let thumb_path = dir.join("normal").join("thumb.png");
+        let thumb = image::RgbaImage::from_pixel(4, 2, image::Rgba([0, 255, 0, 255]));
+        let uri = "file:///tmp/picture.png";
+        save_cached(&thumb_path, &thumb, uri, 1_700_000_000).unwrap();
+
+        let image = load_cached(&thumb_path, uri, 1_700_000_000).unwrap();
+        assert_eq!(image.size, [4, 2]);
+        assert!(load_cached(&thumb_path, "file:///tmp/other.png", 1_700_000_000).is_none());
+        assert!(load_cached(&thumb_path, uri, 1_700_000_001).is_none());
+        assert!(load_cached(&dir.join("missing.png"), uri, 1_700_000_000).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failed_saves_leave_no_temporary_file() {
+        let dir = scratch("failed");
+        // A non-empty directory where the thumbnail should go makes the rename fail
+        let thumb_path = dir.join("thumb.png");
+        std::fs::create_dir_all(thumb_path.join("occupied")).unwrap();
+        let thumb = image::RgbaImage::new(2, 2);
+        assert!(save_cached(&thumb_path, &thumb, "file:///tmp/x.png", 1).is_err());
+        let names: Vec<_> = std::fs::read_dir(&dir).unwrap().flatten().map(|entry| entry.file_name()).collect();
+        assert_eq!(names, ["thumb.png"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
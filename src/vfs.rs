@@ -0,0 +1,491 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io::{self, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metadata {
+    pub is_dir: bool,
+    /// Size in bytes; 0 for directories.
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VfsEntry {
+    pub name: OsString,
+    /// `None` when the entry vanished or can't be inspected while listing.
+    pub metadata: Option<Metadata>,
+}
+
+/// A filesystem the browser can show. Paths are absolute within the backend;
+/// `list` returns names only, in no particular order.
+pub trait Vfs: Send + Sync {
+    fn list(&self, dir: &Path) -> io::Result<Vec<VfsEntry>>;
+    fn stat(&self, path: &Path) -> io::Result<Metadata>;
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>>;
+    /// Creates or truncates the file. Some backends only send the data when
+    /// the writer is flushed, so flush it to learn whether writing worked.
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>>;
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    /// Removes a file or an empty directory.
+    fn remove(&self, path: &Path) -> io::Result<()>;
+    /// Creates one directory; the parent must exist.
+    fn mkdir(&self, path: &Path) -> io::Result<()>;
+
+    /// The path other programs can open `path` by, if it is a real file.
+    fn local_path(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    /// Where `local_copy` keeps a download of `path` for reuse, for backends
+    /// that cache remote files. The path should change with the contents.
+    fn cache_path(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    /// Reads at most `limit` bytes from the start of the file.
+    fn read_head(&self, path: &Path, limit: u64) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.open_read(path)?.take(limit).read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+/// The real filesystem.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalFs;
+
+impl From<std::fs::Metadata> for Metadata {
+    fn from(meta: std::fs::Metadata) -> Self {
+        Self { is_dir: meta.is_dir(), len: if meta.is_dir() { 0 } else { meta.len() }, modified: meta.modified().ok() }
+    }
+}
+
+impl Vfs for LocalFs {
+    fn list(&self, dir: &Path) -> io::Result<Vec<VfsEntry>> {
+        let entries = std::fs::read_dir(dir)?
+            .flatten()
+            .map(|entry| VfsEntry { name: entry.file_name(), metadata: entry.metadata().ok().map(Metadata::from) })
+            .collect();
+        Ok(entries)
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<Metadata> {
+        std::fs::metadata(path).map(Metadata::from)
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(std::fs::File::open(path)?))
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        Ok(Box::new(std::fs::File::create(path)?))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::rename(from, to)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        if std::fs::symlink_metadata(path)?.is_dir() {
+            std::fs::remove_dir(path)
+        } else {
+            std::fs::remove_file(path)
+        }
+    }
+
+    fn mkdir(&self, path: &Path) -> io::Result<()> {
+        std::fs::create_dir(path)
+    }
+
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(path.to_path_buf())
+    }
+}
+
+/// Receives progress from long copies, like `copy_tree` and the archive
+/// operations. An error from `advance`,
+/// e.g. because the user cancelled, stops the operation.
+pub trait Progress {
+    fn start(&self, total_bytes: u64);
+    fn advance(&self, name: &Path, bytes: u64) -> io::Result<()>;
+}
+
+/// Ignores progress.
+impl Progress for () {
+    fn start(&self, _total_bytes: u64) {}
+
+    fn advance(&self, _name: &Path, _bytes: u64) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// `io::copy` reporting each chunk, then flushing; stops when `progress`
+/// returns an error.
+pub fn copy_with_progress(reader: &mut dyn Read, writer: &mut dyn Write, name: &Path, progress: &dyn Progress) -> io::Result<()> {
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let read = match reader.read(&mut buf) {
+            Ok(0) => return writer.flush(),
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        writer.write_all(&buf[..read])?;
+        progress.advance(name, read as u64)?;
+    }
+}
+
+/// A real path for `path`, copying the file to a temporary folder, or the
+/// backend's cache, when it only exists inside `vfs`, so that external
+/// applications can open it. Reports the download through `progress`.
+/// Copies are read-only: changes to them would never reach `vfs`.
+pub fn local_copy(vfs: &dyn Vfs, path: &Path, progress: &dyn Progress) -> io::Result<PathBuf> {
+    static COPIES: AtomicUsize = AtomicUsize::new(0);
+    if let Some(local) = vfs.local_path(path) {
+        return Ok(local);
+    }
+    let name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+    let len = vfs.stat(path)?.len;
+    let download = |to: &Path| -> io::Result<()> {
+        progress.start(len);
+        let mut file = std::fs::File::create(to)?;
+        copy_with_progress(&mut vfs.open_read(path)?, &mut file, path, progress)?;
+        file.set_permissions(std::fs::Permissions::from_mode(0o444))
+    };
+    if let Some(cached) = vfs.cache_path(path) {
+        // A copy of another size was made writable and changed since
+        if !cached.metadata().is_ok_and(|meta| meta.is_file() && meta.len() == len) {
+            // Renamed into place once complete, so a failed download isn't reused
+            let mut partial = OsString::from(".");
+            partial.push(name);
+            partial.push(".part");
+            let partial = cached.with_file_name(partial);
+            std::fs::create_dir_all(cached.parent().unwrap_or(Path::new("/")))?;
+            // Left read-only by a download whose rename failed
+            let _ = std::fs::remove_file(&partial);
+            download(&partial)?;
+            std::fs::rename(&partial, &cached)?;
+        }
+        return Ok(cached);
+    }
+    // One folder per copy keeps equal names from different sources apart
+    let dir = std::env::temp_dir().join(format!("datara-{}", std::process::id())).join(COPIES.fetch_add(1, Ordering::Relaxed).to_string());
+    std::fs::create_dir_all(&dir)?;
+    let local = dir.join(name);
+    download(&local)?;
+    Ok(local)
+}
+
+/// Copies a file or a whole folder between filesystems. Refuses to replace
+/// an existing `to`; returns the number of files copied.
+pub fn copy_tree(from_vfs: &dyn Vfs, from: &Path, to_vfs: &dyn Vfs, to: &Path, progress: &dyn Progress) -> io::Result<u64> {
+    if to_vfs.stat(to).is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", to.display())));
+    }
+    if !from_vfs.stat(from)?.is_dir {
+        copy_with_progress(&mut *from_vfs.open_read(from)?, &mut *to_vfs.open_write(to)?, from, progress)?;
+        return Ok(1);
+    }
+    to_vfs.mkdir(to)?;
+    let mut copied = 0;
+    for entry in from_vfs.list(from)? {
+        copied += copy_tree(from_vfs, &from.join(&entry.name), to_vfs, &to.join(&entry.name), progress)?;
+    }
+    Ok(copied)
+}
+
+/// Deletes a file or a folder with everything in it; returns the number of
+/// entries removed.
+pub fn remove_tree(vfs: &dyn Vfs, path: &Path, progress: &dyn Progress) -> io::Result<u64> {
+    let mut removed = 0;
+    if vfs.stat(path)?.is_dir {
+        for entry in vfs.list(path)? {
+            removed += remove_tree(vfs, &path.join(&entry.name), progress)?;
+        }
+    }
+    progress.advance(path, 0)?;
+    vfs.remove(path)?;
+    Ok(removed + 1)
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Dir,
+    File(Arc<Vec<u8>>),
+}
+
+type Nodes = BTreeMap<PathBuf, (Node, SystemTime)>;
+
+/// A filesystem held in memory, rooted at `/`. Meant for tests and for
+/// content that only exists inside Datara.
+#[derive(Debug, Clone)]
+pub struct MemoryFs {
+    nodes: Arc<Mutex<Nodes>>,
+}
+
+impl Default for MemoryFs {
+    fn default() -> Self {
+        let mut nodes = Nodes::new();
+        nodes.insert(PathBuf::from("/"), (Node::Dir, SystemTime::UNIX_EPOCH));
+        Self { nodes: Arc::new(Mutex::new(nodes)) }
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path.display()))
+}
+
+impl MemoryFs {
+    /// Adds a file with `contents`, creating missing parent directories.
+    pub fn add_file(&self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) -> &Self {
+        let path = path.as_ref();
+        self.add_dir(path.parent().unwrap_or(Path::new("/")));
+        self.lock().insert(path.to_path_buf(), (Node::File(Arc::new(contents.into())), SystemTime::now()));
+        self
+    }
+
+    /// Adds a directory and its missing parents.
+    pub fn add_dir(&self, path: impl AsRef<Path>) -> &Self {
+        let mut nodes = self.lock();
+        for dir in path.as_ref().ancestors() {
+            nodes.entry(dir.to_path_buf()).or_insert((Node::Dir, SystemTime::now()));
+        }
+        drop(nodes);
+        self
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Nodes> {
+        self.nodes.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn require_dir(nodes: &Nodes, path: &Path) -> io::Result<()> {
+        match nodes.get(path) {
+            Some((Node::Dir, _)) => Ok(()),
+            Some(_) => Err(io::Error::new(io::ErrorKind::NotADirectory, format!("{} is not a directory", path.display()))),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn metadata((node, modified): &(Node, SystemTime)) -> Metadata {
+        match node {
+            Node::Dir => Metadata { is_dir: true, len: 0, modified: Some(*modified) },
+            Node::File(data) => Metadata { is_dir: false, len: data.len() as u64, modified: Some(*modified) },
+        }
+    }
+}
+
+/// Buffers writes and stores the file when dropped, like closing it.
+struct MemoryWriter {
+    fs: MemoryFs,
+    path: PathBuf,
+    data: Vec<u8>,
+}
+
+impl Write for MemoryWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.fs.lock().insert(self.path.clone(), (Node::File(Arc::new(self.data.clone())), SystemTime::now()));
+        Ok(())
+    }
+}
+
+impl Drop for MemoryWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl Vfs for MemoryFs {
+    fn list(&self, dir: &Path) -> io::Result<Vec<VfsEntry>> {
+        let nodes = self.lock();
+        Self::require_dir(&nodes, dir)?;
+        let entries = nodes
+            .iter()
+            .filter(|(path, _)| path.parent() == Some(dir))
+            .map(|(path, node)| VfsEntry { name: path.file_name().unwrap_or_default().to_os_string(), metadata: Some(Self::metadata(node)) })
+            .collect();
+        Ok(entries)
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<Metadata> {
+        self.lock().get(path).map(Self::metadata).ok_or_else(|| not_found(path))
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        match self.lock().get(path) {
+            Some((Node::File(data), _)) => Ok(Box::new(io::Cursor::new(ArcBytes(data.clone())))),
+            Some((Node::Dir, _)) => Err(io::Error::new(io::ErrorKind::IsADirectory, format!("{} is a directory", path.display()))),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        let mut nodes = self.lock();
+        Self::require_dir(&nodes, path.parent().ok_or_else(|| not_found(path))?)?;
+        if let Some((Node::Dir, _)) = nodes.get(path) {
+            return Err(io::Error::new(io::ErrorKind::IsADirectory, format!("{} is a directory", path.display())));
+        }
+        nodes.insert(path.to_path_buf(), (Node::File(Arc::default()), SystemTime::now()));
+        Ok(Box::new(MemoryWriter { fs: self.clone(), path: path.to_path_buf(), data: Vec::new() }))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut nodes = self.lock();
+        if !nodes.contains_key(from) {
+            return Err(not_found(from));
+        }
+        Self::require_dir(&nodes, to.parent().ok_or_else(|| not_found(to))?)?;
+        if to.starts_with(from) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("cannot move {} into itself", from.display())));
+        }
+        // A directory moves together with everything below it
+        let moved: Vec<PathBuf> = nodes.keys().filter(|path| path.starts_with(from)).cloned().collect();
+        for path in moved {
+            let node = nodes.remove(&path).expect("listed above");
+            let suffix = path.strip_prefix(from).expect("filtered by prefix");
+            nodes.insert(if suffix.as_os_str().is_empty() { to.to_path_buf() } else { to.join(suffix) }, node);
+        }
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.lock();
+        if !nodes.contains_key(path) || path == Path::new("/") {
+            return Err(not_found(path));
+        }
+        if nodes.keys().any(|other| other.parent() == Some(path)) {
+            return Err(io::Error::new(io::ErrorKind::DirectoryNotEmpty, format!("{} is not empty", path.display())));
+        }
+        nodes.remove(path);
+        Ok(())
+    }
+
+    fn mkdir(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.lock();
+        if nodes.contains_key(path) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", path.display())));
+        }
+        Self::require_dir(&nodes, path.parent().ok_or_else(|| not_found(path))?)?;
+        nodes.insert(path.to_path_buf(), (Node::Dir, SystemTime::now()));
+        Ok(())
+    }
+}
+
+/// Lets readers share a file's bytes without copying them.
+struct ArcBytes(Arc<Vec<u8>>);
+
+impl AsRef<[u8]> for ArcBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(vfs: &dyn Vfs, dir: &str) -> Vec<String> {
+        let mut names: Vec<String> = vfs.list(Path::new(dir)).unwrap().into_iter().map(|e| e.name.to_string_lossy().to_string()).collect();
+        names.sort();
+        names
+    }
+
+    fn read(vfs: &dyn Vfs, path: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        vfs.open_read(Path::new(path)).unwrap().read_to_end(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn memory_lists_direct_children_only() {
+        let fs = MemoryFs::default();
+        fs.add_file("/docs/a.txt", "alpha").add_file("/docs/deep/b.txt", "beta").add_dir("/empty");
+        assert_eq!(names(&fs, "/"), ["docs", "empty"]);
+        assert_eq!(names(&fs, "/docs"), ["a.txt", "deep"]);
+        let stat = fs.stat(Path::new("/docs/a.txt")).unwrap();
+        assert!(!stat.is_dir);
+        assert_eq!(stat.len, 5);
+        assert!(fs.stat(Path::new("/docs")).unwrap().is_dir);
+        assert_eq!(fs.list(Path::new("/missing")).unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(fs.list(Path::new("/docs/a.txt")).unwrap_err().kind(), io::ErrorKind::NotADirectory);
+    }
+
+    #[test]
+    fn memory_write_read_rename_remove() {
+        let fs = MemoryFs::default();
+        fs.mkdir(Path::new("/dir")).unwrap();
+        assert_eq!(fs.mkdir(Path::new("/dir")).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs.mkdir(Path::new("/no/parent")).unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        let mut file = fs.open_write(Path::new("/dir/new.txt")).unwrap();
+        file.write_all(b"hello ").unwrap();
+        file.write_all(b"world").unwrap();
+        drop(file);
+        assert_eq!(read(&fs, "/dir/new.txt"), b"hello world");
+        assert_eq!(fs.read_head(Path::new("/dir/new.txt"), 5).unwrap(), b"hello");
+
+        fs.rename(Path::new("/dir"), Path::new("/moved")).unwrap();
+        assert_eq!(names(&fs, "/"), ["moved"]);
+        assert_eq!(read(&fs, "/moved/new.txt"), b"hello world");
+
+        assert_eq!(fs.remove(Path::new("/moved")).unwrap_err().kind(), io::ErrorKind::DirectoryNotEmpty);
+        fs.remove(Path::new("/moved/new.txt")).unwrap();
+        fs.remove(Path::new("/moved")).unwrap();
+        assert!(names(&fs, "/").is_empty());
+    }
+
+    #[test]
+    fn trees_copy_between_filesystems_and_remove() {
+        let from = MemoryFs::default();
+        from.add_file("/src/a.txt", "alpha").add_file("/src/deep/b.txt", "beta").add_dir("/src/empty");
+        let to = MemoryFs::default();
+        to.add_dir("/dst");
+        assert_eq!(copy_tree(&from, Path::new("/src"), &to, Path::new("/dst/copy"), &()).unwrap(), 2);
+        assert_eq!(names(&to, "/dst/copy"), ["a.txt", "deep", "empty"]);
+        assert_eq!(to.read_head(Path::new("/dst/copy/deep/b.txt"), 10).unwrap(), b"beta");
+        assert_eq!(copy_tree(&from, Path::new("/src"), &to, Path::new("/dst/copy"), &()).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+
+        assert_eq!(remove_tree(&to, Path::new("/dst/copy"), &()).unwrap(), 5);
+        assert_eq!(names(&to, "/dst"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn local_copies_are_read_only() {
+        let fs = MemoryFs::default();
+        fs.add_file("/docs/notes.txt", "draft");
+        let local = local_copy(&fs, Path::new("/docs/notes.txt"), &()).unwrap();
+        assert_eq!(local.file_name(), Some(std::ffi::OsStr::new("notes.txt")));
+        assert_eq!(std::fs::read(&local).unwrap(), b"draft");
+        assert!(std::fs::metadata(&local).unwrap().permissions().readonly());
+    }
+
+    #[test]
+    fn local_round_trip() {
+        let root = std::env::temp_dir().join(format!("datara-vfs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        let fs = LocalFs;
+        fs.mkdir(&root.join("sub")).unwrap();
+        fs.open_write(&root.join("sub/f")).unwrap().write_all(b"data").unwrap();
+        fs.rename(&root.join("sub/f"), &root.join("g")).unwrap();
+        let mut listed: Vec<(String, bool)> = fs.list(&root).unwrap().into_iter().map(|e| (e.name.to_string_lossy().to_string(), e.metadata.unwrap().is_dir)).collect();
+        listed.sort();
+        assert_eq!(listed, [("g".to_string(), false), ("sub".to_string(), true)]);
+        assert_eq!(fs.stat(&root.join("g")).unwrap().len, 4);
+        fs.remove(&root.join("g")).unwrap();
+        fs.remove(&root.join("sub")).unwrap();
+        assert!(fs.list(&root).unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}